@@ -6,6 +6,12 @@ use wasm_bindgen::prelude::*;
 pub mod op;
 
 const STACK_START: u16 = 0x0100;
+const NMI_VECTOR: u16 = 0xFFFA;
+const RESET_VECTOR: u16 = 0xFFFC;
+const IRQ_VECTOR: u16 = 0xFFFE;
+
+/// Cycles taken to push the state and jump through an interrupt vector.
+pub const INTERRUPT_CYCLES: u8 = 7;
 
 /// Registers
 #[derive(Default, Debug, PartialEq, Eq, Clone, Copy)]
@@ -49,6 +55,11 @@ pub trait Bus {
 
     fn write_u8(&mut self, address: u16, data: u8);
 
+    /// Reads without side effects, for inspecting memory.
+    fn peek_u8(&mut self, address: u16) -> u8 {
+        self.read_u8(address)
+    }
+
     /// [AB, CD] -> 0xABCD
     fn read_u16_be(&mut self, address: u16) -> u16 {
         let x = self.read_u8(address);
@@ -137,6 +148,25 @@ impl Cpu {
         self.regs
     }
 
+    /// Resets registers and jumps through the reset vector.
+    pub fn reset(&mut self, bus: &mut impl Bus) {
+        *self = Self::default();
+        self.regs.pc = bus.read_u16_le(RESET_VECTOR);
+    }
+
+    /// Services a maskable interrupt. Returns false if interrupts are disabled.
+    pub fn irq(&mut self, bus: &mut impl Bus) -> bool {
+        if self.regs.flags.contains(Flags::INTERRUPT_DISABLE) {
+            return false;
+        }
+        interrupt(IRQ_VECTOR, &mut self.regs, bus);
+        true
+    }
+
+    pub fn nmi(&mut self, bus: &mut impl Bus) {
+        interrupt(NMI_VECTOR, &mut self.regs, bus);
+    }
+
     pub fn tick(&mut self, bus: &mut impl Bus) -> Effects {
         let opcode = self.read_opcode(bus);
         let op = Op::decode(opcode).unwrap_or_else(|| panic!("Unsupported opcode: {opcode:x}"));
@@ -299,7 +329,7 @@ impl Cpu {
             }
         }
         .map(|(raw, indirect, effective)| Operand {
-            value: bus.read_u8(effective),
+            value: bus.peek_u8(effective),
             effective_address: effective,
             indirect_address: indirect,
             raw_address: raw,
@@ -342,6 +372,7 @@ fn asl_a(regs: &mut Regs) {
 
 fn asl(address: u16, regs: &mut Regs, bus: &mut impl Bus) {
     let m = bus.read_u8(address);
+    dummy_write(address, m, bus);
     regs.flags.set(Flags::CARRY, (m >> 7) == 1);
 
     let m = m << 1;
@@ -460,6 +491,7 @@ fn cpy(address: u16, regs: &mut Regs, bus: &mut impl Bus) {
 
 fn dec(address: u16, regs: &mut Regs, bus: &mut impl Bus) {
     let m = bus.read_u8(address);
+    dummy_write(address, m, bus);
     let m = m.wrapping_sub(1);
     regs.flags.set(Flags::ZERO, is_zero(m));
     regs.flags.set(Flags::NEGATIVE, is_negative(m));
@@ -487,6 +519,7 @@ fn eor(address: u16, regs: &mut Regs, bus: &mut impl Bus) {
 
 fn inc(address: u16, regs: &mut Regs, bus: &mut impl Bus) {
     let m = bus.read_u8(address);
+    dummy_write(address, m, bus);
     let m = m.wrapping_add(1);
     regs.flags.set(Flags::ZERO, is_zero(m));
     regs.flags.set(Flags::NEGATIVE, is_negative(m));
@@ -546,6 +579,7 @@ fn lsr_a(regs: &mut Regs) {
 
 fn lsr(address: u16, regs: &mut Regs, bus: &mut impl Bus) {
     let m = bus.read_u8(address);
+    dummy_write(address, m, bus);
     regs.flags.set(Flags::CARRY, m & (1 << 0) != 0);
 
     let m = m >> 1;
@@ -598,6 +632,7 @@ fn rol_a(regs: &mut Regs) {
 
 fn rol(address: u16, regs: &mut Regs, bus: &mut impl Bus) {
     let m = bus.read_u8(address);
+    dummy_write(address, m, bus);
     let new_carry = m & (1 << 7) != 0;
     let result = (m << 1) | regs.flags.contains(Flags::CARRY) as u8;
     regs.flags.set(Flags::CARRY, new_carry);
@@ -616,6 +651,7 @@ fn ror_a(regs: &mut Regs) {
 
 fn ror(address: u16, regs: &mut Regs, bus: &mut impl Bus) {
     let m = bus.read_u8(address);
+    dummy_write(address, m, bus);
     let new_carry = m & (1 << 0) != 0;
     let result = (m >> 1) | ((regs.flags.contains(Flags::CARRY) as u8) << 7);
     regs.flags.set(Flags::CARRY, new_carry);
@@ -716,6 +752,24 @@ fn tya(regs: &mut Regs) {
     regs.flags.set(Flags::NEGATIVE, is_negative(regs.a));
 }
 
+/// Read-modify-write instructions write the unmodified value back before the result.
+fn dummy_write(address: u16, m: u8, bus: &mut impl Bus) {
+    bus.write_u8(address, m);
+}
+
+fn interrupt(vector: u16, regs: &mut Regs, bus: &mut impl Bus) {
+    bus.write_u16_le(
+        STACK_START.wrapping_add(regs.sp.wrapping_sub(1).into()),
+        regs.pc,
+    );
+    regs.sp = regs.sp.wrapping_sub(2);
+    let flags = regs.flags.difference(Flags::BREAK_1).union(Flags::BREAK_2);
+    bus.write_u8(STACK_START.wrapping_add(regs.sp.into()), flags.bits());
+    regs.sp = regs.sp.wrapping_sub(1);
+    regs.flags.insert(Flags::INTERRUPT_DISABLE);
+    regs.pc = bus.read_u16_le(vector);
+}
+
 fn is_zero(n: u8) -> bool {
     n == 0x00
 }
@@ -1,4 +1,4 @@
-use membranes_cpu::{Cpu, Flags, Regs};
+use membranes_cpu::{Bus, Cpu, Flags, Regs};
 use proptest::prelude::*;
 use test_strategy::proptest;
 
@@ -58,3 +58,32 @@ fn negative(regs: Regs) {
     );
     prop_assert_eq!(bus[0x0002], 0b1000_0001);
 }
+
+#[test]
+fn dummy_write() {
+    struct RecordingBus {
+        memory: [u8; 3],
+        writes: Vec<u8>,
+    }
+
+    impl Bus for RecordingBus {
+        fn read_u8(&mut self, address: u16) -> u8 {
+            self.memory[usize::from(address)]
+        }
+
+        fn write_u8(&mut self, address: u16, data: u8) {
+            self.writes.push(data);
+            self.memory[usize::from(address)] = data;
+        }
+    }
+
+    let mut cpu = Cpu::from_regs(Regs::default());
+    let mut bus = RecordingBus {
+        memory: [0xE6, 0x02, 0x41],
+        writes: Vec::new(),
+    };
+
+    cpu.tick(&mut bus);
+
+    assert_eq!(bus.writes, [0x41, 0x42]);
+}
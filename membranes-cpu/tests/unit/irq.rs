@@ -0,0 +1,52 @@
+use membranes_cpu::{Cpu, Flags, Regs};
+use proptest::prelude::*;
+use test_strategy::proptest;
+
+#[proptest]
+fn taken(regs: Regs) {
+    let regs = Regs {
+        pc: 0x1234,
+        sp: 0xFF,
+        flags: regs.flags.difference(Flags::INTERRUPT_DISABLE),
+        ..regs
+    };
+    let mut cpu = Cpu::from_regs(regs);
+    let mut bus = vec![0x00; 0x10000];
+    bus[0xFFFE] = 0xEF;
+    bus[0xFFFF] = 0xBE;
+
+    prop_assert!(cpu.irq(&mut bus));
+
+    prop_assert_eq!(
+        cpu.regs(),
+        Regs {
+            pc: 0xBEEF,
+            sp: 0xFC,
+            flags: regs.flags.union(Flags::INTERRUPT_DISABLE),
+            ..regs
+        }
+    );
+    prop_assert_eq!(bus[0x01FF], 0x12);
+    prop_assert_eq!(bus[0x01FE], 0x34);
+    prop_assert_eq!(
+        bus[0x01FD],
+        regs.flags
+            .difference(Flags::BREAK_1)
+            .union(Flags::BREAK_2)
+            .bits()
+    );
+}
+
+#[proptest]
+fn masked(regs: Regs) {
+    let regs = Regs {
+        flags: regs.flags.union(Flags::INTERRUPT_DISABLE),
+        ..regs
+    };
+    let mut cpu = Cpu::from_regs(regs);
+    let mut bus = vec![0x00; 0x10000];
+
+    prop_assert!(!cpu.irq(&mut bus));
+
+    prop_assert_eq!(cpu.regs(), regs);
+}
@@ -1,5 +1,5 @@
 use crate::strategy::*;
-use membranes_cpu::{Cpu, Flags, Regs};
+use membranes_cpu::{Bus, Cpu, Flags, Regs};
 use proptest::prelude::*;
 use test_strategy::proptest;
 
@@ -59,3 +59,37 @@ fn negative(regs: Regs, #[strategy(negative_byte())] operand: u8) {
         }
     );
 }
+
+/// Reading a register can have side effects, so the operand is read only once.
+#[test]
+fn single_read() {
+    struct CountingBus {
+        memory: [u8; 3],
+        reads: usize,
+    }
+
+    impl Bus for CountingBus {
+        fn read_u8(&mut self, address: u16) -> u8 {
+            if address == 0x4030 {
+                self.reads += 1;
+            }
+            self.memory.get(usize::from(address)).copied().unwrap_or(0)
+        }
+
+        fn write_u8(&mut self, _address: u16, _data: u8) {}
+
+        fn peek_u8(&mut self, _address: u16) -> u8 {
+            0x00
+        }
+    }
+
+    let mut cpu = Cpu::from_regs(Regs::default());
+    let mut bus = CountingBus {
+        memory: [0xAD, 0x30, 0x40],
+        reads: 0,
+    };
+
+    cpu.tick(&mut bus);
+
+    assert_eq!(bus.reads, 1);
+}
@@ -16,12 +16,14 @@ mod dey;
 mod inc;
 mod inx;
 mod iny;
+mod irq;
 mod jmp;
 mod jsr;
 mod lda;
 mod ldx;
 mod ldy;
 mod lsr;
+mod nmi;
 mod rts;
 mod sbc;
 mod txa;
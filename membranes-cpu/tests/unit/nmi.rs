@@ -0,0 +1,30 @@
+use membranes_cpu::{Cpu, Flags, Regs};
+use proptest::prelude::*;
+use test_strategy::proptest;
+
+#[proptest]
+fn test(regs: Regs) {
+    let regs = Regs {
+        pc: 0x1234,
+        sp: 0xFF,
+        ..regs
+    };
+    let mut cpu = Cpu::from_regs(regs);
+    let mut bus = vec![0x00; 0x10000];
+    bus[0xFFFA] = 0xEF;
+    bus[0xFFFB] = 0xBE;
+
+    cpu.nmi(&mut bus);
+
+    prop_assert_eq!(
+        cpu.regs(),
+        Regs {
+            pc: 0xBEEF,
+            sp: 0xFC,
+            flags: regs.flags.union(Flags::INTERRUPT_DISABLE),
+            ..regs
+        }
+    );
+    prop_assert_eq!(bus[0x01FF], 0x12);
+    prop_assert_eq!(bus[0x01FE], 0x34);
+}
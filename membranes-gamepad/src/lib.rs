@@ -19,8 +19,10 @@ bitflags! {
 #[derive(Default, Clone, Copy)]
 #[wasm_bindgen]
 pub struct Gamepad {
-    _is_strobe: bool,
+    is_strobe: bool,
     button_status_flags: ButtonFlags,
+    /// Buttons left to read, A first.
+    shift: u8,
 }
 
 impl Gamepad {
@@ -36,12 +38,22 @@ impl Gamepad {
         self.button_status_flags.remove(button_flags)
     }
 
+    /// Next button, in the order A, B, Select, Start, Up, Down, Left, Right,
+    /// then 1s. While strobing it keeps returning A.
     pub fn read_u8(&mut self) -> u8 {
-        // TODO
-        0
+        if self.is_strobe {
+            return self.button_status_flags.contains(ButtonFlags::A).into();
+        }
+        let bit = self.shift & 1;
+        self.shift = self.shift >> 1 | 0x80;
+        bit
     }
 
-    pub fn write_u8(&mut self, _data: u8) {
-        // TODO
+    /// Bit 0 strobes, reloading the buttons until it is cleared.
+    pub fn write_u8(&mut self, data: u8) {
+        self.is_strobe = data & 1 != 0;
+        if self.is_strobe {
+            self.shift = self.button_status_flags.bits();
+        }
     }
 }
@@ -0,0 +1,126 @@
+//! Background fetches and shift registers.
+
+use crate::{Bus, Ctrl, Ppu};
+
+/// Tile data fetched over 8 dots, then fed through 16-bit shift registers
+/// so the next tile is already loaded when the current one runs out.
+#[derive(Default)]
+pub(crate) struct Background {
+    tile: u8,
+    attribute: u8,
+    pattern_low: u8,
+    pattern_high: u8,
+    shift_pattern_low: u16,
+    shift_pattern_high: u16,
+    shift_attribute_low: u16,
+    shift_attribute_high: u16,
+}
+
+impl Background {
+    pub(crate) fn shift(&mut self) {
+        self.shift_pattern_low <<= 1;
+        self.shift_pattern_high <<= 1;
+        self.shift_attribute_low <<= 1;
+        self.shift_attribute_high <<= 1;
+    }
+
+    /// Palette entry (0-15) under fine X, 0 for transparent.
+    pub(crate) fn pixel(&self, x: u8) -> u8 {
+        let bit = 0x8000 >> x;
+        let pattern = u8::from(self.shift_pattern_low & bit != 0)
+            | u8::from(self.shift_pattern_high & bit != 0) << 1;
+        if pattern == 0 {
+            return 0;
+        }
+        let attribute = u8::from(self.shift_attribute_low & bit != 0)
+            | u8::from(self.shift_attribute_high & bit != 0) << 1;
+        attribute << 2 | pattern
+    }
+
+    fn load(&mut self) {
+        self.shift_pattern_low = self.shift_pattern_low & 0xFF00 | u16::from(self.pattern_low);
+        self.shift_pattern_high = self.shift_pattern_high & 0xFF00 | u16::from(self.pattern_high);
+        let spread = |bit: u8| {
+            if self.attribute & bit != 0 {
+                0xFF
+            } else {
+                0x00
+            }
+        };
+        self.shift_attribute_low = self.shift_attribute_low & 0xFF00 | spread(0b01);
+        self.shift_attribute_high = self.shift_attribute_high & 0xFF00 | spread(0b10);
+    }
+}
+
+impl Ppu {
+    /// One step of the 8-dot fetch cycle, on dots 1-256 and 321-336.
+    pub(crate) fn fetch_background(&mut self, bus: &mut impl Bus) {
+        match (self.dot - 1) & 0b111 {
+            0 => {
+                self.background.load();
+                self.background.tile = bus.read_u8(0x2000 | self.v & 0x0FFF);
+            }
+            2 => {
+                let v = self.v;
+                let address = 0x23C0 | v & 0x0C00 | v >> 4 & 0x38 | v >> 2 & 0x07;
+                // Each attribute byte covers 4x4 tiles, 2 bits per 2x2 quadrant.
+                let shift = v >> 4 & 0b100 | v & 0b10;
+                self.background.attribute = bus.read_u8(address) >> shift & 0b11;
+            }
+            4 => self.background.pattern_low = bus.read_u8(self.pattern_address()),
+            6 => self.background.pattern_high = bus.read_u8(self.pattern_address() | 0b1000),
+            7 => self.increment_x(),
+            _ => {}
+        }
+    }
+
+    /// Loads the tile fetched on dots 249-256 for the start of the next line.
+    pub(crate) fn load_background(&mut self) {
+        self.background.load();
+    }
+
+    fn pattern_address(&self) -> u16 {
+        let table = if self.ctrl.contains(Ctrl::BACKGROUND_TABLE) {
+            0x1000
+        } else {
+            0x0000
+        };
+        table | u16::from(self.background.tile) << 4 | self.v >> 12 & 0b111
+    }
+
+    /// Coarse X, wrapping into the horizontally adjacent nametable.
+    fn increment_x(&mut self) {
+        if self.v & 0x001F == 31 {
+            self.v = self.v & !0x001F ^ 0x0400;
+        } else {
+            self.v += 1;
+        }
+    }
+
+    /// Fine Y, carrying into coarse Y, which wraps into the vertically adjacent
+    /// nametable after row 29. Rows 30 and 31 wrap without switching.
+    pub(crate) fn increment_y(&mut self) {
+        if self.v & 0x7000 != 0x7000 {
+            self.v += 0x1000;
+            return;
+        }
+        self.v &= !0x7000;
+        let y = match self.v >> 5 & 0x1F {
+            29 => {
+                self.v ^= 0x0800;
+                0
+            }
+            31 => 0,
+            y => y + 1,
+        };
+        self.v = self.v & !0x03E0 | y << 5;
+    }
+
+    pub(crate) fn copy_horizontal(&mut self) {
+        self.v = self.v & !0x041F | self.t & 0x041F;
+    }
+
+    pub(crate) fn copy_vertical(&mut self) {
+        self.v = self.v & !0x7BE0 | self.t & 0x7BE0;
+    }
+}
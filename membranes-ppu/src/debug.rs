@@ -0,0 +1,235 @@
+//! RGBA images of the PPU's memory for debugging. Memory is peeked, so drawing
+//! them doesn't disturb boards that watch the PPU's reads.
+
+use crate::{palette_index, Bus, Ctrl, Palette, Ppu, HEIGHT, WIDTH};
+
+/// Both pattern tables side by side, 16x16 tiles each.
+pub const PATTERN_TABLES_WIDTH: usize = 256;
+pub const PATTERN_TABLES_HEIGHT: usize = 128;
+/// All four nametables in a 2x2 grid.
+pub const NAMETABLES_WIDTH: usize = 2 * WIDTH;
+pub const NAMETABLES_HEIGHT: usize = 2 * HEIGHT;
+/// The 64 sprites 8 to a row, each in an 8x16 cell.
+pub const OAM_WIDTH: usize = 64;
+pub const OAM_HEIGHT: usize = 128;
+/// Background palettes in the top row, sprite palettes in the bottom one.
+pub const PALETTE_WIDTH: usize = 16;
+pub const PALETTE_HEIGHT: usize = 2;
+
+/// Outline of the visible screen on the nametables.
+const SCROLL_OVERLAY: [u8; 4] = [0xFF, 0x00, 0xFF, 0xFF];
+
+/// One OAM entry and how it looks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpriteView {
+    pub index: u8,
+    pub x: u8,
+    /// One less than the first scanline drawn on, as stored.
+    pub y: u8,
+    pub tile: u8,
+    pub attribute: u8,
+    /// 8 or 16
+    pub height: u8,
+    /// 8 x `height` pixels, transparent where the sprite is.
+    pub rgba: Vec<u8>,
+}
+
+/// RGBA image being drawn.
+struct Image {
+    width: usize,
+    pixels: Vec<u8>,
+}
+
+impl Image {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            pixels: vec![0x00; width * height * 4],
+        }
+    }
+
+    fn set(&mut self, x: usize, y: usize, rgba: [u8; 4]) {
+        let offset = (y * self.width + x) * 4;
+        self.pixels[offset..offset + 4].copy_from_slice(&rgba);
+    }
+}
+
+impl Ppu {
+    /// Both pattern tables, colored with one of the 8 palettes.
+    pub fn debug_pattern_tables(
+        &self,
+        palette: u8,
+        colors: &Palette,
+        bus: &mut impl Bus,
+    ) -> Vec<u8> {
+        let mut image = Image::new(PATTERN_TABLES_WIDTH, PATTERN_TABLES_HEIGHT);
+        for table in 0..2 {
+            for tile in 0..256 {
+                let x = table * 128 + tile % 16 * 8;
+                let y = tile / 16 * 8;
+                let address = (table * 0x1000 + tile * 16) as u16;
+                self.draw_tile(&mut image, x, y, address, palette, colors, bus);
+            }
+        }
+        image.pixels
+    }
+
+    /// All four nametables as the background would draw them, with the
+    /// screen's scroll position from `t` outlined.
+    pub fn debug_nametables(&self, colors: &Palette, bus: &mut impl Bus) -> Vec<u8> {
+        let mut image = Image::new(NAMETABLES_WIDTH, NAMETABLES_HEIGHT);
+        let table = if self.ctrl.contains(Ctrl::BACKGROUND_TABLE) {
+            0x1000
+        } else {
+            0x0000
+        };
+        for nametable in 0..4 {
+            let base = 0x2000 + nametable as u16 * 0x400;
+            for row in 0..30 {
+                for column in 0..32 {
+                    let tile = bus.peek_u8(base + row * 32 + column);
+                    let attribute = bus.peek_u8(base + 0x3C0 + row / 4 * 8 + column / 4);
+                    let shift = (row & 0b10) << 1 | column & 0b10;
+                    let palette = attribute >> shift & 0b11;
+                    let x = nametable % 2 * WIDTH + usize::from(column) * 8;
+                    let y = nametable / 2 * HEIGHT + usize::from(row) * 8;
+                    let address = table | u16::from(tile) << 4;
+                    self.draw_tile(&mut image, x, y, address, palette, colors, bus);
+                }
+            }
+        }
+
+        let t = usize::from(self.t);
+        let scroll_x = (t & 0x1F) * 8 + usize::from(self.x) + (t >> 10 & 1) * WIDTH;
+        let scroll_y = (t >> 5 & 0x1F) * 8 + (t >> 12 & 0b111) + (t >> 11 & 1) * HEIGHT;
+        for offset in 0..WIDTH {
+            let x = (scroll_x + offset) % NAMETABLES_WIDTH;
+            image.set(x, scroll_y % NAMETABLES_HEIGHT, SCROLL_OVERLAY);
+            image.set(
+                x,
+                (scroll_y + HEIGHT - 1) % NAMETABLES_HEIGHT,
+                SCROLL_OVERLAY,
+            );
+        }
+        for offset in 0..HEIGHT {
+            let y = (scroll_y + offset) % NAMETABLES_HEIGHT;
+            image.set(scroll_x % NAMETABLES_WIDTH, y, SCROLL_OVERLAY);
+            image.set((scroll_x + WIDTH - 1) % NAMETABLES_WIDTH, y, SCROLL_OVERLAY);
+        }
+        image.pixels
+    }
+
+    /// Every OAM entry, drawn with its flips and palette.
+    pub fn debug_sprites(&self, colors: &Palette, bus: &mut impl Bus) -> Vec<SpriteView> {
+        let tall = self.ctrl.contains(Ctrl::TALL_SPRITES);
+        let height = if tall { 16 } else { 8 };
+        self.oam
+            .chunks_exact(4)
+            .enumerate()
+            .map(|(index, entry)| {
+                let [y, tile, attribute, x] = [entry[0], entry[1], entry[2], entry[3]];
+                let mut image = Image::new(8, height);
+                for row in 0..height {
+                    let flipped = if attribute & 0x80 != 0 {
+                        height - 1 - row
+                    } else {
+                        row
+                    };
+                    let address = if tall {
+                        let table = u16::from(tile & 1) << 12;
+                        let half = u16::from(tile & 0xFE) + (flipped as u16 >> 3);
+                        table | half << 4 | (flipped & 0b111) as u16
+                    } else {
+                        let table = if self.ctrl.contains(Ctrl::SPRITE_TABLE) {
+                            0x1000
+                        } else {
+                            0x0000
+                        };
+                        table | u16::from(tile) << 4 | flipped as u16
+                    };
+                    let pixels = tile_row(address, bus);
+                    for (column, &pixel) in pixels.iter().enumerate() {
+                        let column = if attribute & 0x40 != 0 {
+                            7 - column
+                        } else {
+                            column
+                        };
+                        if pixel != 0 {
+                            let entry =
+                                0x10 | usize::from(attribute & 0b11) << 2 | usize::from(pixel);
+                            image.set(column, row, self.rgba(entry, colors));
+                        }
+                    }
+                }
+                SpriteView {
+                    index: index as u8,
+                    x,
+                    y,
+                    tile,
+                    attribute,
+                    height: height as u8,
+                    rgba: image.pixels,
+                }
+            })
+            .collect()
+    }
+
+    /// `debug_sprites` laid out on one sheet.
+    pub fn debug_oam(&self, colors: &Palette, bus: &mut impl Bus) -> Vec<u8> {
+        let mut image = Image::new(OAM_WIDTH, OAM_HEIGHT);
+        for sprite in self.debug_sprites(colors, bus) {
+            let index = usize::from(sprite.index);
+            let (left, top) = (index % 8 * 8, index / 8 * 16);
+            for (i, rgba) in sprite.rgba.chunks_exact(4).enumerate() {
+                image.set(
+                    left + i % 8,
+                    top + i / 8,
+                    [rgba[0], rgba[1], rgba[2], rgba[3]],
+                );
+            }
+        }
+        image.pixels
+    }
+
+    /// One pixel per palette RAM entry.
+    pub fn debug_palette(&self, colors: &Palette) -> Vec<u8> {
+        (0..32).flat_map(|entry| self.rgba(entry, colors)).collect()
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn draw_tile(
+        &self,
+        image: &mut Image,
+        x: usize,
+        y: usize,
+        address: u16,
+        palette: u8,
+        colors: &Palette,
+        bus: &mut impl Bus,
+    ) {
+        for row in 0..8 {
+            let pixels = tile_row(address | row, bus);
+            for (column, &pixel) in pixels.iter().enumerate() {
+                // Color 0 of every palette shows the backdrop, as on screen.
+                let entry = if pixel == 0 {
+                    0
+                } else {
+                    usize::from(palette & 0b111) << 2 | usize::from(pixel)
+                };
+                image.set(x + column, y + usize::from(row), self.rgba(entry, colors));
+            }
+        }
+    }
+
+    fn rgba(&self, entry: usize, colors: &Palette) -> [u8; 4] {
+        let [r, g, b] = colors.rgb(u16::from(self.palette[palette_index(entry as u16)]));
+        [r, g, b, 0xFF]
+    }
+}
+
+/// The 2-bit pixels of one row of a tile, left to right.
+fn tile_row(address: u16, bus: &mut impl Bus) -> [u8; 8] {
+    let low = bus.peek_u8(address);
+    let high = bus.peek_u8(address | 0b1000);
+    std::array::from_fn(|column| (low >> (7 - column) & 1) | (high >> (7 - column) & 1) << 1)
+}
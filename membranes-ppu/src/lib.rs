@@ -0,0 +1,494 @@
+use background::Background;
+use bitflags::bitflags;
+use sprites::Sprites;
+use std::ops::IndexMut;
+
+mod background;
+pub mod debug;
+pub mod memory;
+pub mod ntsc;
+pub mod palette;
+mod sprites;
+
+pub use debug::SpriteView;
+pub use memory::{Memory, MemoryMap, CIRAM_LEN};
+pub use ntsc::{NtscFilter, NtscSettings, NTSC_WIDTH};
+pub use palette::{Palette, PALETTE_LEN};
+pub use sprites::LINE_SPRITES;
+
+pub const WIDTH: usize = 256;
+pub const HEIGHT: usize = 240;
+
+/// Dots per scanline
+pub const DOTS: u16 = 341;
+/// Scanlines per NTSC frame, counting the pre-render line.
+pub const SCANLINES: u16 = 262;
+/// The first scanline of NTSC's vertical blank.
+pub const VBLANK_SCANLINE: u16 = 241;
+
+/// TV system the console was built for, which sets the frame's timing.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Region {
+    #[default]
+    Ntsc,
+    Pal,
+    /// Famiclones pairing PAL video with NTSC-like CPU timing.
+    Dendy,
+}
+
+impl Region {
+    /// Scanlines per frame, counting the pre-render line.
+    pub fn scanlines(self) -> u16 {
+        match self {
+            Region::Ntsc => SCANLINES,
+            Region::Pal | Region::Dendy => 312,
+        }
+    }
+
+    /// The first scanline of vertical blank. Dendy idles 51 lines after the
+    /// picture so its vblank is as short as NTSC's.
+    pub fn vblank_scanline(self) -> u16 {
+        match self {
+            Region::Ntsc | Region::Pal => VBLANK_SCANLINE,
+            Region::Dendy => 291,
+        }
+    }
+
+    /// Whether odd frames are a dot shorter while rendering.
+    pub fn skips_odd_dot(self) -> bool {
+        self == Region::Ntsc
+    }
+}
+
+bitflags! {
+    /// PPUCTRL ($2000)
+    #[derive(Default)]
+    pub struct Ctrl: u8 {
+        const NAMETABLE_X = 0b0000_0001;
+        const NAMETABLE_Y = 0b0000_0010;
+        /// Add 32 to the VRAM address after PPUDATA accesses, instead of 1.
+        const INCREMENT_32 = 0b0000_0100;
+        const SPRITE_TABLE = 0b0000_1000;
+        const BACKGROUND_TABLE = 0b0001_0000;
+        /// 8x16 sprites
+        const TALL_SPRITES = 0b0010_0000;
+        const EXT_OUTPUT = 0b0100_0000;
+        const NMI = 0b1000_0000;
+    }
+}
+
+bitflags! {
+    /// PPUMASK ($2001)
+    #[derive(Default)]
+    pub struct Mask: u8 {
+        const GREYSCALE = 0b0000_0001;
+        const BACKGROUND_LEFT = 0b0000_0010;
+        const SPRITES_LEFT = 0b0000_0100;
+        const BACKGROUND = 0b0000_1000;
+        const SPRITES = 0b0001_0000;
+        const EMPHASIZE_RED = 0b0010_0000;
+        const EMPHASIZE_GREEN = 0b0100_0000;
+        const EMPHASIZE_BLUE = 0b1000_0000;
+    }
+}
+
+bitflags! {
+    /// PPUSTATUS ($2002)
+    #[derive(Default)]
+    pub struct Status: u8 {
+        const SPRITE_OVERFLOW = 0b0010_0000;
+        const SPRITE_ZERO_HIT = 0b0100_0000;
+        const VBLANK = 0b1000_0000;
+    }
+}
+
+/// The PPU's address space outside the palette, $0000-$2FFF.
+pub trait Bus {
+    fn read_u8(&mut self, address: u16) -> u8;
+
+    fn write_u8(&mut self, address: u16, data: u8);
+
+    /// Reads without side effects, for debug views.
+    fn peek_u8(&mut self, address: u16) -> u8 {
+        self.read_u8(address)
+    }
+
+    /// Puts an address on the bus without accessing it, as loading `v` through
+    /// PPUADDR does.
+    fn observe_address(&mut self, _address: u16) {}
+}
+
+impl<T> Bus for T
+where
+    T: IndexMut<usize, Output = u8>,
+{
+    fn read_u8(&mut self, address: u16) -> u8 {
+        self[usize::from(address)]
+    }
+
+    fn write_u8(&mut self, address: u16, data: u8) {
+        self[usize::from(address)] = data;
+    }
+}
+
+pub struct Ppu {
+    pub ctrl: Ctrl,
+    pub mask: Mask,
+    pub status: Status,
+    pub oam_address: u8,
+    pub oam: [u8; 256],
+    /// Backdrop and 3 colors for each of the 4 background then 4 sprite palettes.
+    pub palette: [u8; 32],
+    /// Current VRAM address, also the scroll position while rendering.
+    pub v: u16,
+    /// Temporary VRAM address, the scroll position copied into `v`.
+    pub t: u16,
+    /// Fine X scroll
+    pub x: u8,
+    /// Write toggle shared by PPUSCROLL and PPUADDR, set after the first write.
+    pub w: bool,
+    pub region: Region,
+    /// 0-261 (0-311 outside NTSC), with 0-239 visible and the last the pre-render line.
+    pub scanline: u16,
+    /// 0-340 within the scanline.
+    pub dot: u16,
+    /// Frames completed since power-on.
+    pub frame: u64,
+    /// The visible picture, row by row: colors (0-63) in bits 0-5 and
+    /// PPUMASK's emphasis bits in bits 6-8, as indexed by [`Palette`].
+    pub framebuffer: Vec<u16>,
+    background: Background,
+    sprites: Sprites,
+    nmi_pending: bool,
+    /// Set by a PPUSTATUS read just before vblank starts, which keeps it from starting.
+    suppress_vblank: bool,
+    read_buffer: u8,
+    /// Last value on the register data bus, which unused bits read back as.
+    io_latch: u8,
+}
+
+impl Default for Ppu {
+    fn default() -> Self {
+        Self {
+            ctrl: Default::default(),
+            mask: Default::default(),
+            status: Default::default(),
+            oam_address: 0,
+            oam: [0x00; 256],
+            palette: [0x00; 32],
+            v: 0,
+            t: 0,
+            x: 0,
+            w: false,
+            region: Default::default(),
+            scanline: 0,
+            dot: 0,
+            frame: 0,
+            framebuffer: vec![0x00; WIDTH * HEIGHT],
+            background: Default::default(),
+            sprites: Default::default(),
+            nmi_pending: false,
+            suppress_vblank: false,
+            read_buffer: 0,
+            io_latch: 0,
+        }
+    }
+}
+
+impl Ppu {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// CPU reads from $2000-$3FFF, mirrored every 8 bytes.
+    pub fn read_register(&mut self, address: u16, bus: &mut impl Bus) -> u8 {
+        let data = match address & 0b111 {
+            2 => {
+                // Reading as vblank starts races it: one dot early the flag never
+                // sets, on the next two the flag reads set but NMI is cancelled.
+                if self.scanline == self.region.vblank_scanline() {
+                    match self.dot {
+                        1 => self.suppress_vblank = true,
+                        2 | 3 => self.nmi_pending = false,
+                        _ => {}
+                    }
+                }
+                let data = self.status.bits() | self.io_latch & 0x1F;
+                self.status.remove(Status::VBLANK);
+                self.w = false;
+                data
+            }
+            4 => self.read_oam(),
+            7 => {
+                let address = self.v & 0x3FFF;
+                let data = if address >= 0x3F00 {
+                    // Palette reads skip the buffer, which gets the nametable byte underneath.
+                    self.read_buffer = bus.read_u8(address & 0x2FFF);
+                    self.read_palette(address) | self.io_latch & 0xC0
+                } else {
+                    let data = self.read_buffer;
+                    self.read_buffer = self.read_vram(address, bus);
+                    data
+                };
+                self.increment_v();
+                data
+            }
+            _ => self.io_latch,
+        };
+        self.io_latch = data;
+        data
+    }
+
+    /// Register reads without side effects, for inspecting memory.
+    pub fn peek_register(&self, address: u16) -> u8 {
+        match address & 0b111 {
+            2 => self.status.bits() | self.io_latch & 0x1F,
+            4 => self.read_oam(),
+            7 => self.read_buffer,
+            _ => self.io_latch,
+        }
+    }
+
+    /// CPU writes to $2000-$3FFF, mirrored every 8 bytes.
+    pub fn write_register(&mut self, address: u16, data: u8, bus: &mut impl Bus) {
+        self.io_latch = data;
+        match address & 0b111 {
+            0 => {
+                let ctrl = Ctrl::from_bits_truncate(data);
+                // Enabling NMI during vblank raises one straight away.
+                if ctrl.contains(Ctrl::NMI)
+                    && !self.ctrl.contains(Ctrl::NMI)
+                    && self.status.contains(Status::VBLANK)
+                {
+                    self.nmi_pending = true;
+                }
+                self.ctrl = ctrl;
+                self.t = self.t & !0x0C00 | u16::from(data & 0b11) << 10;
+            }
+            1 => self.mask = Mask::from_bits_truncate(data),
+            2 => {}
+            3 => self.oam_address = data,
+            4 => self.write_oam(data),
+            5 => {
+                if self.w {
+                    self.t = self.t & !0x73E0
+                        | u16::from(data & 0b111) << 12
+                        | u16::from(data & 0xF8) << 2;
+                } else {
+                    self.t = self.t & !0x001F | u16::from(data >> 3);
+                    self.x = data & 0b111;
+                }
+                self.w = !self.w;
+            }
+            6 => {
+                if self.w {
+                    self.t = self.t & 0xFF00 | u16::from(data);
+                    self.v = self.t;
+                    bus.observe_address(self.v & 0x3FFF);
+                } else {
+                    self.t = self.t & 0x00FF | u16::from(data & 0x3F) << 8;
+                }
+                self.w = !self.w;
+            }
+            _ => {
+                self.write_vram(self.v & 0x3FFF, data, bus);
+                self.increment_v();
+            }
+        }
+    }
+
+    /// Writes at the OAM address and advances it, for OAMDATA and OAM DMA.
+    pub fn write_oam(&mut self, data: u8) {
+        self.oam[usize::from(self.oam_address)] = data;
+        self.oam_address = self.oam_address.wrapping_add(1);
+    }
+
+    /// Advances one dot.
+    pub fn clock(&mut self, bus: &mut impl Bus) {
+        let visible = self.scanline < HEIGHT as u16;
+        let pre_render = self.scanline == self.pre_render();
+        if self.dot == 1 {
+            if self.scanline == self.region.vblank_scanline() {
+                if !self.suppress_vblank {
+                    self.status.insert(Status::VBLANK);
+                    self.nmi_pending = self.ctrl.contains(Ctrl::NMI);
+                }
+                self.suppress_vblank = false;
+            } else if pre_render {
+                self.status.remove(Status::all());
+            }
+        }
+        if self.rendering() && (visible || pre_render) {
+            self.clock_background(bus);
+            self.clock_sprites(visible, bus);
+        }
+        if visible && (1..=WIDTH as u16).contains(&self.dot) {
+            self.render_pixel();
+        }
+
+        self.dot += 1;
+        // NTSC's odd frames skip the pre-render line's last dot while rendering.
+        if pre_render
+            && self.dot == DOTS - 1
+            && self.frame & 1 == 1
+            && self.region.skips_odd_dot()
+            && self.rendering()
+        {
+            self.dot = DOTS;
+        }
+        if self.dot == DOTS {
+            self.dot = 0;
+            self.scanline += 1;
+            // Past the end too, in case the region changed mid-frame.
+            if self.scanline >= self.region.scanlines() {
+                self.scanline = 0;
+                self.frame += 1;
+            }
+        }
+    }
+
+    /// Whether an NMI was raised since the last call.
+    pub fn take_nmi(&mut self) -> bool {
+        std::mem::take(&mut self.nmi_pending)
+    }
+
+    /// Whether background or sprite rendering is enabled.
+    pub fn rendering(&self) -> bool {
+        self.mask.intersects(Mask::BACKGROUND | Mask::SPRITES)
+    }
+
+    fn pre_render(&self) -> u16 {
+        self.region.scanlines() - 1
+    }
+
+    fn clock_background(&mut self, bus: &mut impl Bus) {
+        match self.dot {
+            2..=257 | 322..=337 => self.background.shift(),
+            _ => {}
+        }
+        match self.dot {
+            1..=256 | 321..=336 => self.fetch_background(bus),
+            _ => {}
+        }
+        match self.dot {
+            256 => self.increment_y(),
+            257 => {
+                self.load_background();
+                self.copy_horizontal();
+            }
+            280..=304 if self.scanline == self.pre_render() => self.copy_vertical(),
+            // Unused nametable fetches, which some boards count to find the line's end.
+            337 | 339 => {
+                bus.read_u8(0x2000 | self.v & 0x0FFF);
+            }
+            _ => {}
+        }
+    }
+
+    fn clock_sprites(&mut self, visible: bool, bus: &mut impl Bus) {
+        if self.dot == 257 {
+            self.oam_address = 0;
+            if visible {
+                self.evaluate_sprites();
+            } else {
+                self.clear_sprites();
+            }
+        }
+        if (257..=320).contains(&self.dot) {
+            self.fetch_sprite(bus);
+        }
+    }
+
+    fn render_pixel(&mut self) {
+        let x = usize::from(self.dot - 1);
+        let color = if self.rendering() {
+            let background = if self.mask.contains(Mask::BACKGROUND)
+                && (x >= 8 || self.mask.contains(Mask::BACKGROUND_LEFT))
+            {
+                self.background.pixel(self.x)
+            } else {
+                0
+            };
+            let (sprite, zero) = if self.mask.contains(Mask::SPRITES)
+                && (x >= 8 || self.mask.contains(Mask::SPRITES_LEFT))
+            {
+                self.sprites.pixel(x as u8)
+            } else {
+                (None, false)
+            };
+            // Sprite 0 hits wherever it overlaps opaque background, except at x = 255.
+            if zero && background != 0 && x != 255 {
+                self.status.insert(Status::SPRITE_ZERO_HIT);
+            }
+            let color = match sprite {
+                Some(sprite) if background == 0 || !sprite.behind_background => sprite.color,
+                _ => background,
+            };
+            self.read_palette(0x3F00 | u16::from(color))
+        } else if self.v & 0x3F00 == 0x3F00 {
+            // With rendering off, pointing v at the palette shows that color.
+            self.read_palette(self.v)
+        } else {
+            self.read_palette(0x3F00)
+        };
+        let emphasis = u16::from(self.mask.bits() >> 5) << 6;
+        self.framebuffer[usize::from(self.scanline) * WIDTH + x] = emphasis | u16::from(color);
+    }
+
+    /// Reads from $0000-$3FFF. $3000-$3EFF mirrors $2000-$2EFF.
+    pub fn read_vram(&mut self, address: u16, bus: &mut impl Bus) -> u8 {
+        match address & 0x3FFF {
+            address @ 0x3F00.. => self.read_palette(address),
+            address @ 0x3000.. => bus.read_u8(address - 0x1000),
+            address => bus.read_u8(address),
+        }
+    }
+
+    /// Writes to $0000-$3FFF. $3000-$3EFF mirrors $2000-$2EFF.
+    pub fn write_vram(&mut self, address: u16, data: u8, bus: &mut impl Bus) {
+        match address & 0x3FFF {
+            address @ 0x3F00.. => self.palette[palette_index(address)] = data & 0x3F,
+            address @ 0x3000.. => bus.write_u8(address - 0x1000, data),
+            address => bus.write_u8(address, data),
+        }
+    }
+
+    /// Greyscale mode keeps only the brightness bits.
+    fn read_palette(&self, address: u16) -> u8 {
+        let data = self.palette[palette_index(address)];
+        if self.mask.contains(Mask::GREYSCALE) {
+            data & 0x30
+        } else {
+            data
+        }
+    }
+
+    /// Sprite attribute bits 2-4 don't exist and read back as 0.
+    fn read_oam(&self) -> u8 {
+        let data = self.oam[usize::from(self.oam_address)];
+        if self.oam_address & 0b11 == 2 {
+            data & 0xE3
+        } else {
+            data
+        }
+    }
+
+    fn increment_v(&mut self) {
+        let increment = if self.ctrl.contains(Ctrl::INCREMENT_32) {
+            32
+        } else {
+            1
+        };
+        self.v = self.v.wrapping_add(increment) & 0x7FFF;
+    }
+}
+
+/// Sprite palette backdrops at $3F10/$3F14/$3F18/$3F1C mirror the background ones.
+fn palette_index(address: u16) -> usize {
+    let index = usize::from(address & 0x1F);
+    if index & 0x13 == 0x10 {
+        index & 0x0F
+    } else {
+        index
+    }
+}
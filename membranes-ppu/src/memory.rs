@@ -0,0 +1,97 @@
+//! The PPU bus below the palette: pattern tables and nametables.
+
+use crate::Bus;
+
+/// CIRAM plus the 2 KiB four-screen boards add, as four 1 KiB pages.
+pub const CIRAM_LEN: usize = 0x1000;
+
+/// The cartridge's side of the PPU bus. Boards supply the pattern tables, pick the
+/// CIRAM page behind each nametable, and can substitute their own nametable memory.
+pub trait Memory {
+    /// Reads from $0000-$1FFF.
+    fn read_pattern(&mut self, address: u16) -> u8;
+
+    /// Writes to $0000-$1FFF.
+    fn write_pattern(&mut self, address: u16, data: u8);
+
+    /// CIRAM page (0-3) shown by each nametable (0-3).
+    fn nametable_page(&self, nametable: usize) -> usize;
+
+    /// Reads from $2000-$2FFF, given what CIRAM holds there.
+    fn read_nametable(&mut self, _address: u16, ciram: u8) -> u8 {
+        ciram
+    }
+
+    /// Writes to $2000-$2FFF. Returns false to let them through to CIRAM.
+    fn write_nametable(&mut self, _address: u16, _data: u8) -> bool {
+        false
+    }
+
+    /// Observes every address put on the bus, before it is read or written.
+    fn observe_address(&mut self, _address: u16) {}
+
+    /// Pattern reads without side effects, for debug views.
+    fn peek_pattern(&mut self, address: u16) -> u8 {
+        self.read_pattern(address)
+    }
+
+    /// Nametable reads without side effects, for debug views.
+    fn peek_nametable(&mut self, address: u16, ciram: u8) -> u8 {
+        self.read_nametable(address, ciram)
+    }
+}
+
+/// Routes $0000-$2FFF between the cartridge and CIRAM.
+pub struct MemoryMap<'a, M: Memory + ?Sized> {
+    pub ciram: &'a mut [u8],
+    pub memory: &'a mut M,
+}
+
+impl<M: Memory + ?Sized> MemoryMap<'_, M> {
+    fn ciram_offset(&self, address: u16) -> usize {
+        let page = self
+            .memory
+            .nametable_page(usize::from(address >> 10 & 0b11));
+        (page * 0x400 + usize::from(address & 0x03FF)) % self.ciram.len()
+    }
+}
+
+impl<M: Memory + ?Sized> Bus for MemoryMap<'_, M> {
+    fn read_u8(&mut self, address: u16) -> u8 {
+        self.memory.observe_address(address);
+        match address {
+            0x0000..=0x1FFF => self.memory.read_pattern(address),
+            _ => {
+                let ciram = self.ciram[self.ciram_offset(address)];
+                self.memory.read_nametable(address, ciram)
+            }
+        }
+    }
+
+    fn peek_u8(&mut self, address: u16) -> u8 {
+        match address {
+            0x0000..=0x1FFF => self.memory.peek_pattern(address),
+            _ => {
+                let ciram = self.ciram[self.ciram_offset(address)];
+                self.memory.peek_nametable(address, ciram)
+            }
+        }
+    }
+
+    fn observe_address(&mut self, address: u16) {
+        self.memory.observe_address(address);
+    }
+
+    fn write_u8(&mut self, address: u16, data: u8) {
+        self.memory.observe_address(address);
+        match address {
+            0x0000..=0x1FFF => self.memory.write_pattern(address, data),
+            _ => {
+                if !self.memory.write_nametable(address, data) {
+                    let offset = self.ciram_offset(address);
+                    self.ciram[offset] = data;
+                }
+            }
+        }
+    }
+}
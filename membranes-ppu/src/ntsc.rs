@@ -0,0 +1,160 @@
+//! NTSC composite video: each pixel's signal is synthesized as the 2C02 generates
+//! it, then decoded back to RGB like a TV would, with the same dot crawl, color
+//! fringing and artifacts.
+
+use crate::{HEIGHT, WIDTH};
+use std::f32::consts::PI;
+
+/// Output width, about 7/3 of the PPU's so artifacts have room to show.
+pub const NTSC_WIDTH: usize = 602;
+
+/// Signal samples per pixel
+const PIXEL_SAMPLES: usize = 8;
+/// Samples per color subcarrier cycle
+const PHASES: usize = 12;
+const LINE_SAMPLES: usize = WIDTH * PIXEL_SAMPLES;
+
+/// Voltages of the low then high halves of the square wave, for each luma level.
+const LOW_LEVELS: [f32; 4] = [0.350, 0.518, 0.962, 1.550];
+const HIGH_LEVELS: [f32; 4] = [1.094, 1.506, 1.962, 1.962];
+const BLACK: f32 = 0.518;
+const WHITE: f32 = 1.962;
+/// Lines the decoder's I and Q axes up with the colorburst, in samples.
+const DECODE_PHASE: usize = 4;
+/// Emphasis scales the signal during a third of each subcarrier cycle.
+const EMPHASIS_ATTENUATION: f32 = 0.746;
+
+/// Each tunable ranges over -1 to 1, with 0 the default.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct NtscSettings {
+    /// Narrows the luma filter, from blurry to crisp.
+    pub sharpness: f32,
+    /// From greyscale to doubly saturated.
+    pub saturation: f32,
+    /// Rotates colors by up to 45 degrees either way.
+    pub hue: f32,
+    /// How much chroma leaks into luma as fringing and dot crawl, from none to full.
+    pub artifacts: f32,
+}
+
+pub struct NtscFilter {
+    pub settings: NtscSettings,
+    signal: Vec<f32>,
+    cos: [f32; PHASES],
+    sin: [f32; PHASES],
+}
+
+impl Default for NtscFilter {
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+impl NtscFilter {
+    pub fn new(settings: NtscSettings) -> Self {
+        let angle = |phase: usize| PI * (phase + DECODE_PHASE) as f32 / 6.0;
+        Self {
+            settings,
+            signal: vec![0.0; LINE_SAMPLES],
+            cos: std::array::from_fn(|phase| angle(phase).cos()),
+            sin: std::array::from_fn(|phase| angle(phase).sin()),
+        }
+    }
+
+    /// Filters a framebuffer of 9-bit pixels into `NTSC_WIDTH` x `HEIGHT` RGBA.
+    /// The subcarrier's phase shifts every scanline and, with the odd-frame
+    /// dot skip, alternates between frames.
+    pub fn filter_rgba(&mut self, framebuffer: &[u16], frame: u64) -> Vec<u8> {
+        let mut output = Vec::with_capacity(NTSC_WIDTH * HEIGHT * 4);
+        let frame_phase = if frame & 1 == 0 { 0 } else { 4 };
+        for (y, line) in framebuffer.chunks_exact(WIDTH).take(HEIGHT).enumerate() {
+            // 341 dots of 8 samples leave the phase 4 further along each line.
+            let phase = (frame_phase + y * 4) % PHASES;
+            self.encode_line(line, phase);
+            for x in 0..NTSC_WIDTH {
+                let [r, g, b] = self.decode(x * LINE_SAMPLES / NTSC_WIDTH, phase);
+                output.extend([r, g, b, 0xFF]);
+            }
+        }
+        output
+    }
+
+    fn encode_line(&mut self, line: &[u16], phase: usize) {
+        for (x, &pixel) in line.iter().enumerate() {
+            for sample in 0..PIXEL_SAMPLES {
+                let position = x * PIXEL_SAMPLES + sample;
+                self.signal[position] = encode(pixel, (phase + position) % PHASES);
+            }
+        }
+    }
+
+    /// YIQ around a sample, converted to RGB.
+    fn decode(&self, center: usize, phase: usize) -> [u8; 3] {
+        let window = |half: usize| center.saturating_sub(half)..(center + half).min(LINE_SAMPLES);
+        let (mut luma, mut i, mut q) = (0.0, 0.0, 0.0);
+        let chroma_window = window(PHASES / 2);
+        let chroma_len = chroma_window.len() as f32;
+        for position in chroma_window {
+            let level = self.signal[position];
+            let phase = (phase + position) % PHASES;
+            luma += level;
+            i += level * self.cos[phase];
+            q += level * self.sin[phase];
+        }
+        let (luma, i, q) = (luma / chroma_len, i / chroma_len, q / chroma_len);
+
+        // A full subcarrier cycle cancels chroma out of luma, anything narrower leaks some.
+        let settings = &self.settings;
+        let half = (3.0 - 2.0 * settings.sharpness).round().clamp(1.0, 6.0) as usize;
+        let sharp_window = window(half);
+        let sharp_len = sharp_window.len() as f32;
+        let sharp: f32 = sharp_window.map(|p| self.signal[p]).sum::<f32>() / sharp_len;
+        let leak = (settings.artifacts + 1.0) / 2.0;
+        let luma = luma + (sharp - luma) * leak;
+
+        let saturation = 1.0 + settings.saturation;
+        let (sin, cos) = (settings.hue * PI / 4.0).sin_cos();
+        let (i, q) = (
+            (i * cos - q * sin) * saturation,
+            (i * sin + q * cos) * saturation,
+        );
+
+        let to_u8 = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+        [
+            to_u8(luma + 0.946_882 * i + 0.623_557 * q),
+            to_u8(luma - 0.274_788 * i - 0.635_691 * q),
+            to_u8(luma - 1.108_545 * i + 1.709_007 * q),
+        ]
+    }
+}
+
+/// One sample of a pixel's signal, from black at 0 to white at 1.
+fn encode(pixel: u16, phase: usize) -> f32 {
+    let hue = usize::from(pixel & 0x0F);
+    let level = if hue > 13 {
+        1
+    } else {
+        usize::from(pixel >> 4 & 0b11)
+    };
+    let emphasis = pixel >> 6;
+    let in_phase = |hue: usize| (hue + phase) % PHASES < PHASES / 2;
+
+    // Hue 0 stays high, 13-15 stay low, the rest alternate with the subcarrier.
+    let high = match hue {
+        0 => true,
+        13.. => false,
+        _ => in_phase(hue),
+    };
+    let mut signal = if high {
+        HIGH_LEVELS[level]
+    } else {
+        LOW_LEVELS[level]
+    };
+    if (emphasis & 0b001 != 0 && in_phase(0))
+        || (emphasis & 0b010 != 0 && in_phase(4))
+        || (emphasis & 0b100 != 0 && in_phase(8))
+    {
+        signal *= EMPHASIS_ATTENUATION;
+    }
+    (signal - BLACK) / (WHITE - BLACK)
+}
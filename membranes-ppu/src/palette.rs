@@ -0,0 +1,105 @@
+//! Conversion of PPU colors to RGB.
+
+/// Colors with every emphasis combination: 8 sets of 64.
+pub const PALETTE_LEN: usize = 512;
+
+/// How much emphasizing the other channels dims a channel.
+const EMPHASIS_ATTENUATION: f32 = 0.816;
+
+/// 2C02 colors without emphasis.
+#[rustfmt::skip]
+const NTSC: [[u8; 3]; 64] = [
+    [0x80, 0x80, 0x80], [0x00, 0x3D, 0xA6], [0x00, 0x12, 0xB0], [0x44, 0x00, 0x96],
+    [0xA1, 0x00, 0x5E], [0xC7, 0x00, 0x28], [0xBA, 0x06, 0x00], [0x8C, 0x17, 0x00],
+    [0x5C, 0x2F, 0x00], [0x10, 0x45, 0x00], [0x05, 0x4A, 0x00], [0x00, 0x47, 0x2E],
+    [0x00, 0x41, 0x66], [0x00, 0x00, 0x00], [0x05, 0x05, 0x05], [0x05, 0x05, 0x05],
+    [0xC7, 0xC7, 0xC7], [0x00, 0x77, 0xFF], [0x21, 0x55, 0xFF], [0x82, 0x37, 0xFA],
+    [0xEB, 0x2F, 0xB5], [0xFF, 0x29, 0x50], [0xFF, 0x22, 0x00], [0xD6, 0x32, 0x00],
+    [0xC4, 0x62, 0x00], [0x35, 0x80, 0x00], [0x05, 0x8F, 0x00], [0x00, 0x8A, 0x55],
+    [0x00, 0x99, 0xCC], [0x21, 0x21, 0x21], [0x09, 0x09, 0x09], [0x09, 0x09, 0x09],
+    [0xFF, 0xFF, 0xFF], [0x0F, 0xD7, 0xFF], [0x69, 0xA2, 0xFF], [0xD4, 0x80, 0xFF],
+    [0xFF, 0x45, 0xF3], [0xFF, 0x61, 0x8B], [0xFF, 0x88, 0x33], [0xFF, 0x9C, 0x12],
+    [0xFA, 0xBC, 0x20], [0x9F, 0xE3, 0x0E], [0x2B, 0xF0, 0x35], [0x0C, 0xF0, 0xA4],
+    [0x05, 0xFB, 0xFF], [0x5E, 0x5E, 0x5E], [0x0D, 0x0D, 0x0D], [0x0D, 0x0D, 0x0D],
+    [0xFF, 0xFF, 0xFF], [0xA6, 0xFC, 0xFF], [0xB3, 0xEC, 0xFF], [0xDA, 0xAB, 0xEB],
+    [0xFF, 0xA8, 0xF9], [0xFF, 0xAB, 0xB3], [0xFF, 0xD2, 0xB0], [0xFF, 0xEF, 0xA6],
+    [0xFF, 0xF7, 0x9C], [0xD7, 0xE8, 0x95], [0xA6, 0xED, 0xAF], [0xA2, 0xF2, 0xDA],
+    [0x99, 0xFF, 0xFC], [0xDD, 0xDD, 0xDD], [0x11, 0x11, 0x11], [0x11, 0x11, 0x11],
+];
+
+/// RGB for each 9-bit framebuffer pixel: color in bits 0-5, PPUMASK emphasis in bits 6-8.
+#[derive(Clone)]
+pub struct Palette {
+    colors: Vec<[u8; 3]>,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::with_emphasis(&NTSC)
+    }
+}
+
+impl Palette {
+    /// The built-in NTSC palette.
+    pub fn ntsc() -> Self {
+        Self::default()
+    }
+
+    /// Parses a .pal file of 64 colors, emphasis then approximated, or all 512.
+    pub fn from_pal(data: &[u8]) -> Result<Self, String> {
+        let colors: Vec<[u8; 3]> = data
+            .chunks_exact(3)
+            .map(|rgb| [rgb[0], rgb[1], rgb[2]])
+            .collect();
+        match data.len() {
+            192 => Ok(Self::with_emphasis(&colors)),
+            1536 => Ok(Self { colors }),
+            len => Err(format!(
+                "Palette should be 192 or 1536 bytes, found {}",
+                len
+            )),
+        }
+    }
+
+    /// Emphasizing red, green or blue darkens the other two, so emphasizing all
+    /// three darkens everything.
+    fn with_emphasis(base: &[[u8; 3]]) -> Self {
+        let mut colors = Vec::with_capacity(PALETTE_LEN);
+        for emphasis in 0..8_u8 {
+            for (index, &color) in base.iter().enumerate() {
+                // Columns $E and $F are black and stay black.
+                if emphasis == 0 || index & 0x0E == 0x0E {
+                    colors.push(color);
+                    continue;
+                }
+                let mut color = color;
+                for (channel, value) in color.iter_mut().enumerate() {
+                    let others = (emphasis & !(1 << channel)).count_ones() as i32;
+                    *value = (f32::from(*value) * EMPHASIS_ATTENUATION.powi(others)) as u8;
+                }
+                colors.push(color);
+            }
+        }
+        Self { colors }
+    }
+
+    pub fn rgb(&self, pixel: u16) -> [u8; 3] {
+        self.colors[usize::from(pixel) % PALETTE_LEN]
+    }
+
+    /// Converts framebuffer pixels to 3 bytes each.
+    pub fn to_rgb24(&self, pixels: &[u16]) -> Vec<u8> {
+        pixels.iter().flat_map(|&pixel| self.rgb(pixel)).collect()
+    }
+
+    /// Converts framebuffer pixels to 4 bytes each, fully opaque.
+    pub fn to_rgba(&self, pixels: &[u16]) -> Vec<u8> {
+        pixels
+            .iter()
+            .flat_map(|&pixel| {
+                let [r, g, b] = self.rgb(pixel);
+                [r, g, b, 0xFF]
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,178 @@
+//! Sprite evaluation, fetches and per-pixel lookup.
+
+use crate::{Bus, Ctrl, Ppu, Status};
+
+/// Sprites drawn on one scanline.
+pub const LINE_SPRITES: usize = 8;
+
+const BEHIND_BACKGROUND: u8 = 0b0010_0000;
+const FLIP_HORIZONTAL: u8 = 0b0100_0000;
+const FLIP_VERTICAL: u8 = 0b1000_0000;
+
+#[derive(Default, Clone, Copy)]
+struct Sprite {
+    y: u8,
+    tile: u8,
+    attribute: u8,
+    x: u8,
+    pattern_low: u8,
+    pattern_high: u8,
+}
+
+/// Opaque sprite pixel at a dot.
+pub(crate) struct Pixel {
+    /// Palette entry (16-31)
+    pub(crate) color: u8,
+    pub(crate) behind_background: bool,
+}
+
+#[derive(Default)]
+pub(crate) struct Sprites {
+    /// Found by evaluation, then fetched for the next line.
+    line: [Sprite; LINE_SPRITES],
+    count: usize,
+    /// Whether `line[0]` is sprite 0.
+    zero: bool,
+}
+
+impl Sprites {
+    /// The front-most opaque sprite pixel and sprite 0's own pixel, if opaque.
+    pub(crate) fn pixel(&self, x: u8) -> (Option<Pixel>, bool) {
+        let mut front = None;
+        let mut zero = false;
+        for (index, sprite) in self.line[..self.count].iter().enumerate() {
+            let column = match x.checked_sub(sprite.x) {
+                Some(column @ 0..=7) => column,
+                _ => continue,
+            };
+            let bit = 0x80 >> column;
+            let pattern = u8::from(sprite.pattern_low & bit != 0)
+                | u8::from(sprite.pattern_high & bit != 0) << 1;
+            if pattern == 0 {
+                continue;
+            }
+            if index == 0 && self.zero {
+                zero = true;
+            }
+            if front.is_none() {
+                front = Some(Pixel {
+                    color: 0x10 | (sprite.attribute & 0b11) << 2 | pattern,
+                    behind_background: sprite.attribute & BEHIND_BACKGROUND != 0,
+                });
+            }
+        }
+        (front, zero)
+    }
+}
+
+impl Ppu {
+    fn sprite_height(&self) -> u16 {
+        if self.ctrl.contains(Ctrl::TALL_SPRITES) {
+            16
+        } else {
+            8
+        }
+    }
+
+    /// Finds the first 8 sprites on the current line, to draw on the next.
+    /// Past 8, the overflow check wrongly steps through each sprite's bytes
+    /// as if they were Y coordinates, like the hardware does.
+    pub(crate) fn evaluate_sprites(&mut self) {
+        let height = self.sprite_height();
+        let line = self.scanline;
+        let in_range = |y: u8| line.wrapping_sub(u16::from(y)) < height;
+
+        let sprites = &mut self.sprites;
+        sprites.count = 0;
+        sprites.zero = false;
+        let mut n = 0;
+        while n < 64 && sprites.count < LINE_SPRITES {
+            let entry = &self.oam[n * 4..n * 4 + 4];
+            if in_range(entry[0]) {
+                sprites.line[sprites.count] = Sprite {
+                    y: entry[0],
+                    tile: entry[1],
+                    attribute: entry[2],
+                    x: entry[3],
+                    ..Default::default()
+                };
+                sprites.zero |= n == 0;
+                sprites.count += 1;
+            }
+            n += 1;
+        }
+        let mut m = 0;
+        while n < 64 {
+            if in_range(self.oam[n * 4 + m]) {
+                self.status.insert(Status::SPRITE_OVERFLOW);
+                break;
+            }
+            n += 1;
+            m = (m + 1) & 0b11;
+        }
+    }
+
+    /// Nothing is evaluated on the pre-render line, so nothing is drawn on line 0.
+    pub(crate) fn clear_sprites(&mut self) {
+        self.sprites.count = 0;
+        self.sprites.zero = false;
+    }
+
+    /// One step of the 8-dot fetch for each sprite slot, on dots 257-320.
+    /// Empty slots still fetch tile $FF, which boards watching A12 rely on.
+    pub(crate) fn fetch_sprite(&mut self, bus: &mut impl Bus) {
+        let slot = usize::from(self.dot - 257) / 8;
+        match (self.dot - 257) & 0b111 {
+            // The background's nametable and attribute fetches, with the results unused.
+            0 | 2 => {
+                bus.read_u8(0x2000 | self.v & 0x0FFF);
+            }
+            step @ (4 | 6) => {
+                let address =
+                    self.sprite_pattern_address(slot) | if step == 6 { 0b1000 } else { 0 };
+                let mut data = bus.read_u8(address);
+                if let Some(sprite) = self.sprites.line[..self.sprites.count].get_mut(slot) {
+                    if sprite.attribute & FLIP_HORIZONTAL != 0 {
+                        data = data.reverse_bits();
+                    }
+                    if step == 4 {
+                        sprite.pattern_low = data;
+                    } else {
+                        sprite.pattern_high = data;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn sprite_pattern_address(&self, slot: usize) -> u16 {
+        let (tile, attribute, row) = match self.sprites.line[..self.sprites.count].get(slot) {
+            Some(sprite) => (
+                sprite.tile,
+                sprite.attribute,
+                self.scanline.wrapping_sub(u16::from(sprite.y)),
+            ),
+            None => (0xFF, 0x00, 0),
+        };
+        let height = self.sprite_height();
+        let row = if attribute & FLIP_VERTICAL != 0 {
+            height - 1 - row
+        } else {
+            row
+        } & (height - 1);
+        if height == 16 {
+            // Bit 0 of the tile picks the pattern table, the bottom half is the next tile.
+            let table = u16::from(tile & 1) << 12;
+            let tile = u16::from(tile & 0xFE) + (row >> 3);
+            table | tile << 4 | row & 0b111
+        } else {
+            let table = if self.ctrl.contains(Ctrl::SPRITE_TABLE) {
+                0x1000
+            } else {
+                0x0000
+            };
+            table | u16::from(tile) << 4 | row
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use crate::helpers::{memory, pixel, ppu, run_dots, run_frame};
+use membranes_ppu::{Ctrl, Mask, Ppu, DOTS, HEIGHT, WIDTH};
+
+/// Tile 1 is solid color 1, tile 2 solid color 3.
+fn patterns(bus: &mut [u8]) {
+    bus[0x0010..0x0018].fill(0xFF);
+    bus[0x0020..0x0030].fill(0xFF);
+}
+
+fn rendering() -> Ppu {
+    let mut ppu = ppu();
+    ppu.mask = Mask::BACKGROUND | Mask::BACKGROUND_LEFT;
+    ppu
+}
+
+#[test]
+fn frame_timing() {
+    let mut ppu = Ppu::new();
+    let mut bus = memory();
+    run_frame(&mut ppu, &mut bus);
+    assert_eq!((ppu.frame, ppu.scanline, ppu.dot), (1, 0, 0));
+    run_dots(&mut ppu, 341 * 262, &mut bus);
+    assert_eq!((ppu.frame, ppu.scanline, ppu.dot), (2, 0, 0));
+    assert_eq!(ppu.framebuffer.len(), WIDTH * HEIGHT);
+}
+
+#[test]
+fn backdrop_when_disabled() {
+    let mut ppu = Ppu::new();
+    let mut bus = memory();
+    ppu.palette[0] = 0x21;
+    run_frame(&mut ppu, &mut bus);
+    assert!(ppu.framebuffer.iter().all(|&color| color == 0x21));
+}
+
+#[test]
+fn palette_address_when_disabled() {
+    let mut ppu = Ppu::new();
+    let mut bus = memory();
+    ppu.palette[0x05] = 0x16;
+    ppu.v = 0x3F05;
+    run_frame(&mut ppu, &mut bus);
+    assert_eq!(pixel(&ppu, 100, 100), 0x16);
+}
+
+#[test]
+fn tiles() {
+    let mut ppu = rendering();
+    let mut bus = memory();
+    patterns(&mut bus);
+    bus[0x2000] = 1;
+    bus[0x2001] = 2;
+    // Top-right quadrant of the first attribute byte uses palette 2.
+    bus[0x23C0] = 0b10 << 2;
+    run_frame(&mut ppu, &mut bus);
+    run_frame(&mut ppu, &mut bus);
+
+    assert_eq!(pixel(&ppu, 0, 0), 1);
+    assert_eq!(pixel(&ppu, 7, 7), 1);
+    assert_eq!(pixel(&ppu, 0, 8), 0);
+    assert_eq!(pixel(&ppu, 8, 0), 3);
+    assert_eq!(pixel(&ppu, 16, 0), 0);
+    assert_eq!(pixel(&ppu, 16, 16), 0);
+    bus[0x2002] = 1;
+    run_frame(&mut ppu, &mut bus);
+    assert_eq!(pixel(&ppu, 16, 0), 2 << 2 | 1);
+}
+
+#[test]
+fn fine_x_scroll() {
+    let mut ppu = rendering();
+    let mut bus = memory();
+    patterns(&mut bus);
+    bus[0x2001] = 1;
+    ppu.write_register(0x2005, 3, &mut bus);
+    ppu.write_register(0x2005, 0, &mut bus);
+    run_frame(&mut ppu, &mut bus);
+    run_frame(&mut ppu, &mut bus);
+    assert_eq!(pixel(&ppu, 4, 0), 0);
+    assert_eq!(pixel(&ppu, 5, 0), 1);
+    assert_eq!(pixel(&ppu, 12, 0), 1);
+    assert_eq!(pixel(&ppu, 13, 0), 0);
+}
+
+#[test]
+fn coarse_x_wraps_into_next_nametable() {
+    let mut ppu = rendering();
+    let mut bus = memory();
+    patterns(&mut bus);
+    bus[0x2400] = 1;
+    ppu.write_register(0x2005, 248, &mut bus);
+    ppu.write_register(0x2005, 0, &mut bus);
+    run_frame(&mut ppu, &mut bus);
+    run_frame(&mut ppu, &mut bus);
+    assert_eq!(pixel(&ppu, 7, 0), 0);
+    assert_eq!(pixel(&ppu, 8, 0), 1);
+}
+
+#[test]
+fn coarse_y_wraps_into_next_nametable() {
+    let mut ppu = rendering();
+    let mut bus = memory();
+    patterns(&mut bus);
+    bus[0x2800] = 1;
+    ppu.write_register(0x2005, 0, &mut bus);
+    ppu.write_register(0x2005, 232, &mut bus);
+    run_frame(&mut ppu, &mut bus);
+    run_frame(&mut ppu, &mut bus);
+    assert_eq!(pixel(&ppu, 0, 7), 0);
+    assert_eq!(pixel(&ppu, 0, 8), 1);
+}
+
+#[test]
+fn left_clipping() {
+    let mut ppu = rendering();
+    let mut bus = memory();
+    patterns(&mut bus);
+    bus[0x2000..0x2020].fill(1);
+    ppu.mask.remove(Mask::BACKGROUND_LEFT);
+    run_frame(&mut ppu, &mut bus);
+    run_frame(&mut ppu, &mut bus);
+    assert_eq!(pixel(&ppu, 7, 0), 0);
+    assert_eq!(pixel(&ppu, 8, 0), 1);
+}
+
+#[test]
+fn pattern_table_select() {
+    let mut ppu = rendering();
+    let mut bus = memory();
+    bus[0x1010..0x1018].fill(0xFF);
+    bus[0x2000] = 1;
+    ppu.ctrl = Ctrl::BACKGROUND_TABLE;
+    run_frame(&mut ppu, &mut bus);
+    run_frame(&mut ppu, &mut bus);
+    assert_eq!(pixel(&ppu, 0, 0), 1);
+}
+
+#[test]
+fn scroll_increments() {
+    let mut ppu = rendering();
+    let mut bus = memory();
+    run_frame(&mut ppu, &mut bus);
+    // Two tiles were prefetched on the pre-render line, then 31 more crossed into
+    // the next nametable.
+    run_dots(&mut ppu, 256, &mut bus);
+    assert_eq!(ppu.v, 0x0401);
+    // Dot 256 bumps coarse X and fine Y, dot 257 restores coarse X from t.
+    ppu.clock(&mut bus);
+    assert_eq!(ppu.v, 0x1402);
+    ppu.clock(&mut bus);
+    assert_eq!(ppu.v, 0x1000);
+    run_dots(&mut ppu, usize::from(DOTS) - 258, &mut bus);
+    assert_eq!(ppu.v, 0x1002);
+}
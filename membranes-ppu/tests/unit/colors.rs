@@ -0,0 +1,70 @@
+use membranes_ppu::{Mask, Palette, Ppu, PALETTE_LEN, WIDTH};
+
+#[test]
+fn ntsc() {
+    let palette = Palette::ntsc();
+    assert_eq!(palette.rgb(0x0F), [0x05, 0x05, 0x05]);
+    assert_eq!(palette.rgb(0x30), [0xFF, 0xFF, 0xFF]);
+}
+
+#[test]
+fn emphasis_dims_other_channels() {
+    let palette = Palette::ntsc();
+    // Red emphasis on white.
+    let [r, g, b] = palette.rgb(0b001 << 6 | 0x30);
+    assert_eq!(r, 0xFF);
+    assert!(g < 0xFF && b < 0xFF);
+    // All three dim everything.
+    let [r, g, b] = palette.rgb(0b111 << 6 | 0x30);
+    assert!(r < 0xFF && r == g && g == b);
+    // Black columns are untouched.
+    assert_eq!(palette.rgb(0b010 << 6 | 0x1E), palette.rgb(0x1E));
+}
+
+#[test]
+fn pal_64() {
+    let pal: Vec<u8> = (0..64).flat_map(|i| [i, i * 2, i * 3]).collect();
+    let palette = Palette::from_pal(&pal).unwrap();
+    assert_eq!(palette.rgb(0x21), [0x21, 0x42, 0x63]);
+    assert_ne!(palette.rgb(0b100 << 6 | 0x21), [0x21, 0x42, 0x63]);
+}
+
+#[test]
+fn pal_512() {
+    let pal: Vec<u8> = (0..PALETTE_LEN)
+        .flat_map(|i| [(i >> 6) as u8, i as u8 & 0x3F, 0])
+        .collect();
+    let palette = Palette::from_pal(&pal).unwrap();
+    assert_eq!(palette.rgb(0b101 << 6 | 0x21), [0b101, 0x21, 0]);
+}
+
+#[test]
+fn pal_wrong_size() {
+    assert!(Palette::from_pal(&[0; 100]).is_err());
+}
+
+#[test]
+fn conversions() {
+    let palette = Palette::ntsc();
+    let pixels = [0x00, 0x30];
+    assert_eq!(
+        palette.to_rgb24(&pixels),
+        [0x80, 0x80, 0x80, 0xFF, 0xFF, 0xFF]
+    );
+    assert_eq!(
+        palette.to_rgba(&pixels),
+        [0x80, 0x80, 0x80, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]
+    );
+}
+
+#[test]
+fn framebuffer_has_emphasis() {
+    let mut ppu = Ppu::new();
+    let mut bus = vec![0x00; 0x3000];
+    ppu.palette[0] = 0x21;
+    ppu.mask = Mask::EMPHASIZE_GREEN | Mask::EMPHASIZE_BLUE;
+    while ppu.scanline == 0 {
+        ppu.clock(&mut bus);
+    }
+    assert_eq!(ppu.framebuffer[WIDTH - 1], 0b110 << 6 | 0x21);
+}
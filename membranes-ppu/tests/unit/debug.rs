@@ -0,0 +1,127 @@
+use crate::helpers::{self, ppu};
+use membranes_ppu::debug::{NAMETABLES_WIDTH, PATTERN_TABLES_WIDTH};
+use membranes_ppu::{Ctrl, Palette};
+
+/// Tile 1 is solid color 1, tile 2 has only its top-left pixel set to color 3.
+fn memory() -> Vec<u8> {
+    let mut bus = helpers::memory();
+    bus[0x0010..0x0018].fill(0xFF);
+    bus[0x0020] = 0x80;
+    bus[0x0028] = 0x80;
+    bus
+}
+
+fn pixel(rgba: &[u8], width: usize, x: usize, y: usize) -> [u8; 4] {
+    let offset = (y * width + x) * 4;
+    rgba[offset..offset + 4].try_into().unwrap()
+}
+
+fn color(colors: &Palette, index: u16) -> [u8; 4] {
+    let [r, g, b] = colors.rgb(index);
+    [r, g, b, 0xFF]
+}
+
+#[test]
+fn pattern_tables() {
+    let ppu = ppu();
+    let mut bus = memory();
+    bus[0x1000] = 0xFF;
+    let colors = Palette::ntsc();
+    let rgba = ppu.debug_pattern_tables(2, &colors, &mut bus);
+    let at = |x, y| pixel(&rgba, PATTERN_TABLES_WIDTH, x, y);
+    assert_eq!(at(0, 0), color(&colors, 0x00));
+    assert_eq!(at(8, 0), color(&colors, 0x09));
+    assert_eq!(at(16, 0), color(&colors, 0x0B));
+    assert_eq!(at(17, 0), color(&colors, 0x00));
+    assert_eq!(at(128, 0), color(&colors, 0x09));
+    assert_eq!(at(128, 1), color(&colors, 0x00));
+}
+
+#[test]
+fn nametables_use_attributes() {
+    let mut ppu = ppu();
+    ppu.ctrl = Ctrl::empty();
+    let mut bus = memory();
+    // Tile 1 at the top left of nametable 1, on attribute quadrant 3 (bottom right).
+    bus[0x2400 + 2 * 32 + 2] = 0x01;
+    bus[0x2400 + 0x3C0] = 0b1100_0000;
+    let colors = Palette::ntsc();
+    // Scroll to nametable 2, coarse X 16 and Y 15, so the outline is out of the way.
+    ppu.t = 2 << 10 | 15 << 5 | 16;
+    let rgba = ppu.debug_nametables(&colors, &mut bus);
+    let at = |x, y| pixel(&rgba, NAMETABLES_WIDTH, x, y);
+    assert_eq!(at(256 + 16, 16), color(&colors, 0x0D));
+    assert_eq!(at(256 + 24, 16), color(&colors, 0x00));
+}
+
+#[test]
+fn scroll_outline_wraps() {
+    let mut ppu = ppu();
+    let mut bus = memory();
+    let colors = Palette::ntsc();
+    // Coarse X 31, fine X 7 on nametable 1: the screen starts 1 pixel before the right edge.
+    ppu.t = 1 << 10 | 31;
+    ppu.x = 7;
+    let rgba = ppu.debug_nametables(&colors, &mut bus);
+    let at = |x, y| pixel(&rgba, NAMETABLES_WIDTH, x, y);
+    let outline = [0xFF, 0x00, 0xFF, 0xFF];
+    assert_eq!(at(511, 100), outline);
+    assert_eq!(at(254, 100), outline);
+    assert_eq!(at(100, 0), outline);
+    assert_eq!(at(100, 239), outline);
+    assert_ne!(at(300, 100), outline);
+}
+
+#[test]
+fn sprites() {
+    let mut ppu = ppu();
+    let mut bus = memory();
+    let colors = Palette::ntsc();
+    ppu.oam[4..8].copy_from_slice(&[0x20, 0x02, 0b0100_0001, 0x30]);
+    let sprites = ppu.debug_sprites(&colors, &mut bus);
+    assert_eq!(sprites.len(), 64);
+    let sprite = &sprites[1];
+    assert_eq!(
+        (
+            sprite.index,
+            sprite.y,
+            sprite.tile,
+            sprite.attribute,
+            sprite.x
+        ),
+        (1, 0x20, 0x02, 0b0100_0001, 0x30)
+    );
+    assert_eq!(sprite.height, 8);
+    // Flipped horizontally, so the single pixel is top-right.
+    assert_eq!(pixel(&sprite.rgba, 8, 7, 0), color(&colors, 0x17));
+    assert_eq!(pixel(&sprite.rgba, 8, 0, 0), [0x00; 4]);
+}
+
+#[test]
+fn tall_sprites_flip_vertically() {
+    let mut ppu = ppu();
+    ppu.ctrl = Ctrl::TALL_SPRITES;
+    let mut bus = memory();
+    // Last row of the bottom half of tile pair $02/$03 in the $1000 table.
+    bus[0x1037] = 0x01;
+    let colors = Palette::ntsc();
+    ppu.oam[0..4].copy_from_slice(&[0x00, 0x03, 0b1000_0000, 0x00]);
+    let sprite = &ppu.debug_sprites(&colors, &mut bus)[0];
+    assert_eq!(sprite.height, 16);
+    assert_eq!(sprite.rgba.len(), 8 * 16 * 4);
+    assert_eq!(pixel(&sprite.rgba, 8, 7, 0), color(&colors, 0x11));
+    assert_eq!(pixel(&sprite.rgba, 8, 7, 15), [0x00; 4]);
+}
+
+#[test]
+fn palette() {
+    let mut ppu = ppu();
+    ppu.palette[0x10] = 0x30;
+    let colors = Palette::ntsc();
+    let rgba = ppu.debug_palette(&colors);
+    assert_eq!(rgba.len(), 32 * 4);
+    assert_eq!(pixel(&rgba, 16, 5, 0), color(&colors, 0x05));
+    assert_eq!(pixel(&rgba, 16, 1, 1), color(&colors, 0x11));
+    // $3F10 mirrors $3F00.
+    assert_eq!(pixel(&rgba, 16, 0, 1), color(&colors, 0x00));
+}
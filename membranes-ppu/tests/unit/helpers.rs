@@ -0,0 +1,42 @@
+use membranes_ppu::{Ppu, WIDTH};
+
+/// The whole $0000-$3FFF address space, so pattern tables and four nametables with no mirroring.
+pub fn memory() -> Vec<u8> {
+    vec![0x00; 0x4000]
+}
+
+/// Palette entries are their own index.
+pub fn ppu() -> Ppu {
+    let mut ppu = Ppu::new();
+    for (index, color) in ppu.palette.iter_mut().enumerate() {
+        *color = index as u8;
+    }
+    ppu
+}
+
+pub fn run_dots(ppu: &mut Ppu, dots: usize, bus: &mut Vec<u8>) {
+    for _ in 0..dots {
+        ppu.clock(bus);
+    }
+}
+
+pub fn run_to(ppu: &mut Ppu, scanline: u16, dot: u16, bus: &mut Vec<u8>) {
+    while (ppu.scanline, ppu.dot) != (scanline, dot) {
+        ppu.clock(bus);
+    }
+}
+
+/// Runs to the start of the next frame, returning how many dots that took.
+pub fn run_frame(ppu: &mut Ppu, bus: &mut Vec<u8>) -> usize {
+    let frame = ppu.frame;
+    let mut dots = 0;
+    while ppu.frame == frame {
+        ppu.clock(bus);
+        dots += 1;
+    }
+    dots
+}
+
+pub fn pixel(ppu: &Ppu, x: usize, y: usize) -> u16 {
+    ppu.framebuffer[y * WIDTH + x]
+}
@@ -0,0 +1,11 @@
+mod background;
+mod colors;
+mod debug;
+mod memory;
+mod ntsc;
+mod palette;
+mod registers;
+mod sprites;
+mod vblank;
+
+mod helpers;
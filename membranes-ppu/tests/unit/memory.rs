@@ -0,0 +1,137 @@
+use membranes_ppu::{Bus, Memory, MemoryMap, Ppu, CIRAM_LEN};
+
+/// Cartridge with CHR-RAM and a fixed nametable arrangement.
+struct Board {
+    chr: Vec<u8>,
+    pages: [usize; 4],
+    /// Substitutes its own byte for nametable 3 when set.
+    fill: Option<u8>,
+    addresses: Vec<u16>,
+}
+
+impl Board {
+    fn new(pages: [usize; 4]) -> Self {
+        Self {
+            chr: vec![0x00; 0x2000],
+            pages,
+            fill: None,
+            addresses: Vec::new(),
+        }
+    }
+}
+
+impl Memory for Board {
+    fn read_pattern(&mut self, address: u16) -> u8 {
+        self.chr[usize::from(address)]
+    }
+
+    fn write_pattern(&mut self, address: u16, data: u8) {
+        self.chr[usize::from(address)] = data;
+    }
+
+    fn nametable_page(&self, nametable: usize) -> usize {
+        self.pages[nametable]
+    }
+
+    fn read_nametable(&mut self, address: u16, ciram: u8) -> u8 {
+        match self.fill {
+            Some(fill) if address >= 0x2C00 => fill,
+            _ => ciram,
+        }
+    }
+
+    fn write_nametable(&mut self, address: u16, _data: u8) -> bool {
+        self.fill.is_some() && address >= 0x2C00
+    }
+
+    fn observe_address(&mut self, address: u16) {
+        self.addresses.push(address);
+    }
+}
+
+#[test]
+fn pattern_tables() {
+    let mut ciram = [0x00; CIRAM_LEN];
+    let mut board = Board::new([0, 0, 1, 1]);
+    let mut bus = MemoryMap {
+        ciram: &mut ciram,
+        memory: &mut board,
+    };
+    bus.write_u8(0x1234, 0x56);
+    assert_eq!(bus.read_u8(0x1234), 0x56);
+    assert_eq!(board.chr[0x1234], 0x56);
+}
+
+#[test]
+fn horizontal() {
+    let mut ciram = [0x00; CIRAM_LEN];
+    let mut board = Board::new([0, 0, 1, 1]);
+    let mut bus = MemoryMap {
+        ciram: &mut ciram,
+        memory: &mut board,
+    };
+    bus.write_u8(0x2005, 0x11);
+    bus.write_u8(0x2805, 0x22);
+    assert_eq!(bus.read_u8(0x2405), 0x11);
+    assert_eq!(bus.read_u8(0x2C05), 0x22);
+    assert_eq!((ciram[0x005], ciram[0x405]), (0x11, 0x22));
+}
+
+#[test]
+fn vertical() {
+    let mut ciram = [0x00; CIRAM_LEN];
+    let mut board = Board::new([0, 1, 0, 1]);
+    let mut bus = MemoryMap {
+        ciram: &mut ciram,
+        memory: &mut board,
+    };
+    bus.write_u8(0x2005, 0x11);
+    bus.write_u8(0x2405, 0x22);
+    assert_eq!(bus.read_u8(0x2805), 0x11);
+    assert_eq!(bus.read_u8(0x2C05), 0x22);
+}
+
+#[test]
+fn four_screen() {
+    let mut ciram = [0x00; CIRAM_LEN];
+    let mut board = Board::new([0, 1, 2, 3]);
+    let mut bus = MemoryMap {
+        ciram: &mut ciram,
+        memory: &mut board,
+    };
+    for nametable in 0..4 {
+        bus.write_u8(0x2000 + nametable * 0x400, nametable as u8 + 1);
+    }
+    assert_eq!(
+        [ciram[0x000], ciram[0x400], ciram[0x800], ciram[0xC00]],
+        [1, 2, 3, 4]
+    );
+}
+
+#[test]
+fn board_nametable() {
+    let mut ciram = [0x00; CIRAM_LEN];
+    let mut board = Board::new([0, 1, 0, 1]);
+    board.fill = Some(0xEE);
+    let mut bus = MemoryMap {
+        ciram: &mut ciram,
+        memory: &mut board,
+    };
+    bus.write_u8(0x2C00, 0x33);
+    assert_eq!(bus.read_u8(0x2C00), 0xEE);
+    assert_eq!(bus.read_u8(0x2400), 0x00);
+}
+
+#[test]
+fn addresses_observed() {
+    let mut ciram = [0x00; CIRAM_LEN];
+    let mut board = Board::new([0, 0, 1, 1]);
+    let mut ppu = Ppu::new();
+    let mut bus = MemoryMap {
+        ciram: &mut ciram,
+        memory: &mut board,
+    };
+    ppu.write_vram(0x3123, 0x00, &mut bus);
+    ppu.read_vram(0x1000, &mut bus);
+    assert_eq!(board.addresses, [0x2123, 0x1000]);
+}
@@ -0,0 +1,96 @@
+use membranes_ppu::{NtscFilter, NtscSettings, HEIGHT, NTSC_WIDTH, WIDTH};
+
+fn frame(pixel: u16) -> Vec<u16> {
+    vec![pixel; WIDTH * HEIGHT]
+}
+
+/// RGB in the middle of the picture.
+fn center(output: &[u8]) -> [u8; 3] {
+    let offset = (HEIGHT / 2 * NTSC_WIDTH + NTSC_WIDTH / 2) * 4;
+    [output[offset], output[offset + 1], output[offset + 2]]
+}
+
+#[test]
+fn widened_rgba() {
+    let output = NtscFilter::default().filter_rgba(&frame(0x0F), 0);
+    assert_eq!(output.len(), NTSC_WIDTH * HEIGHT * 4);
+    assert!(output.chunks(4).all(|pixel| pixel == [0, 0, 0, 0xFF]));
+}
+
+#[test]
+fn greys() {
+    let mut filter = NtscFilter::default();
+    let [r, g, b] = center(&filter.filter_rgba(&frame(0x30), 0));
+    assert_eq!([r, g, b], [0xFF; 3]);
+    let [r, g, b] = center(&filter.filter_rgba(&frame(0x00), 0));
+    assert!(r == g && g == b && r > 0x40 && r < 0xC0);
+}
+
+#[test]
+fn hues() {
+    let mut filter = NtscFilter::default();
+    // $16 is red, $1A green, $12 blue.
+    let [r, g, b] = center(&filter.filter_rgba(&frame(0x16), 0));
+    assert!(r > g && r > b, "{r} {g} {b}");
+    let [r, g, b] = center(&filter.filter_rgba(&frame(0x1A), 0));
+    assert!(g > r && g > b, "{r} {g} {b}");
+    let [r, g, b] = center(&filter.filter_rgba(&frame(0x12), 0));
+    assert!(b > r && b > g, "{r} {g} {b}");
+}
+
+#[test]
+fn saturation() {
+    let mut filter = NtscFilter::new(NtscSettings {
+        saturation: -1.0,
+        artifacts: -1.0,
+        ..Default::default()
+    });
+    let [r, g, b] = center(&filter.filter_rgba(&frame(0x16), 0));
+    assert!(r.abs_diff(g) <= 1 && g.abs_diff(b) <= 1, "{r} {g} {b}");
+}
+
+#[test]
+fn hue_rotates() {
+    let red = center(&NtscFilter::default().filter_rgba(&frame(0x16), 0));
+    let mut filter = NtscFilter::new(NtscSettings {
+        hue: 1.0,
+        ..Default::default()
+    });
+    assert_ne!(center(&filter.filter_rgba(&frame(0x16), 0)), red);
+}
+
+#[test]
+fn artifacts() {
+    // Without artifacts, a flat color is flat across the line; with them, the
+    // subcarrier leaks into luma as a pattern.
+    let row = |filter: &mut NtscFilter| {
+        let output = filter.filter_rgba(&frame(0x16), 0);
+        let line = &output[HEIGHT / 2 * NTSC_WIDTH * 4..][..NTSC_WIDTH * 4];
+        line[40 * 4..(NTSC_WIDTH - 40) * 4].to_vec()
+    };
+    let clean = row(&mut NtscFilter::new(NtscSettings {
+        artifacts: -1.0,
+        ..Default::default()
+    }));
+    assert!(clean.chunks(4).all(|pixel| pixel == &clean[..4]));
+    let noisy = row(&mut NtscFilter::new(NtscSettings {
+        artifacts: 1.0,
+        sharpness: 1.0,
+        ..Default::default()
+    }));
+    assert!(noisy.chunks(4).any(|pixel| pixel != &noisy[..4]));
+}
+
+#[test]
+fn dot_crawl() {
+    let mut filter = NtscFilter::default();
+    let mut pixels = frame(0x0F);
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        if i & 1 == 0 {
+            *pixel = 0x30;
+        }
+    }
+    let even = filter.filter_rgba(&pixels, 0);
+    let odd = filter.filter_rgba(&pixels, 1);
+    assert_ne!(even, odd);
+}
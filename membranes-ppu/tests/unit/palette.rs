@@ -0,0 +1,71 @@
+use crate::helpers::memory;
+use membranes_ppu::{Mask, Ppu};
+
+#[test]
+fn backdrop_mirrors() {
+    let mut ppu = Ppu::new();
+    let mut bus = memory();
+    for (address, data) in [
+        (0x3F10, 0x01),
+        (0x3F14, 0x02),
+        (0x3F18, 0x03),
+        (0x3F1C, 0x04),
+    ] {
+        ppu.write_vram(address, data, &mut bus);
+    }
+    assert_eq!(
+        [
+            ppu.palette[0x00],
+            ppu.palette[0x04],
+            ppu.palette[0x08],
+            ppu.palette[0x0C]
+        ],
+        [0x01, 0x02, 0x03, 0x04]
+    );
+    assert_eq!(ppu.palette[0x10], 0x00);
+    assert_eq!(ppu.read_vram(0x3F00, &mut bus), 0x01);
+}
+
+#[test]
+fn sprite_colors_distinct() {
+    let mut ppu = Ppu::new();
+    let mut bus = memory();
+    ppu.write_vram(0x3F11, 0x21, &mut bus);
+    assert_eq!(ppu.read_vram(0x3F01, &mut bus), 0x00);
+    assert_eq!(ppu.read_vram(0x3F11, &mut bus), 0x21);
+}
+
+#[test]
+fn mirrored_every_32_bytes() {
+    let mut ppu = Ppu::new();
+    let mut bus = memory();
+    ppu.write_vram(0x3FE3, 0x16, &mut bus);
+    assert_eq!(ppu.read_vram(0x3F03, &mut bus), 0x16);
+    assert_eq!(ppu.read_vram(0x7F23, &mut bus), 0x16);
+}
+
+#[test]
+fn six_bits() {
+    let mut ppu = Ppu::new();
+    let mut bus = memory();
+    ppu.write_vram(0x3F00, 0xFF, &mut bus);
+    assert_eq!(ppu.read_vram(0x3F00, &mut bus), 0x3F);
+}
+
+#[test]
+fn greyscale() {
+    let mut ppu = Ppu::new();
+    let mut bus = memory();
+    ppu.write_vram(0x3F00, 0x2A, &mut bus);
+    ppu.mask = Mask::GREYSCALE;
+    assert_eq!(ppu.read_vram(0x3F00, &mut bus), 0x20);
+}
+
+#[test]
+fn nametable_mirror_below() {
+    let mut ppu = Ppu::new();
+    let mut bus = memory();
+    ppu.write_vram(0x3456, 0x77, &mut bus);
+    assert_eq!(bus[0x2456], 0x77);
+    assert_eq!(ppu.read_vram(0x3EFF, &mut bus), bus[0x2EFF]);
+}
@@ -0,0 +1,128 @@
+use crate::helpers::memory;
+use membranes_ppu::{Ppu, Status};
+
+fn set_address(ppu: &mut Ppu, address: u16, bus: &mut Vec<u8>) {
+    let [high, low] = address.to_be_bytes();
+    ppu.write_register(0x2006, high, bus);
+    ppu.write_register(0x2006, low, bus);
+}
+
+#[test]
+fn ctrl_nametable_bits() {
+    let mut ppu = Ppu::new();
+    ppu.t = 0x7FFF;
+    ppu.write_register(0x2000, 0b01, &mut memory());
+    assert_eq!(ppu.t, 0x77FF);
+}
+
+#[test]
+fn status_read() {
+    let mut ppu = Ppu::new();
+    let mut bus = memory();
+    ppu.status = Status::VBLANK | Status::SPRITE_ZERO_HIT;
+    ppu.w = true;
+    // Unused bits come from the last value on the data bus.
+    ppu.write_register(0x2000, 0x1F, &mut bus);
+
+    assert_eq!(ppu.read_register(0x2002, &mut bus), 0xDF);
+    assert_eq!(ppu.status, Status::SPRITE_ZERO_HIT);
+    assert!(!ppu.w);
+    assert_eq!(ppu.read_register(0x2002, &mut bus), 0x5F);
+}
+
+#[test]
+fn scroll() {
+    let mut ppu = Ppu::new();
+    let mut bus = memory();
+    // Coarse X 15, fine X 5.
+    ppu.write_register(0x2005, 15 << 3 | 5, &mut bus);
+    assert_eq!((ppu.t, ppu.x, ppu.w), (15, 5, true));
+    // Coarse Y 11, fine Y 6.
+    ppu.write_register(0x2005, 11 << 3 | 6, &mut bus);
+    assert_eq!(ppu.t, 6 << 12 | 11 << 5 | 15);
+    assert!(!ppu.w);
+    assert_eq!(ppu.v, 0);
+}
+
+#[test]
+fn address() {
+    let mut ppu = Ppu::new();
+    let mut bus = memory();
+    ppu.write_register(0x2006, 0xFF, &mut bus);
+    assert_eq!((ppu.t, ppu.v, ppu.w), (0x3F00, 0, true));
+    ppu.write_register(0x2006, 0x12, &mut bus);
+    assert_eq!((ppu.t, ppu.v, ppu.w), (0x3F12, 0x3F12, false));
+}
+
+#[test]
+fn shared_toggle() {
+    let mut ppu = Ppu::new();
+    let mut bus = memory();
+    ppu.write_register(0x2005, 0x00, &mut bus);
+    ppu.write_register(0x2006, 0x34, &mut bus);
+    assert_eq!(ppu.v, 0x0034);
+}
+
+#[test]
+fn buffered_read() {
+    let mut ppu = Ppu::new();
+    let mut bus = memory();
+    bus[0x2000] = 0x11;
+    bus[0x2001] = 0x22;
+    set_address(&mut ppu, 0x2000, &mut bus);
+
+    assert_eq!(ppu.read_register(0x2007, &mut bus), 0x00);
+    assert_eq!(ppu.read_register(0x2007, &mut bus), 0x11);
+    assert_eq!(ppu.read_register(0x2007, &mut bus), 0x22);
+    assert_eq!(ppu.v, 0x2003);
+}
+
+#[test]
+fn palette_read() {
+    let mut ppu = Ppu::new();
+    let mut bus = memory();
+    ppu.palette[0] = 0x0F;
+    bus[0x2F00] = 0x55;
+    set_address(&mut ppu, 0x3F00, &mut bus);
+
+    assert_eq!(ppu.read_register(0x2007, &mut bus), 0x0F);
+    set_address(&mut ppu, 0x0000, &mut bus);
+    assert_eq!(ppu.read_register(0x2007, &mut bus), 0x55);
+}
+
+#[test]
+fn data_write() {
+    let mut ppu = Ppu::new();
+    let mut bus = memory();
+    ppu.write_register(0x2000, 0b100, &mut bus);
+    set_address(&mut ppu, 0x2400, &mut bus);
+    ppu.write_register(0x2007, 0xAA, &mut bus);
+    ppu.write_register(0x2007, 0xBB, &mut bus);
+    assert_eq!((bus[0x2400], bus[0x2420]), (0xAA, 0xBB));
+    assert_eq!(ppu.v, 0x2440);
+}
+
+#[test]
+fn oam() {
+    let mut ppu = Ppu::new();
+    let mut bus = memory();
+    ppu.write_register(0x2003, 0x01, &mut bus);
+    for data in [0xFF, 0xFF, 0x20] {
+        ppu.write_register(0x2004, data, &mut bus);
+    }
+    assert_eq!(ppu.oam[1..4], [0xFF, 0xFF, 0x20]);
+    assert_eq!(ppu.oam_address, 0x04);
+
+    ppu.write_register(0x2003, 0x02, &mut bus);
+    assert_eq!(ppu.read_register(0x2004, &mut bus), 0xE3);
+    assert_eq!(ppu.read_register(0x2004, &mut bus), 0xE3);
+}
+
+#[test]
+fn mirrored_registers() {
+    let mut ppu = Ppu::new();
+    let mut bus = memory();
+    ppu.write_register(0x3FFE, 0x21, &mut bus);
+    ppu.write_register(0x200E, 0x08, &mut bus);
+    assert_eq!(ppu.v, 0x2108);
+}
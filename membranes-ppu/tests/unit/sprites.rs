@@ -0,0 +1,177 @@
+use crate::helpers::{self, pixel, ppu, run_frame, run_to};
+use membranes_ppu::{Ctrl, Mask, Ppu, Status};
+
+/// Tile 1 is solid color 1, tile 3 has only its top-left pixel set.
+fn memory() -> Vec<u8> {
+    let mut bus = helpers::memory();
+    bus[0x0010..0x0018].fill(0xFF);
+    bus[0x0030] = 0x80;
+    bus
+}
+
+/// Sprites and background on, all sprites off screen, palette entries are their own index.
+fn rendering() -> Ppu {
+    let mut ppu = ppu();
+    ppu.mask = Mask::BACKGROUND | Mask::BACKGROUND_LEFT | Mask::SPRITES | Mask::SPRITES_LEFT;
+    ppu.oam.fill(0xFF);
+    ppu
+}
+
+/// Renders a whole frame from the start.
+fn render(ppu: &mut Ppu, bus: &mut Vec<u8>) {
+    run_frame(ppu, bus);
+    run_frame(ppu, bus);
+}
+
+fn sprite(ppu: &mut Ppu, index: usize, [y, tile, attribute, x]: [u8; 4]) {
+    ppu.oam[index * 4..index * 4 + 4].copy_from_slice(&[y, tile, attribute, x]);
+}
+
+#[test]
+fn drawn_one_line_down() {
+    let mut ppu = rendering();
+    let mut bus = memory();
+    sprite(&mut ppu, 0, [20, 1, 0b10, 30]);
+    render(&mut ppu, &mut bus);
+    assert_eq!(pixel(&ppu, 30, 20), 0);
+    assert_eq!(pixel(&ppu, 29, 21), 0);
+    assert_eq!(pixel(&ppu, 30, 21), 0x10 | 2 << 2 | 1);
+    assert_eq!(pixel(&ppu, 37, 28), 0x19);
+    assert_eq!(pixel(&ppu, 38, 28), 0);
+    assert_eq!(pixel(&ppu, 37, 29), 0);
+}
+
+#[test]
+fn flips() {
+    let mut ppu = rendering();
+    let mut bus = memory();
+    sprite(&mut ppu, 0, [20, 3, 0b0100_0000, 30]);
+    sprite(&mut ppu, 1, [40, 3, 0b1000_0000, 30]);
+    render(&mut ppu, &mut bus);
+    assert_eq!(pixel(&ppu, 30, 21), 0);
+    assert_eq!(pixel(&ppu, 37, 21), 0x11);
+    assert_eq!(pixel(&ppu, 30, 41), 0);
+    assert_eq!(pixel(&ppu, 30, 48), 0x11);
+}
+
+#[test]
+fn priority() {
+    let mut ppu = rendering();
+    let mut bus = memory();
+    // Background tile 3 covers (8, 16), both sprites cover it too.
+    bus[0x2041] = 3;
+    sprite(&mut ppu, 0, [15, 1, 0b0010_0001, 8]);
+    sprite(&mut ppu, 1, [15, 1, 0b0000_0010, 8]);
+    render(&mut ppu, &mut bus);
+    // The behind-background sprite still wins against sprite 1, then loses to the tile.
+    assert_eq!(pixel(&ppu, 8, 16), 1);
+    assert_eq!(pixel(&ppu, 9, 16), 0x15);
+}
+
+#[test]
+fn eight_per_line() {
+    let mut ppu = rendering();
+    let mut bus = memory();
+    for index in 0..9 {
+        sprite(
+            &mut ppu,
+            index,
+            [50, 1, index as u8 & 0b11, 20 * index as u8],
+        );
+    }
+    render(&mut ppu, &mut bus);
+    assert_ne!(pixel(&ppu, 140, 51), 0);
+    assert_eq!(pixel(&ppu, 160, 51), 0);
+    run_to(&mut ppu, 60, 0, &mut bus);
+    assert!(ppu.status.contains(Status::SPRITE_OVERFLOW));
+}
+
+#[test]
+fn overflow_false_positive() {
+    let mut ppu = rendering();
+    let mut bus = memory();
+    for index in 0..8 {
+        sprite(&mut ppu, index, [50, 1, 0, 0]);
+    }
+    // After 8 hits the check reads sprite 9's tile number as its Y.
+    sprite(&mut ppu, 9, [0xFF, 50, 0, 0]);
+    run_frame(&mut ppu, &mut bus);
+    run_to(&mut ppu, 60, 0, &mut bus);
+    assert!(ppu.status.contains(Status::SPRITE_OVERFLOW));
+}
+
+#[test]
+fn overflow_cleared_before_render() {
+    let mut ppu = rendering();
+    let mut bus = memory();
+    ppu.status = Status::SPRITE_OVERFLOW | Status::SPRITE_ZERO_HIT;
+    ppu.mask = Mask::empty();
+    run_frame(&mut ppu, &mut bus);
+    assert!(ppu.status.is_empty());
+}
+
+#[test]
+fn tall_sprites() {
+    let mut ppu = rendering();
+    let mut bus = memory();
+    // Tile $13 is $12 and $13 from the right pattern table.
+    bus[0x1120] = 0x80;
+    bus[0x1137] = 0x01;
+    ppu.ctrl = Ctrl::TALL_SPRITES;
+    sprite(&mut ppu, 0, [20, 0x13, 0, 30]);
+    sprite(&mut ppu, 1, [60, 0x13, 0b1100_0000, 30]);
+    render(&mut ppu, &mut bus);
+    assert_eq!(pixel(&ppu, 30, 21), 0x11);
+    assert_eq!(pixel(&ppu, 37, 36), 0x11);
+    assert_eq!(pixel(&ppu, 30, 61), 0x11);
+    assert_eq!(pixel(&ppu, 37, 76), 0x11);
+}
+
+/// Solid background with sprite 0 at (x, y), returning where the hit flag was set.
+fn sprite_zero_hit(ppu: &mut Ppu, x: u8, y: u8) -> Option<(u16, u16)> {
+    let mut bus = memory();
+    bus[0x2000..0x23C0].fill(1);
+    sprite(ppu, 0, [y, 1, 0, x]);
+    run_frame(ppu, &mut bus);
+    while ppu.scanline < 240 {
+        ppu.clock(&mut bus);
+        if ppu.status.contains(Status::SPRITE_ZERO_HIT) {
+            return Some((ppu.scanline, ppu.dot));
+        }
+    }
+    None
+}
+
+#[test]
+fn sprite_zero_hit_timing() {
+    let mut ppu = rendering();
+    // Pixel (40, 31) is drawn on dot 41, so the flag is seen from dot 42.
+    assert_eq!(sprite_zero_hit(&mut ppu, 40, 30), Some((31, 42)));
+}
+
+#[test]
+fn sprite_zero_hit_not_at_255() {
+    let mut ppu = rendering();
+    assert_eq!(sprite_zero_hit(&mut ppu, 255, 30), None);
+    let mut ppu = rendering();
+    assert_eq!(sprite_zero_hit(&mut ppu, 254, 30), Some((31, 256)));
+}
+
+#[test]
+fn sprite_zero_hit_clipped() {
+    let mut ppu = rendering();
+    ppu.mask.remove(Mask::SPRITES_LEFT);
+    assert_eq!(sprite_zero_hit(&mut ppu, 0, 30), None);
+    let mut ppu = rendering();
+    ppu.mask.remove(Mask::BACKGROUND_LEFT);
+    assert_eq!(sprite_zero_hit(&mut ppu, 4, 30), Some((31, 10)));
+}
+
+#[test]
+fn oam_address_reset() {
+    let mut ppu = rendering();
+    let mut bus = memory();
+    ppu.oam_address = 0x40;
+    run_frame(&mut ppu, &mut bus);
+    assert_eq!(ppu.oam_address, 0);
+}
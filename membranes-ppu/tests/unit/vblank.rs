@@ -0,0 +1,133 @@
+use crate::helpers::{memory, run_frame, run_to};
+use membranes_ppu::{Ctrl, Mask, Ppu, Region, Status, DOTS, SCANLINES, VBLANK_SCANLINE};
+
+#[test]
+fn starts_at_241_dot_1() {
+    let mut ppu = Ppu::new();
+    let mut bus = memory();
+    ppu.ctrl = Ctrl::NMI;
+    run_to(&mut ppu, VBLANK_SCANLINE, 1, &mut bus);
+    assert!(!ppu.status.contains(Status::VBLANK));
+    assert!(!ppu.take_nmi());
+    ppu.clock(&mut bus);
+    assert!(ppu.status.contains(Status::VBLANK));
+    assert!(ppu.take_nmi());
+    assert!(!ppu.take_nmi());
+}
+
+#[test]
+fn no_nmi_when_disabled() {
+    let mut ppu = Ppu::new();
+    let mut bus = memory();
+    run_to(&mut ppu, VBLANK_SCANLINE, 2, &mut bus);
+    assert!(ppu.status.contains(Status::VBLANK));
+    assert!(!ppu.take_nmi());
+}
+
+#[test]
+fn ends_at_pre_render_dot_1() {
+    let mut ppu = Ppu::new();
+    let mut bus = memory();
+    run_to(&mut ppu, SCANLINES - 1, 1, &mut bus);
+    assert!(ppu.status.contains(Status::VBLANK));
+    ppu.clock(&mut bus);
+    assert!(!ppu.status.contains(Status::VBLANK));
+}
+
+#[test]
+fn enabling_nmi_in_vblank() {
+    let mut ppu = Ppu::new();
+    let mut bus = memory();
+    run_to(&mut ppu, 250, 0, &mut bus);
+    ppu.write_register(0x2000, 0x80, &mut bus);
+    assert!(ppu.take_nmi());
+    // Only the transition raises one.
+    ppu.write_register(0x2000, 0x80, &mut bus);
+    assert!(!ppu.take_nmi());
+}
+
+#[test]
+fn status_read_before_vblank_suppresses_it() {
+    let mut ppu = Ppu::new();
+    let mut bus = memory();
+    ppu.ctrl = Ctrl::NMI;
+    run_to(&mut ppu, VBLANK_SCANLINE, 1, &mut bus);
+    assert_eq!(ppu.read_register(0x2002, &mut bus) & 0x80, 0);
+    ppu.clock(&mut bus);
+    assert!(!ppu.status.contains(Status::VBLANK));
+    assert!(!ppu.take_nmi());
+}
+
+#[test]
+fn status_read_as_vblank_starts_cancels_nmi() {
+    let mut ppu = Ppu::new();
+    let mut bus = memory();
+    ppu.ctrl = Ctrl::NMI;
+    run_to(&mut ppu, VBLANK_SCANLINE, 2, &mut bus);
+    assert_eq!(ppu.read_register(0x2002, &mut bus) & 0x80, 0x80);
+    assert!(!ppu.take_nmi());
+}
+
+#[test]
+fn status_read_later_keeps_nmi() {
+    let mut ppu = Ppu::new();
+    let mut bus = memory();
+    ppu.ctrl = Ctrl::NMI;
+    run_to(&mut ppu, VBLANK_SCANLINE, 4, &mut bus);
+    assert_eq!(ppu.read_register(0x2002, &mut bus) & 0x80, 0x80);
+    assert!(ppu.take_nmi());
+}
+
+#[test]
+fn odd_frames_skip_a_dot() {
+    let mut ppu = Ppu::new();
+    let mut bus = memory();
+    let full = usize::from(DOTS) * usize::from(SCANLINES);
+    assert_eq!(run_frame(&mut ppu, &mut bus), full);
+    assert_eq!(run_frame(&mut ppu, &mut bus), full);
+
+    ppu.mask = Mask::BACKGROUND;
+    assert_eq!(run_frame(&mut ppu, &mut bus), full);
+    assert_eq!(run_frame(&mut ppu, &mut bus), full - 1);
+    assert_eq!(run_frame(&mut ppu, &mut bus), full);
+}
+
+#[test]
+fn pal_frames_are_312_lines_without_skip() {
+    let mut ppu = Ppu::new();
+    ppu.region = Region::Pal;
+    ppu.mask = Mask::BACKGROUND;
+    let mut bus = memory();
+    let full = usize::from(DOTS) * 312;
+    for _ in 0..3 {
+        assert_eq!(run_frame(&mut ppu, &mut bus), full);
+    }
+}
+
+#[test]
+fn pal_vblank() {
+    let mut ppu = Ppu::new();
+    ppu.region = Region::Pal;
+    ppu.ctrl = Ctrl::NMI;
+    let mut bus = memory();
+    run_to(&mut ppu, VBLANK_SCANLINE, 2, &mut bus);
+    assert!(ppu.take_nmi());
+    run_to(&mut ppu, 311, 1, &mut bus);
+    assert!(ppu.status.contains(Status::VBLANK));
+    ppu.clock(&mut bus);
+    assert!(!ppu.status.contains(Status::VBLANK));
+}
+
+#[test]
+fn dendy_vblank_starts_late() {
+    let mut ppu = Ppu::new();
+    ppu.region = Region::Dendy;
+    ppu.ctrl = Ctrl::NMI;
+    let mut bus = memory();
+    run_to(&mut ppu, VBLANK_SCANLINE, 2, &mut bus);
+    assert!(!ppu.status.contains(Status::VBLANK));
+    run_to(&mut ppu, 291, 2, &mut bus);
+    assert!(ppu.status.contains(Status::VBLANK));
+    assert!(ppu.take_nmi());
+    assert_eq!(Region::Dendy.scanlines(), 312);
+}
@@ -0,0 +1,71 @@
+//! Converts the NES 2.0 XML database into `src/db/entries.rs`.
+//!
+//! Usage: `cargo run --example nes20db -- nes20db.xml > src/db/entries.rs`
+
+fn main() {
+    let path = std::env::args()
+        .nth(1)
+        .expect("usage: nes20db <nes20db.xml>");
+    let xml = std::fs::read_to_string(path).unwrap();
+
+    let mut entries: Vec<(u32, String)> = xml
+        .split("<game>")
+        .skip(1)
+        .filter_map(|game| {
+            let game = game.split("</game>").next()?;
+            let rom = |tag, key| attribute(game, tag, key);
+            let size = |tag| rom(tag, "size").map_or(0, |s| s.parse().unwrap());
+
+            let crc32 = u32::from_str_radix(rom("rom", "crc32")?, 16).unwrap();
+            let sha1 = rom("rom", "sha1")?;
+            let mirroring = match rom("pcb", "mirroring")? {
+                "V" => "Vertical",
+                "4" => "FourScreen",
+                _ => "Horizontal",
+            };
+            let timing = match rom("console", "region").unwrap_or("0") {
+                "1" => "Pal",
+                "2" => "MultiRegion",
+                "3" => "Dendy",
+                _ => "Ntsc",
+            };
+            let line = format!(
+                "    entry(0x{crc32:08X}, \"{sha1}\", {}, {}, {mirroring}, {}, [0x{:04X}, 0x{:04X}], [0x{:X}, 0x{:X}, 0x{:X}, 0x{:X}], {timing}),",
+                rom("pcb", "mapper")?,
+                rom("pcb", "submapper").unwrap_or("0"),
+                rom("pcb", "battery") == Some("1"),
+                size("prgrom"),
+                size("chrrom"),
+                size("prgram"),
+                size("prgnvram"),
+                size("chrram"),
+                size("chrnvram"),
+            );
+            Some((crc32, line))
+        })
+        .collect();
+    entries.sort();
+
+    println!("// Generated by `cargo run --example nes20db`. Do not edit by hand.");
+    println!("// Sorted by CRC32.");
+    println!();
+    println!("use super::{{entry, Entry}};");
+    println!("use crate::{{Mirroring::*, Timing::*}};");
+    println!();
+    println!("#[rustfmt::skip]");
+    println!("pub(super) static ENTRIES: &[Entry] = &[");
+    for (_, line) in entries {
+        println!("{line}");
+    }
+    println!("];");
+}
+
+/// Value of `key` in the first `<tag .../>` element of `game`.
+fn attribute<'a>(game: &'a str, tag: &str, key: &str) -> Option<&'a str> {
+    let element = game.split(&format!("<{tag} ")).nth(1)?.split('>').next()?;
+    let value = element
+        .split(&format!(" {key}=\""))
+        .nth(1)
+        .or_else(|| element.strip_prefix(&format!("{key}=\"")))?;
+    value.split('"').next()
+}
@@ -0,0 +1,89 @@
+//! Built-in cartridge database derived from the NES 2.0 XML database (nes20db.xml).
+//!
+//! Entries are keyed by the CRC32 of PRG-ROM followed by CHR-ROM and confirmed by SHA-1.
+//!
+//! Only sample entries for the test ROMs are checked in, so `corrected_header`
+//! finds nothing for other games. Build the full table into `src/db/entries.rs`
+//! with `cargo run --example nes20db -- nes20db.xml`.
+
+use crate::{Field, Hashes, Header, Mirroring, Timing};
+
+mod entries;
+
+pub struct Entry {
+    pub crc32: u32,
+    pub sha1: [u8; 20],
+    pub header: Header,
+}
+
+/// Corrected header together with the fields the database overrode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Correction {
+    pub header: Header,
+    pub overridden: Vec<Field>,
+}
+
+pub fn entries() -> &'static [Entry] {
+    entries::ENTRIES
+}
+
+pub fn find(hashes: &Hashes) -> Option<&'static Entry> {
+    let entries = entries::ENTRIES;
+    let start = entries.partition_point(|e| e.crc32 < hashes.crc32);
+    entries[start..]
+        .iter()
+        .take_while(|e| e.crc32 == hashes.crc32)
+        .find(|e| e.sha1 == hashes.sha1)
+}
+
+#[allow(clippy::too_many_arguments)]
+const fn entry(
+    crc32: u32,
+    sha1: &str,
+    mapper: u16,
+    submapper: u8,
+    mirroring: Mirroring,
+    has_battery: bool,
+    [prg_rom_len, chr_rom_len]: [usize; 2],
+    [prg_ram_len, prg_nvram_len, chr_ram_len, chr_nvram_len]: [usize; 4],
+    timing: Timing,
+) -> Entry {
+    Entry {
+        crc32,
+        sha1: hex(sha1),
+        header: Header {
+            mapper,
+            submapper,
+            mirroring,
+            has_battery,
+            prg_rom_len,
+            chr_rom_len,
+            prg_ram_len,
+            prg_nvram_len,
+            chr_ram_len,
+            chr_nvram_len,
+            timing,
+        },
+    }
+}
+
+const fn hex(s: &str) -> [u8; 20] {
+    const fn nibble(c: u8) -> u8 {
+        match c {
+            b'0'..=b'9' => c - b'0',
+            b'A'..=b'F' => c - b'A' + 10,
+            b'a'..=b'f' => c - b'a' + 10,
+            _ => panic!("invalid hex digit"),
+        }
+    }
+
+    let s = s.as_bytes();
+    assert!(s.len() == 40);
+    let mut bytes = [0; 20];
+    let mut i = 0;
+    while i < 20 {
+        bytes[i] = nibble(s[2 * i]) << 4 | nibble(s[2 * i + 1]);
+        i += 1;
+    }
+    bytes
+}
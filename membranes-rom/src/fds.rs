@@ -0,0 +1,140 @@
+//! Famicom Disk System images (.fds), with or without the fwNES header.
+
+use crate::ParseError;
+use wasm_bindgen::prelude::*;
+
+pub const SIDE_LEN: usize = 65500;
+
+const FWNES_MAGIC: &[u8] = b"FDS\x1A";
+const FWNES_HEADER_LEN: usize = 16;
+const DISK_INFO_MAGIC: &[u8] = b"\x01*NINTENDO-HVC*";
+
+const DISK_INFO_LEN: usize = 56;
+const FILE_AMOUNT_LEN: usize = 2;
+const FILE_HEADER_LEN: usize = 16;
+
+pub struct Fds<'a> {
+    sides: Vec<&'a [u8]>,
+}
+
+impl Fds<'_> {
+    pub fn parse(bytes: &[u8]) -> Result<Fds<'_>, ParseError> {
+        let bytes = if bytes.starts_with(FWNES_MAGIC) {
+            bytes.get(FWNES_HEADER_LEN..).ok_or(ParseError::Header)?
+        } else {
+            bytes
+        };
+
+        if bytes.is_empty() || bytes.len() % SIDE_LEN != 0 {
+            return Err(ParseError::Length);
+        }
+
+        let sides: Vec<_> = bytes.chunks(SIDE_LEN).collect();
+        if !sides.iter().all(|side| side.starts_with(DISK_INFO_MAGIC)) {
+            return Err(ParseError::Header);
+        }
+
+        Ok(Fds { sides })
+    }
+
+    /// Raw side data: blocks without gaps or CRCs, zero-padded to [`SIDE_LEN`].
+    pub fn sides(&self) -> &[&[u8]] {
+        &self.sides
+    }
+
+    pub fn side(&self, index: usize) -> Result<DiskSide<'_>, ParseError> {
+        DiskSide::parse(self.sides.get(index).ok_or(ParseError::NoSuchSide)?)
+    }
+}
+
+pub struct DiskSide<'a> {
+    pub info: DiskInfo,
+    /// File count declared in block 2. Games may hide files after it.
+    pub file_count: u8,
+    pub files: Vec<File<'a>>,
+}
+
+impl DiskSide<'_> {
+    pub fn parse(bytes: &[u8]) -> Result<DiskSide<'_>, ParseError> {
+        if !bytes.starts_with(DISK_INFO_MAGIC) || bytes.len() < DISK_INFO_LEN + FILE_AMOUNT_LEN {
+            return Err(ParseError::Header);
+        }
+
+        let info = &bytes[..DISK_INFO_LEN];
+        let info = DiskInfo {
+            manufacturer: info[15],
+            game_name: info[16..19].try_into().unwrap(),
+            revision: info[20],
+            side: info[21],
+            disk_number: info[22],
+            boot_file: info[25],
+        };
+
+        let file_amount = &bytes[DISK_INFO_LEN..(DISK_INFO_LEN + FILE_AMOUNT_LEN)];
+        if file_amount[0] != 2 {
+            return Err(ParseError::Header);
+        }
+
+        let mut files = Vec::new();
+        let mut rest = &bytes[(DISK_INFO_LEN + FILE_AMOUNT_LEN)..];
+        while rest.first() == Some(&3) {
+            let header = rest.get(..FILE_HEADER_LEN).ok_or(ParseError::Length)?;
+            let len = usize::from(u16::from_le_bytes([header[13], header[14]]));
+            let data = rest
+                .get((FILE_HEADER_LEN + 1)..(FILE_HEADER_LEN + 1 + len))
+                .ok_or(ParseError::Length)?;
+            if rest[FILE_HEADER_LEN] != 4 {
+                return Err(ParseError::Header);
+            }
+
+            files.push(File {
+                number: header[1],
+                id: header[2],
+                name: header[3..11].try_into().unwrap(),
+                address: u16::from_le_bytes([header[11], header[12]]),
+                kind: match header[15] {
+                    0 => FileKind::Prg,
+                    1 => FileKind::Chr,
+                    _ => FileKind::Nametable,
+                },
+                data,
+            });
+            rest = &rest[(FILE_HEADER_LEN + 1 + len)..];
+        }
+
+        Ok(DiskSide {
+            info,
+            file_count: file_amount[1],
+            files,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiskInfo {
+    pub manufacturer: u8,
+    pub game_name: [u8; 3],
+    pub revision: u8,
+    pub side: u8,
+    pub disk_number: u8,
+    /// Files with an id up to this one are loaded at boot.
+    pub boot_file: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct File<'a> {
+    pub number: u8,
+    pub id: u8,
+    pub name: [u8; 8],
+    pub address: u16,
+    pub kind: FileKind,
+    pub data: &'a [u8],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[wasm_bindgen]
+pub enum FileKind {
+    Prg,
+    Chr,
+    Nametable,
+}
@@ -1,14 +1,24 @@
 use wasm_bindgen::prelude::*;
 
+#[cfg(feature = "db")]
+pub mod db;
+pub mod fds;
+pub mod nsf;
+pub mod patch;
+
 pub const PRG_ROM_PAGE_LEN: usize = 16384;
+pub const CHR_ROM_PAGE_LEN: usize = 8192;
+
+const HEADER_LEN: usize = 16;
+const TRAINER_LEN: usize = 512;
 
 pub struct INesV1<'a> {
     bytes: &'a [u8],
 }
 
 impl INesV1<'_> {
-    pub fn parse(bytes: &[u8]) -> Result<INesV1, ParseError> {
-        if bytes.len() < 16 {
+    pub fn parse(bytes: &[u8]) -> Result<INesV1<'_>, ParseError> {
+        if bytes.len() < HEADER_LEN {
             return Err(ParseError::Header);
         }
 
@@ -16,24 +26,223 @@ impl INesV1<'_> {
             return Err(ParseError::Header);
         }
 
-        // TODO: "assert length"
+        let ines = INesV1 { bytes };
+        let header = ines.header();
+        // Exponent-multiplier sizes can be large enough to overflow.
+        let len = ines
+            .prg_rom_start()
+            .checked_add(header.prg_rom_len)
+            .and_then(|len| len.checked_add(header.chr_rom_len))
+            .ok_or(ParseError::Length)?;
+        if bytes.len() < len {
+            return Err(ParseError::Length);
+        }
 
-        Ok(INesV1 { bytes })
+        Ok(ines)
     }
 
     pub fn prg_rom_npages(&self) -> u8 {
         self.bytes[4]
     }
 
+    pub fn chr_rom_npages(&self) -> u8 {
+        self.bytes[5]
+    }
+
     pub fn has_trainer(&self) -> bool {
         self.bytes[6] & 0b100 != 0
     }
 
+    /// NES 2.0 headers are marked with 0b10 in bits 2-3 of byte 7.
+    pub fn is_nes2(&self) -> bool {
+        self.bytes[7] & 0b1100 == 0b1000
+    }
+
+    pub fn header(&self) -> Header {
+        let b = self.bytes;
+        let mirroring = if b[6] & 0b1000 != 0 {
+            Mirroring::FourScreen
+        } else if b[6] & 0b1 != 0 {
+            Mirroring::Vertical
+        } else {
+            Mirroring::Horizontal
+        };
+        let mapper_lo = u16::from(b[6] >> 4) | u16::from(b[7] & 0xF0);
+
+        if self.is_nes2() {
+            Header {
+                mapper: mapper_lo | (u16::from(b[8] & 0x0F) << 8),
+                submapper: b[8] >> 4,
+                mirroring,
+                has_battery: b[6] & 0b10 != 0,
+                prg_rom_len: nes2_rom_len(b[4], b[9] & 0x0F, PRG_ROM_PAGE_LEN),
+                chr_rom_len: nes2_rom_len(b[5], b[9] >> 4, CHR_ROM_PAGE_LEN),
+                prg_ram_len: nes2_ram_len(b[10] & 0x0F),
+                prg_nvram_len: nes2_ram_len(b[10] >> 4),
+                chr_ram_len: nes2_ram_len(b[11] & 0x0F),
+                chr_nvram_len: nes2_ram_len(b[11] >> 4),
+                timing: match b[12] & 0b11 {
+                    0 => Timing::Ntsc,
+                    1 => Timing::Pal,
+                    2 => Timing::MultiRegion,
+                    _ => Timing::Dendy,
+                },
+            }
+        } else {
+            let has_battery = b[6] & 0b10 != 0;
+            // Byte 8 counts 8 KiB units with 0 meaning a single unit.
+            let prg_ram_len = usize::from(b[8].max(1)) * 0x2000;
+            let chr_rom_len = usize::from(b[5]) * CHR_ROM_PAGE_LEN;
+            Header {
+                mapper: mapper_lo,
+                submapper: 0,
+                mirroring,
+                has_battery,
+                prg_rom_len: usize::from(b[4]) * PRG_ROM_PAGE_LEN,
+                chr_rom_len,
+                prg_ram_len: if has_battery { 0 } else { prg_ram_len },
+                prg_nvram_len: if has_battery { prg_ram_len } else { 0 },
+                chr_ram_len: if chr_rom_len == 0 { 0x2000 } else { 0 },
+                chr_nvram_len: 0,
+                timing: if b[9] & 0b1 != 0 {
+                    Timing::Pal
+                } else {
+                    Timing::Ntsc
+                },
+            }
+        }
+    }
+
     pub fn prg_rom(&self) -> &[u8] {
-        let npages: usize = self.prg_rom_npages().into();
-        let start = 16 + if self.has_trainer() { 512 } else { 0 };
-        let len = npages * PRG_ROM_PAGE_LEN;
-        &self.bytes[start..(start + len - 1)]
+        let start = self.prg_rom_start();
+        let len = self.header().prg_rom_len;
+        &self.bytes[start..][..len]
+    }
+
+    pub fn chr_rom(&self) -> &[u8] {
+        let header = self.header();
+        let start = self.prg_rom_start() + header.prg_rom_len;
+        &self.bytes[start..][..header.chr_rom_len]
+    }
+
+    /// Hashes of PRG-ROM followed by CHR-ROM, as used by the NES 2.0 database.
+    pub fn hashes(&self) -> Hashes {
+        Hashes::of(&[self.prg_rom(), self.chr_rom()])
+    }
+
+    /// Header with fields corrected from the built-in database.
+    /// Returns None if the ROM is not in the database.
+    #[cfg(feature = "db")]
+    pub fn corrected_header(&self) -> Option<db::Correction> {
+        let entry = db::find(&self.hashes())?;
+        let mut header = self.header();
+        let overridden = header.correct(&entry.header);
+        Some(db::Correction { header, overridden })
+    }
+
+    fn prg_rom_start(&self) -> usize {
+        HEADER_LEN + if self.has_trainer() { TRAINER_LEN } else { 0 }
+    }
+}
+
+/// Decoded iNES / NES 2.0 header. Sizes are in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[wasm_bindgen]
+pub struct Header {
+    pub mapper: u16,
+    pub submapper: u8,
+    pub mirroring: Mirroring,
+    pub has_battery: bool,
+    pub prg_rom_len: usize,
+    pub chr_rom_len: usize,
+    pub prg_ram_len: usize,
+    pub prg_nvram_len: usize,
+    pub chr_ram_len: usize,
+    pub chr_nvram_len: usize,
+    pub timing: Timing,
+}
+
+impl Header {
+    /// Overwrites fields that differ from `other`, returning the ones that changed.
+    pub fn correct(&mut self, other: &Header) -> Vec<Field> {
+        let mut overridden = Vec::new();
+        macro_rules! correct {
+            ($($field:ident => $variant:ident),*) => {$(
+                if self.$field != other.$field {
+                    self.$field = other.$field;
+                    overridden.push(Field::$variant);
+                }
+            )*};
+        }
+        correct!(
+            mapper => Mapper,
+            submapper => Submapper,
+            mirroring => Mirroring,
+            has_battery => Battery,
+            prg_rom_len => PrgRom,
+            chr_rom_len => ChrRom,
+            prg_ram_len => PrgRam,
+            prg_nvram_len => PrgNvram,
+            chr_ram_len => ChrRam,
+            chr_nvram_len => ChrNvram,
+            timing => Timing
+        );
+        overridden
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[wasm_bindgen]
+pub enum Mirroring {
+    Horizontal,
+    Vertical,
+    FourScreen,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[wasm_bindgen]
+pub enum Timing {
+    Ntsc,
+    Pal,
+    MultiRegion,
+    Dendy,
+}
+
+/// Header fields, used to report database corrections.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[wasm_bindgen]
+pub enum Field {
+    Mapper,
+    Submapper,
+    Mirroring,
+    Battery,
+    PrgRom,
+    ChrRom,
+    PrgRam,
+    PrgNvram,
+    ChrRam,
+    ChrNvram,
+    Timing,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hashes {
+    pub crc32: u32,
+    pub sha1: [u8; 20],
+}
+
+impl Hashes {
+    pub fn of(chunks: &[&[u8]]) -> Self {
+        let mut crc32 = crc32fast::Hasher::new();
+        let mut sha1 = sha1_smol::Sha1::new();
+        for chunk in chunks {
+            crc32.update(chunk);
+            sha1.update(chunk);
+        }
+        Self {
+            crc32: crc32.finalize(),
+            sha1: sha1.digest().bytes(),
+        }
     }
 }
 
@@ -41,4 +250,26 @@ impl INesV1<'_> {
 #[wasm_bindgen]
 pub enum ParseError {
     Header,
+    Length,
+    /// An FDS disk side that the image doesn't have.
+    NoSuchSide,
+}
+
+fn nes2_rom_len(lsb: u8, msb: u8, page_len: usize) -> usize {
+    if msb == 0x0F {
+        // Exponent-multiplier notation: 2^E * (MM * 2 + 1)
+        let exponent = u32::from(lsb >> 2);
+        let multiplier = usize::from(lsb & 0b11) * 2 + 1;
+        2usize.saturating_pow(exponent).saturating_mul(multiplier)
+    } else {
+        (usize::from(msb) << 8 | usize::from(lsb)) * page_len
+    }
+}
+
+fn nes2_ram_len(shift: u8) -> usize {
+    if shift == 0 {
+        0
+    } else {
+        64 << shift
+    }
 }
@@ -0,0 +1,188 @@
+//! NSF and NSFe music files.
+
+use crate::ParseError;
+use bitflags::bitflags;
+use wasm_bindgen::prelude::*;
+
+const NSF_MAGIC: &[u8] = b"NESM\x1A";
+const NSFE_MAGIC: &[u8] = b"NSFE";
+const NSF_HEADER_LEN: usize = 0x80;
+
+/// Default play periods in microseconds, used when NSFe has no RATE chunk.
+const NTSC_SPEED: u16 = 16639;
+const PAL_SPEED: u16 = 19997;
+
+bitflags! {
+    #[derive(Default)]
+    #[wasm_bindgen]
+    pub struct ExpansionChips: u8 {
+        const VRC6 = 0b0000_0001;
+        const VRC7 = 0b0000_0010;
+        const FDS = 0b0000_0100;
+        const MMC5 = 0b0000_1000;
+        const NAMCO_163 = 0b0001_0000;
+        const SUNSOFT_5B = 0b0010_0000;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[wasm_bindgen]
+pub enum NsfRegion {
+    Ntsc,
+    Pal,
+    Dual,
+}
+
+#[derive(Debug, Clone)]
+pub struct Nsf<'a> {
+    pub songs: u8,
+    /// Zero-based.
+    pub starting_song: u8,
+    pub load_address: u16,
+    pub init_address: u16,
+    pub play_address: u16,
+    pub title: String,
+    pub artist: String,
+    pub copyright: String,
+    /// Play period in microseconds.
+    pub ntsc_speed: u16,
+    /// Play period in microseconds.
+    pub pal_speed: u16,
+    /// Initial values of the 4 KiB bank registers at $5FF8-$5FFF, if the tune is banked.
+    pub bankswitch: Option<[u8; 8]>,
+    pub region: NsfRegion,
+    pub expansion: ExpansionChips,
+    pub data: &'a [u8],
+}
+
+impl Nsf<'_> {
+    pub fn parse(bytes: &[u8]) -> Result<Nsf<'_>, ParseError> {
+        if bytes.starts_with(NSF_MAGIC) {
+            parse_nsf(bytes)
+        } else if bytes.starts_with(NSFE_MAGIC) {
+            parse_nsfe(bytes)
+        } else {
+            Err(ParseError::Header)
+        }
+    }
+}
+
+fn parse_nsf(bytes: &[u8]) -> Result<Nsf<'_>, ParseError> {
+    if bytes.len() < NSF_HEADER_LEN {
+        return Err(ParseError::Length);
+    }
+
+    let u16_at = |i: usize| u16::from_le_bytes([bytes[i], bytes[i + 1]]);
+    let bankswitch: [u8; 8] = bytes[0x70..0x78].try_into().unwrap();
+
+    Ok(Nsf {
+        songs: bytes[0x06],
+        starting_song: bytes[0x07].saturating_sub(1),
+        load_address: u16_at(0x08),
+        init_address: u16_at(0x0A),
+        play_address: u16_at(0x0C),
+        title: string(&bytes[0x0E..0x2E]),
+        artist: string(&bytes[0x2E..0x4E]),
+        copyright: string(&bytes[0x4E..0x6E]),
+        ntsc_speed: u16_at(0x6E),
+        pal_speed: u16_at(0x78),
+        bankswitch: bankswitch.iter().any(|&b| b != 0).then_some(bankswitch),
+        region: region(bytes[0x7A]),
+        expansion: ExpansionChips::from_bits_truncate(bytes[0x7B]),
+        data: &bytes[NSF_HEADER_LEN..],
+    })
+}
+
+fn parse_nsfe(bytes: &[u8]) -> Result<Nsf<'_>, ParseError> {
+    let mut nsf = Nsf {
+        songs: 1,
+        starting_song: 0,
+        load_address: 0,
+        init_address: 0,
+        play_address: 0,
+        title: String::new(),
+        artist: String::new(),
+        copyright: String::new(),
+        ntsc_speed: NTSC_SPEED,
+        pal_speed: PAL_SPEED,
+        bankswitch: None,
+        region: NsfRegion::Ntsc,
+        expansion: ExpansionChips::empty(),
+        data: &[],
+    };
+    let mut has_info = false;
+
+    let mut rest = &bytes[NSFE_MAGIC.len()..];
+    loop {
+        if rest.len() < 8 {
+            return Err(ParseError::Length);
+        }
+        let len = u32::from_le_bytes(rest[0..4].try_into().unwrap()) as usize;
+        let id = &rest[4..8];
+        let end = 8usize.checked_add(len).ok_or(ParseError::Length)?;
+        let chunk = rest.get(8..end).ok_or(ParseError::Length)?;
+        rest = &rest[end..];
+
+        let u16_at = |i: usize| u16::from_le_bytes([chunk[i], chunk[i + 1]]);
+        match id {
+            b"INFO" => {
+                if len < 8 {
+                    return Err(ParseError::Header);
+                }
+                nsf.load_address = u16_at(0);
+                nsf.init_address = u16_at(2);
+                nsf.play_address = u16_at(4);
+                nsf.region = region(chunk[6]);
+                nsf.expansion = ExpansionChips::from_bits_truncate(chunk[7]);
+                nsf.songs = chunk.get(8).copied().unwrap_or(1);
+                nsf.starting_song = chunk.get(9).copied().unwrap_or(0);
+                has_info = true;
+            }
+            b"DATA" => nsf.data = chunk,
+            b"BANK" => {
+                let mut bankswitch = [0; 8];
+                let n = len.min(8);
+                bankswitch[..n].copy_from_slice(&chunk[..n]);
+                nsf.bankswitch = Some(bankswitch);
+            }
+            b"RATE" => {
+                if len >= 2 {
+                    nsf.ntsc_speed = u16_at(0);
+                }
+                if len >= 4 {
+                    nsf.pal_speed = u16_at(2);
+                }
+            }
+            b"auth" => {
+                let mut strings = chunk.split(|&b| b == 0).map(string);
+                nsf.title = strings.next().unwrap_or_default();
+                nsf.artist = strings.next().unwrap_or_default();
+                nsf.copyright = strings.next().unwrap_or_default();
+            }
+            b"NEND" => break,
+            // Chunks starting with an uppercase letter are mandatory to understand.
+            [b'A'..=b'Z', ..] => return Err(ParseError::Header),
+            _ => {}
+        }
+    }
+
+    if !has_info {
+        return Err(ParseError::Header);
+    }
+    Ok(nsf)
+}
+
+fn region(flags: u8) -> NsfRegion {
+    if flags & 0b10 != 0 {
+        NsfRegion::Dual
+    } else if flags & 0b01 != 0 {
+        NsfRegion::Pal
+    } else {
+        NsfRegion::Ntsc
+    }
+}
+
+fn string(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
@@ -0,0 +1,299 @@
+//! IPS, UPS and BPS soft-patching.
+//!
+//! Patches are applied to the whole ROM file, header included, which is how they are distributed.
+
+use wasm_bindgen::prelude::*;
+
+const IPS_MAGIC: &[u8] = b"PATCH";
+const IPS_EOF: &[u8] = b"EOF";
+const UPS_MAGIC: &[u8] = b"UPS1";
+const BPS_MAGIC: &[u8] = b"BPS1";
+/// Source, target and patch CRC32s at the end of UPS and BPS files.
+const FOOTER_LEN: usize = 12;
+/// Largest UPS or BPS output, well past any NES ROM, so a crafted size can't
+/// exhaust memory.
+pub const MAX_TARGET_LEN: usize = 64 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[wasm_bindgen]
+pub enum Format {
+    Ips,
+    Ups,
+    Bps,
+}
+
+impl Format {
+    pub fn detect(patch: &[u8]) -> Option<Format> {
+        if patch.starts_with(IPS_MAGIC) {
+            Some(Format::Ips)
+        } else if patch.starts_with(UPS_MAGIC) {
+            Some(Format::Ups)
+        } else if patch.starts_with(BPS_MAGIC) {
+            Some(Format::Bps)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[wasm_bindgen]
+pub enum PatchError {
+    /// Unrecognized patch format.
+    Format,
+    /// Patch ended in the middle of a record.
+    Truncated,
+    /// A record reads outside of the source or target.
+    OutOfBounds,
+    SourceChecksum,
+    TargetChecksum,
+    PatchChecksum,
+    /// The target would be larger than `MAX_TARGET_LEN`.
+    TooLarge,
+}
+
+/// Applies a patch of any supported format, detected by its magic number.
+pub fn apply(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    match Format::detect(patch).ok_or(PatchError::Format)? {
+        Format::Ips => apply_ips(rom, patch),
+        Format::Ups => apply_ups(rom, patch),
+        Format::Bps => apply_bps(rom, patch),
+    }
+}
+
+/// IPS records are `offset: u24, len: u16, data`, or `offset: u24, 0: u16, len: u16, value: u8`
+/// for RLE runs. Records past the end grow the ROM, and an optional `len: u24` after `EOF`
+/// truncates it.
+pub fn apply_ips(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let mut reader = Reader::new(patch.strip_prefix(IPS_MAGIC).ok_or(PatchError::Format)?);
+    let mut out = rom.to_vec();
+
+    loop {
+        let offset = reader.bytes(3)?;
+        if offset == IPS_EOF {
+            break;
+        }
+        let offset = be(offset);
+        let len = be(reader.bytes(2)?);
+
+        let (len, data) = if len == 0 {
+            let len = be(reader.bytes(2)?);
+            (len, None)
+        } else {
+            (len, Some(reader.bytes(len)?))
+        };
+
+        if out.len() < offset + len {
+            out.resize(offset + len, 0x00);
+        }
+        let dst = &mut out[offset..(offset + len)];
+        match data {
+            Some(data) => dst.copy_from_slice(data),
+            None => dst.fill(reader.u8()?),
+        }
+    }
+
+    if let Ok(len) = reader.bytes(3) {
+        out.truncate(be(len));
+    }
+
+    Ok(out)
+}
+
+pub fn apply_ups(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let (body, [source_crc, target_crc]) = split_checked(patch, UPS_MAGIC)?;
+    if crc32(rom) != source_crc {
+        return Err(PatchError::SourceChecksum);
+    }
+
+    let mut reader = Reader::new(body);
+    let source_len = reader.varint()?;
+    let target_len = target_len(&mut reader)?;
+    if rom.len() != source_len {
+        return Err(PatchError::SourceChecksum);
+    }
+
+    let mut out = vec![0x00; target_len];
+    let copy_len = source_len.min(target_len);
+    out[..copy_len].copy_from_slice(&rom[..copy_len]);
+
+    let mut offset = 0;
+    while !reader.is_empty() {
+        offset = add(offset, reader.varint()?)?;
+        loop {
+            let xor = reader.u8()?;
+            if let Some(byte) = out.get_mut(offset) {
+                *byte = rom.get(offset).copied().unwrap_or(0x00) ^ xor;
+            }
+            offset = add(offset, 1)?;
+            if xor == 0 {
+                break;
+            }
+        }
+    }
+
+    if crc32(&out) != target_crc {
+        return Err(PatchError::TargetChecksum);
+    }
+    Ok(out)
+}
+
+pub fn apply_bps(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let (body, [source_crc, target_crc]) = split_checked(patch, BPS_MAGIC)?;
+    if crc32(rom) != source_crc {
+        return Err(PatchError::SourceChecksum);
+    }
+
+    let mut reader = Reader::new(body);
+    let source_len = reader.varint()?;
+    let target_len = target_len(&mut reader)?;
+    let metadata_len = reader.varint()?;
+    reader.bytes(metadata_len)?;
+    if rom.len() != source_len {
+        return Err(PatchError::SourceChecksum);
+    }
+
+    let mut out = Vec::with_capacity(target_len);
+    let mut source_offset = 0;
+    let mut target_offset = 0;
+    while !reader.is_empty() {
+        let action = reader.varint()?;
+        let len = (action >> 2) + 1;
+        // No action may write past the target.
+        if add(out.len(), len)? > target_len {
+            return Err(PatchError::OutOfBounds);
+        }
+        match action & 0b11 {
+            // SourceRead
+            0 => {
+                let start = out.len();
+                let src = rom
+                    .get(start..add(start, len)?)
+                    .ok_or(PatchError::OutOfBounds)?;
+                out.extend_from_slice(src);
+            }
+            // TargetRead
+            1 => out.extend_from_slice(reader.bytes(len)?),
+            // SourceCopy
+            2 => {
+                source_offset = relative(source_offset, reader.varint()?)?;
+                let end = add(source_offset, len)?;
+                let src = rom.get(source_offset..end).ok_or(PatchError::OutOfBounds)?;
+                out.extend_from_slice(src);
+                source_offset = end;
+            }
+            // TargetCopy, which may overlap the bytes it is producing
+            _ => {
+                target_offset = relative(target_offset, reader.varint()?)?;
+                for _ in 0..len {
+                    let byte = *out.get(target_offset).ok_or(PatchError::OutOfBounds)?;
+                    out.push(byte);
+                    target_offset += 1;
+                }
+            }
+        }
+    }
+
+    if out.len() != target_len || crc32(&out) != target_crc {
+        return Err(PatchError::TargetChecksum);
+    }
+    Ok(out)
+}
+
+/// Strips magic and footer, validating the patch checksum.
+fn split_checked<'a>(patch: &'a [u8], magic: &[u8]) -> Result<(&'a [u8], [u32; 2]), PatchError> {
+    if !patch.starts_with(magic) {
+        return Err(PatchError::Format);
+    }
+    if patch.len() < magic.len() + FOOTER_LEN {
+        return Err(PatchError::Truncated);
+    }
+
+    let (checked, patch_crc) = patch.split_at(patch.len() - 4);
+    if crc32(checked) != le(patch_crc) {
+        return Err(PatchError::PatchChecksum);
+    }
+
+    let footer = &patch[(patch.len() - FOOTER_LEN)..];
+    let body = &patch[magic.len()..(patch.len() - FOOTER_LEN)];
+    Ok((body, [le(&footer[0..4]), le(&footer[4..8])]))
+}
+
+/// BPS copy offsets are signed deltas with the sign in the lowest bit.
+fn relative(offset: usize, data: usize) -> Result<usize, PatchError> {
+    let delta = data >> 1;
+    if data & 1 != 0 {
+        offset.checked_sub(delta).ok_or(PatchError::OutOfBounds)
+    } else {
+        add(offset, delta)
+    }
+}
+
+fn add(a: usize, b: usize) -> Result<usize, PatchError> {
+    a.checked_add(b).ok_or(PatchError::OutOfBounds)
+}
+
+fn target_len(reader: &mut Reader) -> Result<usize, PatchError> {
+    let len = reader.varint()?;
+    if len > MAX_TARGET_LEN {
+        return Err(PatchError::TooLarge);
+    }
+    Ok(len)
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    crc32fast::hash(bytes)
+}
+
+fn be(bytes: &[u8]) -> usize {
+    bytes.iter().fold(0, |acc, &b| acc << 8 | usize::from(b))
+}
+
+fn le(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes(bytes.try_into().unwrap())
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], PatchError> {
+        if self.bytes.len() < len {
+            return Err(PatchError::Truncated);
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8, PatchError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    /// Variable-length integer shared by UPS and BPS.
+    fn varint(&mut self) -> Result<usize, PatchError> {
+        let mut data = 0usize;
+        let mut shift = 1usize;
+        loop {
+            let x = self.u8()?;
+            data = usize::from(x & 0x7F)
+                .checked_mul(shift)
+                .and_then(|n| data.checked_add(n))
+                .ok_or(PatchError::OutOfBounds)?;
+            if x & 0x80 != 0 {
+                return Ok(data);
+            }
+            shift = shift.checked_mul(0x80).ok_or(PatchError::OutOfBounds)?;
+            data = data.checked_add(shift).ok_or(PatchError::OutOfBounds)?;
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use crate::SNAKE_ROM;
+use membranes_rom::{db, Field, INesV1, Mirroring};
+
+#[test]
+fn sorted() {
+    let entries = db::entries();
+    assert!(entries.windows(2).all(|w| w[0].crc32 <= w[1].crc32));
+}
+
+#[test]
+fn find() {
+    let ines = INesV1::parse(SNAKE_ROM).unwrap();
+    let entry = db::find(&ines.hashes()).unwrap();
+    assert_eq!(entry.crc32, 0x862A5C36);
+}
+
+#[test]
+fn correct_header() {
+    let mut rom = SNAKE_ROM.to_vec();
+    // Horizontal mirroring, battery, mapper 1
+    rom[6] = 0b0001_0010;
+    let ines = INesV1::parse(&rom).unwrap();
+
+    let correction = ines.corrected_header().unwrap();
+
+    assert_eq!(correction.header.mapper, 0);
+    assert_eq!(correction.header.mirroring, Mirroring::Vertical);
+    assert!(!correction.header.has_battery);
+    assert_eq!(
+        correction.overridden,
+        [
+            Field::Mapper,
+            Field::Mirroring,
+            Field::Battery,
+            Field::PrgNvram
+        ]
+    );
+}
+
+#[test]
+fn unknown_rom() {
+    let mut rom = SNAKE_ROM.to_vec();
+    rom[16] ^= 0xFF;
+    let ines = INesV1::parse(&rom).unwrap();
+    assert!(ines.corrected_header().is_none());
+}
@@ -0,0 +1,63 @@
+use membranes_rom::{
+    fds::{Fds, FileKind, SIDE_LEN},
+    ParseError,
+};
+
+pub fn disk_side(files: &[(&[u8; 8], u16, FileKind, &[u8])]) -> Vec<u8> {
+    let mut side = vec![0x01];
+    side.extend(b"*NINTENDO-HVC*");
+    // manufacturer, game name, game type, revision, side, disk number
+    side.extend([0x01, b'T', b'S', b'T', b' ', 0x00, 0x00, 0x00]);
+    side.resize(56, 0x00);
+    side.extend([0x02, files.len() as u8]);
+    for (i, (name, address, kind, data)) in files.iter().enumerate() {
+        side.extend([0x03, i as u8, i as u8]);
+        side.extend(*name);
+        side.extend(address.to_le_bytes());
+        side.extend((data.len() as u16).to_le_bytes());
+        side.push(*kind as u8);
+        side.push(0x04);
+        side.extend(*data);
+    }
+    side.resize(SIDE_LEN, 0x00);
+    side
+}
+
+#[test]
+fn sides_and_files() {
+    let side_a = disk_side(&[
+        (b"KYODAKU-", 0x2800, FileKind::Nametable, &[0x20; 0xE0]),
+        (b"MAIN    ", 0x6000, FileKind::Prg, &[0xEA; 0x10]),
+    ]);
+    let side_b = disk_side(&[]);
+    let image = [side_a, side_b].concat();
+
+    let fds = Fds::parse(&image).unwrap();
+
+    assert_eq!(fds.sides().len(), 2);
+    let side = fds.side(0).unwrap();
+    assert_eq!(side.info.game_name, *b"TST");
+    assert_eq!(side.file_count, 2);
+    assert_eq!(side.files.len(), 2);
+    assert_eq!(side.files[1].name, *b"MAIN    ");
+    assert_eq!(side.files[1].address, 0x6000);
+    assert_eq!(side.files[1].kind, FileKind::Prg);
+    assert_eq!(side.files[1].data, [0xEA; 0x10]);
+    assert!(fds.side(1).unwrap().files.is_empty());
+    assert!(matches!(fds.side(2), Err(ParseError::NoSuchSide)));
+}
+
+#[test]
+fn fwnes_header() {
+    let mut image = b"FDS\x1A\x01".to_vec();
+    image.resize(16, 0x00);
+    image.extend(disk_side(&[]));
+
+    assert_eq!(Fds::parse(&image).unwrap().sides().len(), 1);
+}
+
+#[test]
+fn invalid_length() {
+    let image = &disk_side(&[])[..1000];
+    assert!(matches!(Fds::parse(image), Err(ParseError::Length)));
+}
@@ -0,0 +1,51 @@
+use crate::SNAKE_ROM;
+use membranes_rom::{INesV1, Mirroring, ParseError, Timing};
+
+#[test]
+fn ines() {
+    let ines = INesV1::parse(SNAKE_ROM).unwrap();
+    let header = ines.header();
+
+    assert!(!ines.is_nes2());
+    assert_eq!(header.mapper, 0);
+    assert_eq!(header.mirroring, Mirroring::Vertical);
+    assert!(!header.has_battery);
+    assert_eq!(header.timing, Timing::Ntsc);
+    assert_eq!(ines.prg_rom().len(), 0x8000);
+    assert_eq!(ines.chr_rom().len(), 0);
+}
+
+#[test]
+fn nes2() {
+    let mut rom = vec![0; 16 + 0x4000 + 0x2000];
+    rom[..16].copy_from_slice(&[
+        b'N', b'E', b'S', 0x1A, 0x01, 0x01, 0x43, 0x08, 0x51, 0x00, 0x07, 0x00, 0x03, 0, 0, 0,
+    ]);
+    let ines = INesV1::parse(&rom).unwrap();
+    let header = ines.header();
+
+    assert!(ines.is_nes2());
+    assert_eq!(header.mapper, 0x104);
+    assert_eq!(header.submapper, 5);
+    assert_eq!(header.mirroring, Mirroring::Vertical);
+    assert!(header.has_battery);
+    assert_eq!(header.prg_ram_len, 0x2000);
+    assert_eq!(header.timing, Timing::Dendy);
+}
+
+#[test]
+fn truncated() {
+    let rom = &SNAKE_ROM[..SNAKE_ROM.len() - 1];
+    assert!(matches!(INesV1::parse(rom), Err(ParseError::Length)));
+}
+
+#[test]
+fn oversized_exponent() {
+    // NES 2.0 PRG-ROM of 2^63 * 7 bytes, which saturates.
+    let mut rom = [0x00; 16];
+    rom[..4].copy_from_slice(b"NES\x1A");
+    rom[4] = 0xFF;
+    rom[7] = 0x08;
+    rom[9] = 0x0F;
+    assert!(matches!(INesV1::parse(&rom), Err(ParseError::Length)));
+}
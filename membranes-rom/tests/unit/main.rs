@@ -0,0 +1,7 @@
+mod db;
+mod fds;
+mod header;
+mod nsf;
+mod patch;
+
+const SNAKE_ROM: &[u8] = include_bytes!("../../../freeware/snake.nes");
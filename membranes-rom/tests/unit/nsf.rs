@@ -0,0 +1,78 @@
+use membranes_rom::{
+    nsf::{ExpansionChips, Nsf, NsfRegion},
+    ParseError,
+};
+
+#[test]
+fn nsf() {
+    let mut bytes = vec![0x00; 0x80];
+    bytes[..5].copy_from_slice(b"NESM\x1A");
+    bytes[0x05] = 0x01;
+    bytes[0x06] = 12;
+    bytes[0x07] = 3;
+    bytes[0x08..0x0E].copy_from_slice(&[0x00, 0x80, 0x03, 0x80, 0x06, 0x80]);
+    bytes[0x0E..0x13].copy_from_slice(b"Title");
+    bytes[0x6E..0x70].copy_from_slice(&16639u16.to_le_bytes());
+    bytes[0x70..0x78].copy_from_slice(&[0, 1, 2, 3, 4, 5, 6, 7]);
+    bytes[0x78..0x7A].copy_from_slice(&19997u16.to_le_bytes());
+    bytes[0x7A] = 0b10;
+    bytes[0x7B] = 0b0000_1001;
+    bytes.extend([0xEA; 4]);
+
+    let nsf = Nsf::parse(&bytes).unwrap();
+
+    assert_eq!(nsf.songs, 12);
+    assert_eq!(nsf.starting_song, 2);
+    assert_eq!(nsf.load_address, 0x8000);
+    assert_eq!(nsf.init_address, 0x8003);
+    assert_eq!(nsf.play_address, 0x8006);
+    assert_eq!(nsf.title, "Title");
+    assert_eq!(nsf.ntsc_speed, 16639);
+    assert_eq!(nsf.pal_speed, 19997);
+    assert_eq!(nsf.bankswitch, Some([0, 1, 2, 3, 4, 5, 6, 7]));
+    assert_eq!(nsf.region, NsfRegion::Dual);
+    assert_eq!(nsf.expansion, ExpansionChips::VRC6 | ExpansionChips::MMC5);
+    assert_eq!(nsf.data, [0xEA; 4]);
+}
+
+#[test]
+fn nsfe() {
+    let chunk = |id: &[u8], data: &[u8]| [&(data.len() as u32).to_le_bytes(), id, data].concat();
+    let bytes = [
+        b"NSFE".to_vec(),
+        chunk(
+            b"INFO",
+            &[0x00, 0x80, 0x03, 0x80, 0x06, 0x80, 0b01, 0b100, 5, 1],
+        ),
+        chunk(b"DATA", &[0x60; 8]),
+        chunk(b"RATE", &[0x10, 0x27]),
+        chunk(b"auth", b"Title\0Artist\0Copyright\0Ripper\0"),
+        chunk(b"tlbl", b"ignored"),
+        chunk(b"NEND", &[]),
+    ]
+    .concat();
+
+    let nsf = Nsf::parse(&bytes).unwrap();
+
+    assert_eq!(nsf.songs, 5);
+    assert_eq!(nsf.starting_song, 1);
+    assert_eq!(nsf.play_address, 0x8006);
+    assert_eq!(nsf.region, NsfRegion::Pal);
+    assert_eq!(nsf.expansion, ExpansionChips::FDS);
+    assert_eq!(nsf.ntsc_speed, 10000);
+    assert_eq!(nsf.artist, "Artist");
+    assert_eq!(nsf.bankswitch, None);
+    assert_eq!(nsf.data, [0x60; 8]);
+}
+
+#[test]
+fn nsfe_unknown_mandatory_chunk() {
+    let bytes = [b"NSFE".as_slice(), &[0, 0, 0, 0], b"ABCD"].concat();
+    assert!(matches!(Nsf::parse(&bytes), Err(ParseError::Header)));
+}
+
+#[test]
+fn nsfe_oversized_chunk() {
+    let bytes = [b"NSFE".as_slice(), &u32::MAX.to_le_bytes(), b"DATA"].concat();
+    assert!(matches!(Nsf::parse(&bytes), Err(ParseError::Length)));
+}
@@ -0,0 +1,178 @@
+use membranes_rom::patch::{self, Format, PatchError};
+
+#[test]
+fn ips() {
+    let rom = [0x00; 8];
+    let patch = [
+        b"PATCH".as_slice(),
+        // 2 bytes at 0x000001
+        &[0x00, 0x00, 0x01, 0x00, 0x02, 0xAA, 0xBB],
+        // RLE: 3 bytes of 0xCC at 0x000004
+        &[0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x03, 0xCC],
+        b"EOF",
+    ]
+    .concat();
+
+    let out = patch::apply(&rom, &patch).unwrap();
+
+    assert_eq!(out, [0x00, 0xAA, 0xBB, 0x00, 0xCC, 0xCC, 0xCC, 0x00]);
+}
+
+#[test]
+fn ips_extend_and_truncate() {
+    let rom = [0x00; 4];
+    let extend = [
+        b"PATCH".as_slice(),
+        &[0x00, 0x00, 0x05, 0x00, 0x01, 0xAA],
+        b"EOF",
+    ]
+    .concat();
+    let truncate = [b"PATCH".as_slice(), b"EOF", &[0x00, 0x00, 0x02]].concat();
+
+    assert_eq!(
+        patch::apply(&rom, &extend).unwrap(),
+        [0x00, 0x00, 0x00, 0x00, 0x00, 0xAA]
+    );
+    assert_eq!(patch::apply(&rom, &truncate).unwrap(), [0x00, 0x00]);
+}
+
+#[test]
+fn ips_truncated() {
+    let patch = [b"PATCH".as_slice(), &[0x00, 0x00, 0x01, 0x00, 0x04, 0xAA]].concat();
+    assert_eq!(patch::apply(&[0; 8], &patch), Err(PatchError::Truncated));
+}
+
+#[test]
+fn ups() {
+    let rom = [0x10, 0x20, 0x30, 0x40];
+    let target = [0x10, 0x21, 0x30, 0x40, 0x50];
+    let body = [
+        varint(4),
+        varint(5),
+        // skip 1, xor 0x01, end
+        varint(1),
+        vec![0x01, 0x00],
+        // skip 1 (past the terminator), xor 0x50, end
+        varint(1),
+        vec![0x50, 0x00],
+    ]
+    .concat();
+    let patch = with_footer(b"UPS1", &body, &rom, &target);
+
+    assert_eq!(Format::detect(&patch), Some(Format::Ups));
+    assert_eq!(patch::apply(&rom, &patch).unwrap(), target);
+    assert_eq!(
+        patch::apply(&[0x00; 4], &patch),
+        Err(PatchError::SourceChecksum)
+    );
+}
+
+#[test]
+fn bps() {
+    let rom = [0x01, 0x02, 0x03, 0x04];
+    let target = [0x01, 0x02, 0xAA, 0xAA, 0xAA, 0x03, 0x04];
+    let body = [
+        varint(4),
+        varint(7),
+        varint(0),
+        // SourceRead 2
+        varint(1 << 2),
+        // TargetRead 1: 0xAA
+        varint(1),
+        vec![0xAA],
+        // TargetCopy 2 from offset 2 (overlapping)
+        varint((1 << 2) | 3),
+        varint(2 << 1),
+        // SourceCopy 2 from offset 2
+        varint((1 << 2) | 2),
+        varint(2 << 1),
+    ]
+    .concat();
+    let patch = with_footer(b"BPS1", &body, &rom, &target);
+
+    assert_eq!(patch::apply(&rom, &patch).unwrap(), target);
+}
+
+#[test]
+fn patch_checksum() {
+    let rom = [0x00];
+    let mut patch = with_footer(
+        b"BPS1",
+        &[varint(1), varint(1), varint(0), varint(0)].concat(),
+        &rom,
+        &rom,
+    );
+    let len = patch.len();
+    patch[len - 1] ^= 0xFF;
+
+    assert_eq!(patch::apply(&rom, &patch), Err(PatchError::PatchChecksum));
+}
+
+#[test]
+fn crafted_sizes() {
+    let rom = [0x00; 4];
+    let huge = [varint(4), varint(usize::MAX >> 1)].concat();
+    let patch = with_footer(b"UPS1", &huge, &rom, &rom);
+    assert_eq!(patch::apply(&rom, &patch), Err(PatchError::TooLarge));
+    let patch = with_footer(b"BPS1", &[huge, varint(0)].concat(), &rom, &rom);
+    assert_eq!(patch::apply(&rom, &patch), Err(PatchError::TooLarge));
+
+    // UPS skips that overflow the offset.
+    let body = [
+        varint(4),
+        varint(4),
+        varint(usize::MAX >> 1),
+        vec![0x01, 0x00],
+        varint(usize::MAX >> 1),
+        vec![0x01, 0x00],
+        varint(usize::MAX >> 1),
+        vec![0x01, 0x00],
+    ]
+    .concat();
+    let patch = with_footer(b"UPS1", &body, &rom, &rom);
+    assert_eq!(patch::apply(&rom, &patch), Err(PatchError::OutOfBounds));
+
+    // BPS SourceCopy from an offset that overflows, and a TargetCopy past the target.
+    let body = [
+        varint(4),
+        varint(4),
+        varint(0),
+        varint(2),
+        varint((usize::MAX >> 1) & !1),
+    ]
+    .concat();
+    let patch = with_footer(b"BPS1", &body, &rom, &rom);
+    assert_eq!(patch::apply(&rom, &patch), Err(PatchError::OutOfBounds));
+    let body = [
+        varint(4),
+        varint(4),
+        varint(0),
+        varint(8 << 2 | 3),
+        varint(0),
+    ]
+    .concat();
+    let patch = with_footer(b"BPS1", &body, &rom, &rom);
+    assert_eq!(patch::apply(&rom, &patch), Err(PatchError::OutOfBounds));
+}
+
+fn varint(mut n: usize) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    loop {
+        let x = (n & 0x7F) as u8;
+        n >>= 7;
+        if n == 0 {
+            bytes.push(0x80 | x);
+            return bytes;
+        }
+        bytes.push(x);
+        n -= 1;
+    }
+}
+
+fn with_footer(magic: &[u8], body: &[u8], source: &[u8], target: &[u8]) -> Vec<u8> {
+    let mut patch = [magic, body].concat();
+    patch.extend(crc32fast::hash(source).to_le_bytes());
+    patch.extend(crc32fast::hash(target).to_le_bytes());
+    patch.extend(crc32fast::hash(&patch).to_le_bytes());
+    patch
+}
@@ -1,54 +1,151 @@
 //! parts of the code taken from https://bugzmanov.github.io/nes_ebook/chapter_3_4.html
 
-use membranes_cpu::Bus;
-use rand::Rng;
-use sdl2::{event::Event, keyboard::Keycode};
+use membranes::gamepad::ButtonFlags;
+use membranes::ppu::debug::{
+    NAMETABLES_HEIGHT, NAMETABLES_WIDTH, OAM_HEIGHT, OAM_WIDTH, PALETTE_HEIGHT, PALETTE_WIDTH,
+    PATTERN_TABLES_HEIGHT, PATTERN_TABLES_WIDTH,
+};
+use membranes::ppu::{Region, HEIGHT, WIDTH};
+use sdl2::{event::Event, keyboard::Keycode, pixels::PixelFormatEnum};
+use std::time::{Duration, Instant};
+
+const SCALE: u32 = 3;
+
+/// What the window shows, cycled through with Tab.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum View {
+    Game,
+    PatternTables,
+    Nametables,
+    Oam,
+    Palette,
+}
+
+impl View {
+    fn next(self) -> Self {
+        match self {
+            View::Game => View::PatternTables,
+            View::PatternTables => View::Nametables,
+            View::Nametables => View::Oam,
+            View::Oam => View::Palette,
+            View::Palette => View::Game,
+        }
+    }
+
+    fn size(self) -> (usize, usize) {
+        match self {
+            View::Game => (WIDTH, HEIGHT),
+            View::PatternTables => (PATTERN_TABLES_WIDTH, PATTERN_TABLES_HEIGHT),
+            View::Nametables => (NAMETABLES_WIDTH, NAMETABLES_HEIGHT),
+            View::Oam => (OAM_WIDTH, OAM_HEIGHT),
+            View::Palette => (PALETTE_WIDTH, PALETTE_HEIGHT),
+        }
+    }
+
+    fn rgba(self, nes: &mut membranes::Nes, palette: u8) -> Vec<u8> {
+        match self {
+            View::Game => nes.frame_rgba(),
+            View::PatternTables => nes.debug_pattern_tables(palette),
+            View::Nametables => nes.debug_nametables(),
+            View::Oam => nes.debug_oam(),
+            View::Palette => nes.debug_palette(),
+        }
+    }
+}
 
 fn main() {
+    let mut region = None;
+    let mut args = Vec::new();
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--ntsc" => region = Some(Region::Ntsc),
+            "--pal" => region = Some(Region::Pal),
+            "--dendy" => region = Some(Region::Dendy),
+            _ => args.push(arg),
+        }
+    }
+    let mut args = args.into_iter();
+    let rom_path = args.next().unwrap_or_else(|| {
+        eprintln!("usage: membranes-sdl [--ntsc | --pal | --dendy] <rom.nes> [palette.pal]");
+        std::process::exit(1);
+    });
+    let rom = std::fs::read(rom_path).unwrap();
+    let mut nes = membranes::Nes::default();
+    nes.load(&rom).unwrap();
+    if let Some(region) = region {
+        nes.set_region(region);
+    }
+    if let Some(palette_path) = args.next() {
+        nes.set_palette(&std::fs::read(palette_path).unwrap())
+            .unwrap();
+    }
+    nes.reset();
+    let frame_time = Duration::from_secs_f64(1.0 / nes.frame_rate());
+
     let sdl_context = sdl2::init().unwrap();
     let video_subsystem = sdl_context.video().unwrap();
     let window = video_subsystem
-        .window("Snake game", (32.0 * 10.0) as u32, (32.0 * 10.0) as u32)
+        .window("membranes", WIDTH as u32 * SCALE, HEIGHT as u32 * SCALE)
         .position_centered()
         .build()
         .unwrap();
 
-    let mut canvas = window.into_canvas().present_vsync().build().unwrap();
+    let mut canvas = window.into_canvas().build().unwrap();
     let mut event_pump = sdl_context.event_pump().unwrap();
-    canvas.set_scale(10.0, 10.0).unwrap();
 
     let creator = canvas.texture_creator();
-    let mut texture = creator
-        .create_texture_target(sdl2::pixels::PixelFormatEnum::RGB24, 32, 32)
-        .unwrap();
-
-    let mut screen_state = [0_u8; 32 * 3 * 32];
-    let mut rng = rand::thread_rng();
-
-    let rom = std::fs::read("freeware/snake.nes").unwrap();
-    let mut nes = membranes::Nes::default();
-    nes.load(&rom).unwrap();
-    nes.cpu.regs.pc = 0x8600;
-    nes.cpu.regs.sp = 0xFF;
+    let mut view = View::Game;
+    // Palette the pattern tables are colored with, cycled through with P.
+    let mut palette = 0;
+    let mut texture = None;
+    let mut next_frame = Instant::now();
 
     loop {
-        handle_user_input(&mut nes.bus, &mut event_pump);
-        nes.bus.write_u8(0xFE, rng.gen_range(1, 16));
+        let shown = view;
+        handle_user_input(&mut nes, &mut event_pump, &mut view, &mut palette);
+        nes.run_frame();
 
-        if read_screen_state(&mut nes.bus, &mut screen_state) {
-            texture.update(None, &screen_state, 32 * 3).unwrap();
-            canvas.copy(&texture, None, None).unwrap();
-            canvas.present();
+        let (width, height) = view.size();
+        if texture.is_none() || view != shown {
+            texture = Some(
+                creator
+                    .create_texture_target(PixelFormatEnum::RGBA32, width as u32, height as u32)
+                    .unwrap(),
+            );
         }
+        let texture = texture.as_mut().unwrap();
+        texture
+            .update(None, &view.rgba(&mut nes, palette), width * 4)
+            .unwrap();
+        canvas.copy(texture, None, None).unwrap();
+        canvas.present();
 
-        let effects = nes.cpu.tick(&mut nes.bus);
-        if let membranes::cpu::op::Mnemonic::Brk = effects.op.mnemonic {
-            break;
-        }
+        // Paced to the region's frame rate rather than the display's.
+        next_frame += frame_time;
+        std::thread::sleep(next_frame.saturating_duration_since(Instant::now()));
     }
 }
 
-fn handle_user_input(mem: &mut impl Bus, event_pump: &mut sdl2::EventPump) {
+fn button(keycode: Keycode) -> Option<ButtonFlags> {
+    match keycode {
+        Keycode::Right => Some(ButtonFlags::RIGHT),
+        Keycode::Left => Some(ButtonFlags::LEFT),
+        Keycode::Down => Some(ButtonFlags::DOWN),
+        Keycode::Up => Some(ButtonFlags::UP),
+        Keycode::Return => Some(ButtonFlags::START),
+        Keycode::RShift => Some(ButtonFlags::SELECT),
+        Keycode::Z => Some(ButtonFlags::B),
+        Keycode::X => Some(ButtonFlags::A),
+        _ => None,
+    }
+}
+
+fn handle_user_input(
+    nes: &mut membranes::Nes,
+    event_pump: &mut sdl2::EventPump,
+    view: &mut View,
+    palette: &mut u8,
+) {
     for event in event_pump.poll_iter() {
         match event {
             Event::Quit { .. }
@@ -57,61 +154,30 @@ fn handle_user_input(mem: &mut impl Bus, event_pump: &mut sdl2::EventPump) {
                 ..
             } => std::process::exit(0),
             Event::KeyDown {
-                keycode: Some(Keycode::W),
+                keycode: Some(Keycode::Tab),
                 ..
-            } => {
-                mem.write_u8(0xff, 0x77);
-            }
+            } => *view = view.next(),
             Event::KeyDown {
-                keycode: Some(Keycode::S),
+                keycode: Some(Keycode::P),
                 ..
-            } => {
-                mem.write_u8(0xff, 0x73);
-            }
+            } => *palette = (*palette + 1) % 8,
             Event::KeyDown {
-                keycode: Some(Keycode::A),
+                keycode: Some(keycode),
                 ..
             } => {
-                mem.write_u8(0xff, 0x61);
+                if let Some(button) = button(keycode) {
+                    nes.bus.gamepad_1.press(button);
+                }
             }
-            Event::KeyDown {
-                keycode: Some(Keycode::D),
+            Event::KeyUp {
+                keycode: Some(keycode),
                 ..
             } => {
-                mem.write_u8(0xff, 0x64);
+                if let Some(button) = button(keycode) {
+                    nes.bus.gamepad_1.release(button);
+                }
             }
             _ => { /* do nothing */ }
         }
     }
 }
-
-fn color(byte: u8) -> sdl2::pixels::Color {
-    match byte {
-        0 => sdl2::pixels::Color::BLACK,
-        1 => sdl2::pixels::Color::WHITE,
-        2 | 9 => sdl2::pixels::Color::GREY,
-        3 | 10 => sdl2::pixels::Color::RED,
-        4 | 11 => sdl2::pixels::Color::GREEN,
-        5 | 12 => sdl2::pixels::Color::BLUE,
-        6 | 13 => sdl2::pixels::Color::MAGENTA,
-        7 | 14 => sdl2::pixels::Color::YELLOW,
-        _ => sdl2::pixels::Color::CYAN,
-    }
-}
-
-fn read_screen_state(mem: &mut impl Bus, frame: &mut [u8; 32 * 3 * 32]) -> bool {
-    let mut frame_idx = 0;
-    let mut update = false;
-    for i in 0x0200..0x600 {
-        let color_idx = mem.read_u8(i as u16);
-        let (b1, b2, b3) = color(color_idx).rgb();
-        if frame[frame_idx] != b1 || frame[frame_idx + 1] != b2 || frame[frame_idx + 2] != b3 {
-            frame[frame_idx] = b1;
-            frame[frame_idx + 1] = b2;
-            frame[frame_idx + 2] = b3;
-            update = true;
-        }
-        frame_idx += 3;
-    }
-    update
-}
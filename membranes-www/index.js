@@ -1,78 +1,122 @@
 import init, * as membranes from "./pkg/membranes.js";
-const wasm = await init("./pkg/membranes_bg.wasm");
+await init("./pkg/membranes_bg.wasm");
 let nes = new membranes.Nes();
 
-const color = (byte) => {
-    switch (byte) {
-        case 0: 
-            return "black";
-        case 1:
-            return "white"
-        case 2:
-        case 9:
-            return "grey";
-        default:
-            return "cyan";
+const WIDTH = 256;
+const HEIGHT = 240;
+
+let running = false;
+
+const REGIONS = {
+    "ntsc": membranes.Region.Ntsc,
+    "pal": membranes.Region.Pal,
+    "dendy": membranes.Region.Dendy,
+};
+
+// Applies the selected region, or keeps the one the ROM header chose.
+const applyRegion = () => {
+    const region = REGIONS[document.getElementById("region").value];
+    if (region !== undefined) {
+        nes.set_region(region);
     }
 }
 
 const run = (rom) => {
-    console.log(rom)
     nes.load(rom);
+    applyRegion();
+    nes.reset();
 
     const canvas = document.getElementById("screen");
-    const ntiles = 32;
     const ctx = canvas.getContext("2d");
-    ctx.scale(10, 10);
-
-    let ram = new Uint8Array(wasm.memory.buffer, nes.ram(), 0x2000);
+    const image = ctx.createImageData(WIDTH, HEIGHT);
 
-    const loop = () => {
-        for(let i = 0; i < 128; ++i) {
-            ram[0xFE] = randGenRange(1, 16);
-            nes.next();
-        }
-
-        for (let i = 0; i < ntiles; i++) {
-            for (let j = 0; j < ntiles; j++) {
-                ctx.fillStyle = color(ram[0x0200 + i * ntiles + j]);
-                ctx.fillRect(j, i, 1, 1);
+    // Frames run at the region's rate, whatever the display's refresh rate.
+    let last = performance.now();
+    let owed = 0;
+    const loop = (now) => {
+        owed = Math.min(owed + (now - last) / 1000 * nes.frame_rate(), 4);
+        last = now;
+        if (owed >= 1) {
+            while (owed >= 1) {
+                nes.run_frame();
+                owed -= 1;
             }
+            image.data.set(nes.frame_rgba());
+            ctx.putImageData(image, 0, 0);
+            drawDebug();
         }
 
         window.requestAnimationFrame(loop)
     }
-    loop()
+    if (!running) {
+        running = true;
+        window.requestAnimationFrame(loop)
+    }
+}
+
+// Sizes match membranes_ppu::debug.
+const DEBUG_VIEWS = {
+    "pattern-tables": [256, 128, () => nes.debug_pattern_tables(debugPalette())],
+    "nametables": [512, 480, () => nes.debug_nametables()],
+    "oam": [64, 128, () => nes.debug_oam()],
+    "palette": [16, 2, () => nes.debug_palette()],
+};
+
+const debugPalette = () => Number(document.getElementById("debug-palette").value);
+
+const drawDebug = () => {
+    const view = DEBUG_VIEWS[document.getElementById("debug-view").value];
+    const canvas = document.getElementById("debug");
+    if (!view) {
+        canvas.hidden = true;
+        return;
+    }
+    const [width, height, rgba] = view;
+    canvas.hidden = false;
+    canvas.width = width;
+    canvas.height = height;
+    const ctx = canvas.getContext("2d");
+    const image = ctx.createImageData(width, height);
+    image.data.set(rgba());
+    ctx.putImageData(image, 0, 0);
 }
 
-const randGenRange = (min, max) => {
-    min = Math.ceil(min);
-    max = Math.floor(max);
-    return Math.floor(Math.random() * (max - min) + min);
+// Same layout as membranes-sdl, as gamepad::ButtonFlags bits.
+const BUTTONS = {
+    "ArrowRight": 0b1000_0000,
+    "ArrowLeft": 0b0100_0000,
+    "ArrowDown": 0b0010_0000,
+    "ArrowUp": 0b0001_0000,
+    "Enter": 0b0000_1000,
+    "ShiftRight": 0b0000_0100,
+    "KeyZ": 0b0000_0010,
+    "KeyX": 0b0000_0001,
+};
+
+const readFile = (input, callback) => {
+    const file = input.files[0];
+    const reader = new FileReader();
+    reader.addEventListener("load", () => {
+        callback(new Uint8Array(reader.result));
+    });
+    reader.readAsArrayBuffer(file);
 }
 
 const main = async () => {
     const fileSelector = document.getElementById('file-selector');
+    const paletteSelector = document.getElementById('palette-selector');
 
     document.addEventListener(
         'keydown',
         (e) => {
-            let ram = new Uint8Array(wasm.memory.buffer, nes.ram(), 0x2000);
+            const button = BUTTONS[e.code];
+            if (button !== undefined) {
+                nes.press(1, button);
+                e.preventDefault();
+                return;
+            }
             switch (e.key) {
-                case "w":
-                    ram[0xff] = 0x77;
-                    break;
-                case "s":
-                    ram[0xff] = 0x73;
-                    break;
-                case "a":
-                    ram[0xff] = 0x61;
-                    break;
-                case "d":
-                    ram[0xff] = 0x64;
-                    break;
                 case "r":
-                    ram.fill(0x00);
                     nes.reset();
                     break;
                 default:
@@ -81,23 +125,22 @@ const main = async () => {
         },
         false
     );
+    document.addEventListener(
+        'keyup',
+        (e) => {
+            const button = BUTTONS[e.code];
+            if (button !== undefined) {
+                nes.release(1, button);
+            }
+        },
+        false
+    );
 
-    // fileSelector.addEventListener('change', (event) => {
-    //     const file = event.target.files[0];
-    //     const reader = new FileReader();
-    //     reader.addEventListener("load", () => {;
-    //         const bytes = new Uint8Array(reader.result);
-    //         run(bytes)
-    //     });
-    //     reader.readAsArrayBuffer(file);
-    // });
-
-    fetch("./snake.nes")
-        .then((res) => res.arrayBuffer())
-        .then((buf) => {
-            const bytes = new Uint8Array(buf);
-            run(bytes)
-        })
+    document.getElementById('region').addEventListener('change', applyRegion);
+    fileSelector.addEventListener('change', () => readFile(fileSelector, run));
+    paletteSelector.addEventListener('change', () => readFile(paletteSelector, (pal) => {
+        nes.set_palette(pal);
+    }));
 }
 
 await main();
\ No newline at end of file
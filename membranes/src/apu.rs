@@ -0,0 +1,186 @@
+//! The APU's delta modulation channel. The other channels and the frame counter
+//! aren't emulated yet.
+//!
+//! Dendy famiclones keep NTSC's APU timing, only PAL has its own.
+
+use membranes_ppu::Region;
+
+/// DMC output rates, indexed by the low nibble of $4010.
+pub const NTSC_DMC_RATES: [u16; 16] = [
+    428, 380, 340, 320, 286, 254, 226, 214, 190, 160, 142, 128, 106, 84, 72, 54,
+];
+pub const PAL_DMC_RATES: [u16; 16] = [
+    398, 354, 316, 298, 276, 236, 210, 198, 176, 148, 132, 118, 98, 78, 66, 50,
+];
+
+pub fn dmc_rates(region: Region) -> &'static [u16; 16] {
+    match region {
+        Region::Ntsc | Region::Dendy => &NTSC_DMC_RATES,
+        Region::Pal => &PAL_DMC_RATES,
+    }
+}
+
+/// The delta modulation channel (DMC), which plays 1-bit deltas read from
+/// $8000-$FFFF. Each sample byte is fetched by DMA, halting the CPU.
+pub struct Dmc {
+    rates: &'static [u16; 16],
+    rate: u16,
+    timer: u16,
+    irq_enabled: bool,
+    looping: bool,
+    /// 7-bit output level.
+    level: u8,
+    sample_address: u16,
+    sample_len: u16,
+    address: u16,
+    bytes_remaining: u16,
+    buffer: Option<u8>,
+    shift: u8,
+    bits_remaining: u8,
+    silent: bool,
+    irq: bool,
+}
+
+impl Default for Dmc {
+    fn default() -> Self {
+        Self::new(Region::Ntsc)
+    }
+}
+
+impl Dmc {
+    pub fn new(region: Region) -> Self {
+        let rates = dmc_rates(region);
+        Self {
+            rates,
+            rate: rates[0],
+            timer: rates[0],
+            irq_enabled: false,
+            looping: false,
+            level: 0,
+            sample_address: 0xC000,
+            sample_len: 1,
+            address: 0xC000,
+            bytes_remaining: 0,
+            buffer: None,
+            shift: 0,
+            bits_remaining: 8,
+            silent: true,
+            irq: false,
+        }
+    }
+
+    /// Switches to the region's rate table, keeping the selected rate index.
+    pub fn set_region(&mut self, region: Region) {
+        let index = self.rates.iter().position(|&rate| rate == self.rate);
+        self.rates = dmc_rates(region);
+        self.rate = self.rates[index.unwrap_or(0)];
+    }
+
+    /// Writes to $4010-$4013.
+    pub fn write_register(&mut self, address: u16, data: u8) {
+        match address & 0b11 {
+            0 => {
+                self.irq_enabled = data & 0x80 != 0;
+                self.looping = data & 0x40 != 0;
+                self.rate = self.rates[usize::from(data & 0x0F)];
+                if !self.irq_enabled {
+                    self.irq = false;
+                }
+            }
+            1 => self.level = data & 0x7F,
+            2 => self.sample_address = 0xC000 | u16::from(data) << 6,
+            _ => self.sample_len = u16::from(data) << 4 | 1,
+        }
+    }
+
+    /// Bit 4 of $4015: starts the sample over if it has finished, or stops it.
+    /// Either way acknowledges the IRQ.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.irq = false;
+        if !enabled {
+            self.bytes_remaining = 0;
+        } else if self.bytes_remaining == 0 {
+            self.restart();
+        }
+    }
+
+    /// Whether sample bytes remain to be fetched, as read from bit 4 of $4015.
+    pub fn is_active(&self) -> bool {
+        self.bytes_remaining > 0
+    }
+
+    pub fn irq(&self) -> bool {
+        self.irq
+    }
+
+    /// Address of the next sample byte while the buffer waits for it.
+    pub fn dma_request(&self) -> Option<u16> {
+        match self.buffer {
+            None if self.is_active() => Some(self.address),
+            _ => None,
+        }
+    }
+
+    /// Fills the buffer with the byte fetched for `dma_request`.
+    pub fn load_sample(&mut self, data: u8) {
+        self.buffer = Some(data);
+        self.address = self.address.checked_add(1).unwrap_or(0x8000);
+        self.bytes_remaining -= 1;
+        if self.bytes_remaining == 0 {
+            if self.looping {
+                self.restart();
+            } else if self.irq_enabled {
+                self.irq = true;
+            }
+        }
+    }
+
+    /// Output level from 0 to 127.
+    pub fn level(&self) -> u8 {
+        self.level
+    }
+
+    /// Output on the scale of the APU's mixed output.
+    pub fn audio(&self) -> f32 {
+        if self.level == 0 {
+            return 0.0;
+        }
+        159.79 / (22638.0 / f32::from(self.level) + 100.0)
+    }
+
+    /// Advances the channel by one CPU cycle.
+    pub fn clock(&mut self) {
+        self.timer -= 1;
+        if self.timer > 0 {
+            return;
+        }
+        self.timer = self.rate;
+
+        if !self.silent {
+            if self.shift & 0b1 != 0 {
+                if self.level <= 125 {
+                    self.level += 2;
+                }
+            } else if self.level >= 2 {
+                self.level -= 2;
+            }
+        }
+        self.shift >>= 1;
+        self.bits_remaining -= 1;
+        if self.bits_remaining == 0 {
+            self.bits_remaining = 8;
+            match self.buffer.take() {
+                Some(data) => {
+                    self.shift = data;
+                    self.silent = false;
+                }
+                None => self.silent = true,
+            }
+        }
+    }
+
+    fn restart(&mut self) {
+        self.address = self.sample_address;
+        self.bytes_remaining = self.sample_len;
+    }
+}
@@ -0,0 +1,70 @@
+//! Downsampling of per-CPU-cycle audio levels to the output sample rate.
+
+use membranes_ppu::Region;
+
+pub const SAMPLE_RATE: u32 = 44100;
+pub const NTSC_CPU_CLOCK: u32 = 1_789_773;
+pub const PAL_CPU_CLOCK: u32 = 1_662_607;
+pub const DENDY_CPU_CLOCK: u32 = 1_773_448;
+
+/// CPU cycles per second.
+pub fn cpu_clock(region: Region) -> u32 {
+    match region {
+        Region::Ntsc => NTSC_CPU_CLOCK,
+        Region::Pal => PAL_CPU_CLOCK,
+        Region::Dendy => DENDY_CPU_CLOCK,
+    }
+}
+
+/// Level of one APU pulse channel at full volume, used to balance expansion audio.
+pub const PULSE_LEVEL: f32 = 0.1494;
+
+pub struct Mixer {
+    sample_rate: u32,
+    cycles_per_sample: f32,
+    cycles: f32,
+    sum: f32,
+    samples: Vec<f32>,
+}
+
+impl Default for Mixer {
+    fn default() -> Self {
+        Self::new(NTSC_CPU_CLOCK, SAMPLE_RATE)
+    }
+}
+
+impl Mixer {
+    pub fn new(cpu_clock: u32, sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            cycles_per_sample: cpu_clock as f32 / sample_rate as f32,
+            cycles: 0.0,
+            sum: 0.0,
+            samples: Vec::new(),
+        }
+    }
+
+    /// Resamples from a different CPU clock, keeping samples not yet taken.
+    pub fn set_cpu_clock(&mut self, cpu_clock: u32) {
+        self.cycles_per_sample = cpu_clock as f32 / self.sample_rate as f32;
+    }
+
+    /// Adds one CPU cycle's worth of output.
+    pub fn push(&mut self, level: f32) {
+        // todo: APU channels
+        self.sum += level;
+        self.cycles += 1.0;
+        if self.cycles >= self.cycles_per_sample {
+            // Samples nobody drains are dropped after a second.
+            if self.samples.len() < SAMPLE_RATE as usize {
+                self.samples.push(self.sum / self.cycles);
+            }
+            self.cycles -= self.cycles_per_sample;
+            self.sum = 0.0;
+        }
+    }
+
+    pub fn take_samples(&mut self) -> Vec<f32> {
+        std::mem::take(&mut self.samples)
+    }
+}
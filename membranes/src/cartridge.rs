@@ -0,0 +1,261 @@
+//! Cartridge boards, as seen from the CPU and PPU buses.
+
+pub mod discrete;
+pub mod fds;
+pub mod fme7;
+pub mod mmc1;
+pub mod mmc2;
+pub mod mmc3;
+pub mod mmc5;
+pub mod namco163;
+pub mod nrom;
+pub mod vrc2;
+pub mod vrc6;
+pub mod vrc7;
+mod vrc_irq;
+
+pub use discrete::Discrete;
+pub use fds::Fds;
+pub use fme7::Fme7;
+pub use mmc1::Mmc1;
+pub use mmc2::Mmc2;
+pub use mmc3::Mmc3;
+pub use mmc5::Mmc5;
+pub use namco163::Namco163;
+pub use nrom::Nrom;
+pub use vrc2::Vrc2;
+pub use vrc6::Vrc6;
+pub use vrc7::Vrc7;
+
+use crate::ppu;
+use crate::rom::{self, INesV1};
+use std::any::Any;
+use wasm_bindgen::prelude::*;
+
+/// Builds the board described by an iNES header.
+pub fn from_ines(ines: &INesV1) -> Result<Box<dyn Cartridge>, String> {
+    let header = ines.header();
+    let (prg_rom, chr_rom) = (ines.prg_rom(), ines.chr_rom());
+    // Every board maps PRG-ROM modulo its size.
+    if prg_rom.is_empty() {
+        return Err("No PRG-ROM".to_string());
+    }
+    match header.mapper {
+        0 => Ok(Box::new(Nrom::new(&header, prg_rom, chr_rom))),
+        1 => Ok(Box::new(Mmc1::new(&header, prg_rom, chr_rom))),
+        4 => Ok(Box::new(Mmc3::new(&header, prg_rom, chr_rom))),
+        5 => Ok(Box::new(Mmc5::new(&header, prg_rom, chr_rom))),
+        9 | 10 => Ok(Box::new(Mmc2::new(&header, prg_rom, chr_rom))),
+        19 => Ok(Box::new(Namco163::new(&header, prg_rom, chr_rom))),
+        21 | 22 | 23 | 25 => Ok(Box::new(Vrc2::new(
+            &header,
+            ines.is_nes2(),
+            prg_rom,
+            chr_rom,
+        ))),
+        24 | 26 => Ok(Box::new(Vrc6::new(&header, prg_rom, chr_rom))),
+        69 => Ok(Box::new(Fme7::new(&header, prg_rom, chr_rom))),
+        85 => Ok(Box::new(Vrc7::new(&header, prg_rom, chr_rom))),
+        2 | 3 | 7 | 11 | 34 | 66 => Ok(Box::new(Discrete::new(&header, prg_rom, chr_rom))),
+        mapper => Err(format!("Unsupported mapper: {mapper}")),
+    }
+}
+
+/// Index of the last PRG-ROM bank, which boards fix at the top of the address
+/// space. ROMs smaller than a bank count as one.
+fn last_bank(prg_rom_len: usize, bank_len: usize) -> usize {
+    (prg_rom_len / bank_len).saturating_sub(1)
+}
+
+pub trait Cartridge: Any {
+    /// CPU reads from $4020-$FFFF.
+    fn cpu_read(&mut self, address: u16) -> u8;
+
+    /// CPU writes to $4020-$FFFF.
+    fn cpu_write(&mut self, address: u16, data: u8);
+
+    /// CPU reads without side effects, for inspecting memory.
+    fn cpu_peek(&mut self, address: u16) -> u8 {
+        self.cpu_read(address)
+    }
+
+    /// PPU reads from the pattern tables at $0000-$1FFF.
+    fn ppu_read(&mut self, address: u16) -> u8;
+
+    /// PPU writes to the pattern tables at $0000-$1FFF.
+    fn ppu_write(&mut self, address: u16, data: u8);
+
+    /// Pattern table reads without side effects, for debug views.
+    fn ppu_peek(&mut self, address: u16) -> u8 {
+        self.ppu_read(address)
+    }
+
+    /// Observes every address the PPU puts on its bus, before it is read or written.
+    /// Boards watching A12 see nametable fetches and $2006 writes here too.
+    fn ppu_address(&mut self, _address: u16) {}
+
+    /// Nametable reads at $2000-$2FFF, given what CIRAM holds there.
+    /// Boards with their own nametable memory substitute it.
+    fn nametable_read(&mut self, _address: u16, ciram: u8) -> u8 {
+        ciram
+    }
+
+    /// Nametable reads without side effects, for debug views.
+    fn nametable_peek(&mut self, address: u16, ciram: u8) -> u8 {
+        self.nametable_read(address, ciram)
+    }
+
+    /// Nametable writes at $2000-$2FFF. Returns false to let them through to CIRAM.
+    fn nametable_write(&mut self, _address: u16, _data: u8) -> bool {
+        false
+    }
+
+    /// Observes CPU writes to the PPU registers at $2000-$2007.
+    fn ppu_register_write(&mut self, _address: u16, _data: u8) {}
+
+    fn mirroring(&self) -> Mirroring;
+
+    /// CIRAM page (0-3) shown by each nametable (0-3).
+    fn ciram_page(&self, nametable: usize) -> usize {
+        self.mirroring().ciram_page(nametable)
+    }
+
+    /// Advances the board by one CPU cycle.
+    fn clock(&mut self) {}
+
+    /// Whether the board is asserting the CPU's IRQ line.
+    fn irq(&self) -> bool {
+        false
+    }
+
+    /// Expansion audio level, on the scale of the APU's mixed output.
+    fn audio(&self) -> f32 {
+        0.0
+    }
+
+    /// Battery-backed memory to persist between sessions, empty if the board has none.
+    fn battery_ram(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    /// Restores memory previously returned by `battery_ram`.
+    fn load_battery_ram(&mut self, _data: &[u8]) {}
+}
+
+impl ppu::Memory for dyn Cartridge {
+    fn read_pattern(&mut self, address: u16) -> u8 {
+        self.ppu_read(address)
+    }
+
+    fn write_pattern(&mut self, address: u16, data: u8) {
+        self.ppu_write(address, data)
+    }
+
+    fn nametable_page(&self, nametable: usize) -> usize {
+        self.ciram_page(nametable)
+    }
+
+    fn read_nametable(&mut self, address: u16, ciram: u8) -> u8 {
+        self.nametable_read(address, ciram)
+    }
+
+    fn peek_pattern(&mut self, address: u16) -> u8 {
+        self.ppu_peek(address)
+    }
+
+    fn peek_nametable(&mut self, address: u16, ciram: u8) -> u8 {
+        self.nametable_peek(address, ciram)
+    }
+
+    fn write_nametable(&mut self, address: u16, data: u8) -> bool {
+        self.nametable_write(address, data)
+    }
+
+    fn observe_address(&mut self, address: u16) {
+        self.ppu_address(address)
+    }
+}
+
+/// Nametable arrangement of the PPU's internal VRAM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[wasm_bindgen]
+pub enum Mirroring {
+    Horizontal,
+    Vertical,
+    /// All nametables show the first 1 KiB of VRAM.
+    SingleScreenLower,
+    /// All nametables show the second 1 KiB of VRAM.
+    SingleScreenUpper,
+    /// The board supplies 2 KiB of extra VRAM for four distinct nametables.
+    FourScreen,
+}
+
+impl Mirroring {
+    /// Pages 2 and 3 are the extra VRAM of four-screen boards.
+    pub fn ciram_page(self, nametable: usize) -> usize {
+        match self {
+            Mirroring::Horizontal => nametable / 2 % 2,
+            Mirroring::Vertical => nametable % 2,
+            Mirroring::SingleScreenLower => 0,
+            Mirroring::SingleScreenUpper => 1,
+            Mirroring::FourScreen => nametable % 4,
+        }
+    }
+}
+
+impl From<rom::Mirroring> for Mirroring {
+    fn from(mirroring: rom::Mirroring) -> Self {
+        match mirroring {
+            rom::Mirroring::Horizontal => Mirroring::Horizontal,
+            rom::Mirroring::Vertical => Mirroring::Vertical,
+            rom::Mirroring::FourScreen => Mirroring::FourScreen,
+        }
+    }
+}
+
+/// Pattern table memory: CHR-ROM, or CHR-RAM on boards without ROM.
+pub struct Chr {
+    data: Vec<u8>,
+    is_ram: bool,
+}
+
+impl Chr {
+    pub fn new(rom: &[u8], ram_len: usize) -> Self {
+        if rom.is_empty() {
+            Self {
+                data: vec![0x00; ram_len.max(0x2000)],
+                is_ram: true,
+            }
+        } else {
+            Self {
+                data: rom.to_vec(),
+                is_ram: false,
+            }
+        }
+    }
+
+    pub fn is_ram(&self) -> bool {
+        self.is_ram
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Offsets wrap around the memory size.
+    pub fn read(&self, offset: usize) -> u8 {
+        self.data[offset % self.data.len()]
+    }
+
+    /// Writes to CHR-ROM are ignored.
+    pub fn write(&mut self, offset: usize, data: u8) {
+        if self.is_ram {
+            let len = self.data.len();
+            self.data[offset % len] = data;
+        }
+    }
+}
@@ -0,0 +1,200 @@
+//! Boards built from discrete logic: a single latch selecting PRG and CHR banks.
+//!
+//! Most of them don't disable the ROM while the latch is written, so the CPU and
+//! ROM drive the data bus together and the latch sees their bitwise AND.
+
+use super::{Cartridge, Chr, Mirroring};
+use crate::rom::Header;
+
+const PRG_WINDOW_LEN: usize = 0x4000;
+const CHR_WINDOW_LEN: usize = 0x1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Board {
+    /// Mapper 2: 16 KiB at $8000, last bank fixed at $C000.
+    Uxrom,
+    /// Mapper 3: 8 KiB CHR banks.
+    Cnrom,
+    /// Mapper 7: 32 KiB PRG banks and single-screen mirroring.
+    Axrom,
+    /// Mapper 11: 32 KiB PRG banks in bits 0-1, 8 KiB CHR banks in bits 4-7.
+    ColorDreams,
+    /// Mapper 34 with CHR-RAM: 32 KiB PRG banks.
+    Bnrom,
+    /// Mapper 34 with CHR-ROM: registers at $7FFD-$7FFF and 4 KiB CHR banks.
+    Nina001,
+    /// Mapper 66: 32 KiB PRG banks in bits 4-5, 8 KiB CHR banks in bits 0-1.
+    Gxrom,
+}
+
+impl Board {
+    pub fn from_header(header: &Header) -> Option<Self> {
+        match header.mapper {
+            2 => Some(Board::Uxrom),
+            3 => Some(Board::Cnrom),
+            7 => Some(Board::Axrom),
+            11 => Some(Board::ColorDreams),
+            34 => match header.submapper {
+                1 => Some(Board::Nina001),
+                2 => Some(Board::Bnrom),
+                _ if header.chr_rom_len > 0 => Some(Board::Nina001),
+                _ => Some(Board::Bnrom),
+            },
+            66 => Some(Board::Gxrom),
+            _ => None,
+        }
+    }
+
+    /// Submapper 1 marks boards without bus conflicts and 2 boards with them.
+    /// Otherwise AxROM is assumed to be ANROM/AOROM, which avoid them.
+    fn has_bus_conflicts(self, submapper: u8) -> bool {
+        match (self, submapper) {
+            (Board::Nina001, _) => false,
+            (_, 1) => false,
+            (_, 2) => true,
+            (Board::Axrom, _) => false,
+            _ => true,
+        }
+    }
+}
+
+pub struct Discrete {
+    board: Board,
+    prg_rom: Vec<u8>,
+    /// Only present on NINA-001.
+    prg_ram: Vec<u8>,
+    chr: Chr,
+    /// Offsets of the 16 KiB windows at $8000 and $C000.
+    prg_windows: [usize; 2],
+    /// Offsets of the 4 KiB windows at $0000 and $1000.
+    chr_windows: [usize; 2],
+    mirroring: Mirroring,
+    bus_conflicts: bool,
+}
+
+impl Discrete {
+    /// Panics if the header's mapper is not a discrete board.
+    pub fn new(header: &Header, prg_rom: &[u8], chr_rom: &[u8]) -> Self {
+        let board = Board::from_header(header).expect("discrete logic mapper");
+        let last_window = prg_rom.len().saturating_sub(PRG_WINDOW_LEN);
+        Self {
+            board,
+            prg_rom: prg_rom.to_vec(),
+            prg_ram: match board {
+                Board::Nina001 => vec![0x00; 0x2000],
+                _ => Vec::new(),
+            },
+            chr: Chr::new(chr_rom, header.chr_ram_len + header.chr_nvram_len),
+            prg_windows: match board {
+                Board::Uxrom => [0, last_window],
+                _ => [0, PRG_WINDOW_LEN],
+            },
+            chr_windows: [0, CHR_WINDOW_LEN],
+            mirroring: match board {
+                Board::Axrom => Mirroring::SingleScreenLower,
+                _ => header.mirroring.into(),
+            },
+            bus_conflicts: board.has_bus_conflicts(header.submapper),
+        }
+    }
+
+    pub fn board(&self) -> Board {
+        self.board
+    }
+
+    fn select_prg_32k(&mut self, bank: u8) {
+        let offset = usize::from(bank) * 2 * PRG_WINDOW_LEN;
+        self.prg_windows = [offset, offset + PRG_WINDOW_LEN];
+    }
+
+    fn select_chr_8k(&mut self, bank: u8) {
+        let offset = usize::from(bank) * 2 * CHR_WINDOW_LEN;
+        self.chr_windows = [offset, offset + CHR_WINDOW_LEN];
+    }
+
+    fn write_latch(&mut self, data: u8) {
+        match self.board {
+            Board::Uxrom => self.prg_windows[0] = usize::from(data) * PRG_WINDOW_LEN,
+            Board::Cnrom => self.select_chr_8k(data),
+            Board::Axrom => {
+                self.select_prg_32k(data & 0b111);
+                self.mirroring = if data & 0x10 == 0 {
+                    Mirroring::SingleScreenLower
+                } else {
+                    Mirroring::SingleScreenUpper
+                };
+            }
+            Board::ColorDreams => {
+                self.select_prg_32k(data & 0b11);
+                self.select_chr_8k(data >> 4);
+            }
+            Board::Bnrom => self.select_prg_32k(data),
+            Board::Gxrom => {
+                self.select_prg_32k(data >> 4 & 0b11);
+                self.select_chr_8k(data & 0b11);
+            }
+            Board::Nina001 => {}
+        }
+    }
+
+    fn write_nina001(&mut self, address: u16, data: u8) {
+        match address {
+            0x7FFD => self.select_prg_32k(data & 0b1),
+            0x7FFE => self.chr_windows[0] = usize::from(data & 0x0F) * CHR_WINDOW_LEN,
+            0x7FFF => self.chr_windows[1] = usize::from(data & 0x0F) * CHR_WINDOW_LEN,
+            _ => {}
+        }
+    }
+
+    fn chr_offset(&self, address: u16) -> usize {
+        let window = usize::from(address & 0x1FFF) / CHR_WINDOW_LEN;
+        self.chr_windows[window] + usize::from(address & 0x0FFF)
+    }
+}
+
+impl Cartridge for Discrete {
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        match address {
+            0x6000..=0x7FFF if !self.prg_ram.is_empty() => {
+                self.prg_ram[usize::from(address - 0x6000)]
+            }
+            0x8000..=0xFFFF => {
+                let window = usize::from(address - 0x8000) / PRG_WINDOW_LEN;
+                let offset = self.prg_windows[window] + usize::from(address & 0x3FFF);
+                self.prg_rom[offset % self.prg_rom.len()]
+            }
+            _ => 0x00,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, data: u8) {
+        match address {
+            // The NINA-001 registers also write through to PRG-RAM.
+            0x6000..=0x7FFF if !self.prg_ram.is_empty() => {
+                self.prg_ram[usize::from(address - 0x6000)] = data;
+                self.write_nina001(address, data);
+            }
+            0x8000..=0xFFFF => {
+                let data = if self.bus_conflicts {
+                    data & self.cpu_read(address)
+                } else {
+                    data
+                };
+                self.write_latch(data);
+            }
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        self.chr.read(self.chr_offset(address))
+    }
+
+    fn ppu_write(&mut self, address: u16, data: u8) {
+        self.chr.write(self.chr_offset(address), data);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
@@ -0,0 +1,445 @@
+//! Famicom Disk System RAM adapter and disk drive.
+
+mod audio;
+
+use super::{Cartridge, Mirroring};
+use crate::rom;
+use audio::Audio;
+use std::fmt;
+
+pub const BIOS_LEN: usize = 0x2000;
+
+/// Gap before the first block of a side, in bytes.
+const LEADING_GAP_LEN: usize = 28300 / 8;
+/// Gap after every block, in bytes.
+const BLOCK_GAP_LEN: usize = 976 / 8;
+/// Every block starts with a mark bit, which reads as 0x80 after the gap.
+const BLOCK_START_MARK: u8 = 0x80;
+
+/// CPU cycles to transfer one byte at 96.4 kbit/s.
+const BYTE_TRANSFER_CYCLES: u32 = 150;
+/// CPU cycles for the head to return to the start of the disk.
+const HEAD_RETURN_CYCLES: u32 = 50000;
+/// CPU cycles a newly inserted disk reads as absent, so the BIOS notices the swap.
+const INSERT_DELAY_CYCLES: u32 = 900_000;
+
+#[derive(Debug)]
+pub enum FdsError {
+    MissingBios,
+    InvalidBios(usize),
+    Disk(rom::ParseError),
+    NoSuchSide(usize),
+}
+
+impl fmt::Display for FdsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FdsError::MissingBios => {
+                write!(f, "FDS BIOS (disksys.rom) is required to run disk images")
+            }
+            FdsError::InvalidBios(len) => write!(f, "FDS BIOS must be {BIOS_LEN} bytes, got {len}"),
+            FdsError::Disk(e) => write!(f, "invalid disk image: {e:?}"),
+            FdsError::NoSuchSide(side) => write!(f, "no disk side {side}"),
+        }
+    }
+}
+
+pub struct Fds {
+    bios: Vec<u8>,
+    prg_ram: Vec<u8>,
+    chr_ram: Vec<u8>,
+    mirroring: Mirroring,
+    disk_enabled: bool,
+    sound_enabled: bool,
+    timer: Timer,
+    drive: Drive,
+    audio: Audio,
+}
+
+impl Fds {
+    /// Builds the adapter with the first disk side inserted.
+    pub fn new(disk: &[u8], bios: &[u8]) -> Result<Self, FdsError> {
+        match bios.len() {
+            0 => return Err(FdsError::MissingBios),
+            BIOS_LEN => {}
+            len => return Err(FdsError::InvalidBios(len)),
+        }
+        let disk = rom::fds::Fds::parse(disk).map_err(FdsError::Disk)?;
+
+        Ok(Self {
+            bios: bios.to_vec(),
+            prg_ram: vec![0x00; 0x8000],
+            chr_ram: vec![0x00; 0x2000],
+            mirroring: Mirroring::Horizontal,
+            disk_enabled: false,
+            sound_enabled: false,
+            timer: Default::default(),
+            drive: Drive::new(disk.sides().iter().map(|side| with_gaps(side)).collect()),
+            audio: Default::default(),
+        })
+    }
+
+    pub fn side_count(&self) -> usize {
+        self.drive.sides.len()
+    }
+
+    /// Currently inserted side, if any.
+    pub fn side(&self) -> Option<usize> {
+        self.drive.side
+    }
+
+    /// Inserts a disk side. It reads as absent for a moment so the BIOS notices the swap.
+    pub fn insert(&mut self, side: usize) -> Result<(), FdsError> {
+        if side >= self.side_count() {
+            return Err(FdsError::NoSuchSide(side));
+        }
+        self.drive.side = Some(side);
+        self.drive.insert_delay = INSERT_DELAY_CYCLES;
+        Ok(())
+    }
+
+    pub fn eject(&mut self) {
+        self.drive.side = None;
+    }
+
+    fn read_register(&mut self, address: u16) -> u8 {
+        match address {
+            0x4030 => {
+                let status = self.timer.irq as u8
+                    | (self.drive.transfer_complete as u8) << 1
+                    | (self.drive.end_of_head as u8) << 6;
+                self.timer.irq = false;
+                self.drive.transfer_complete = false;
+                self.drive.irq = false;
+                status
+            }
+            0x4031 => {
+                self.drive.transfer_complete = false;
+                self.drive.irq = false;
+                self.drive.read_data
+            }
+            0x4032 => {
+                let is_inserted = self.drive.is_inserted();
+                !is_inserted as u8
+                    | ((!is_inserted || !self.drive.is_scanning) as u8) << 1
+                    | (!is_inserted as u8) << 2
+                    | 0x40
+            }
+            // Battery is good.
+            0x4033 => 0x80,
+            _ => 0x00,
+        }
+    }
+
+    fn write_register(&mut self, address: u16, data: u8) {
+        match address {
+            0x4020 => self.timer.reload = self.timer.reload & 0xFF00 | u16::from(data),
+            0x4021 => self.timer.reload = self.timer.reload & 0x00FF | u16::from(data) << 8,
+            0x4022 => {
+                self.timer.repeat = data & 0b01 != 0;
+                self.timer.enabled = data & 0b10 != 0 && self.disk_enabled;
+                if self.timer.enabled {
+                    self.timer.counter = self.timer.reload;
+                } else {
+                    self.timer.irq = false;
+                }
+            }
+            0x4023 => {
+                self.disk_enabled = data & 0b01 != 0;
+                self.sound_enabled = data & 0b10 != 0;
+                if !self.disk_enabled {
+                    self.timer.enabled = false;
+                    self.timer.irq = false;
+                    self.drive.irq = false;
+                }
+            }
+            0x4024 => {
+                self.drive.write_data = data;
+                self.drive.transfer_complete = false;
+                self.drive.irq = false;
+            }
+            0x4025 => {
+                self.drive.control(data);
+                self.mirroring = if data & 0b1000 != 0 {
+                    Mirroring::Horizontal
+                } else {
+                    Mirroring::Vertical
+                };
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Cartridge for Fds {
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        match address {
+            0x4030..=0x4033 if self.disk_enabled => self.read_register(address),
+            0x4040..=0x4092 if self.sound_enabled => self.audio.read(address),
+            0x6000..=0xDFFF => self.prg_ram[usize::from(address - 0x6000)],
+            0xE000..=0xFFFF => self.bios[usize::from(address - 0xE000)],
+            _ => 0x00,
+        }
+    }
+
+    fn cpu_peek(&mut self, address: u16) -> u8 {
+        match address {
+            0x4030..=0x4033 => 0x00,
+            _ => self.cpu_read(address),
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, data: u8) {
+        match address {
+            0x4023 => self.write_register(address, data),
+            0x4020..=0x4026 if self.disk_enabled => self.write_register(address, data),
+            0x4040..=0x408A if self.sound_enabled => self.audio.write(address, data),
+            0x6000..=0xDFFF => self.prg_ram[usize::from(address - 0x6000)] = data,
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        self.chr_ram[usize::from(address & 0x1FFF)]
+    }
+
+    fn ppu_write(&mut self, address: u16, data: u8) {
+        self.chr_ram[usize::from(address & 0x1FFF)] = data;
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn clock(&mut self) {
+        self.timer.clock();
+        self.drive.clock();
+        self.audio.clock();
+    }
+
+    fn irq(&self) -> bool {
+        self.timer.irq || self.drive.irq
+    }
+
+    fn audio(&self) -> f32 {
+        self.audio.output()
+    }
+}
+
+/// 16-bit CPU cycle timer at $4020-$4022.
+#[derive(Default)]
+struct Timer {
+    reload: u16,
+    counter: u16,
+    repeat: bool,
+    enabled: bool,
+    irq: bool,
+}
+
+impl Timer {
+    fn clock(&mut self) {
+        if !self.enabled {
+            return;
+        }
+        if self.counter == 0 {
+            self.irq = true;
+            self.counter = self.reload;
+            self.enabled = self.repeat;
+        } else {
+            self.counter -= 1;
+        }
+    }
+}
+
+/// Disk drive, streaming sides byte by byte at the speed of the real mechanism.
+struct Drive {
+    /// Sides with gaps, start marks and CRCs, as laid out on the disk surface.
+    sides: Vec<Vec<u8>>,
+    side: Option<usize>,
+    insert_delay: u32,
+    position: usize,
+    delay: u32,
+    motor_on: bool,
+    reset_transfer: bool,
+    read_mode: bool,
+    crc_control: bool,
+    previous_crc_control: bool,
+    transfer_enabled: bool,
+    irq_enabled: bool,
+    is_scanning: bool,
+    end_of_head: bool,
+    gap_ended: bool,
+    transfer_complete: bool,
+    read_data: u8,
+    write_data: u8,
+    crc: u16,
+    irq: bool,
+}
+
+impl Drive {
+    fn new(sides: Vec<Vec<u8>>) -> Self {
+        Self {
+            sides,
+            side: Some(0),
+            insert_delay: 0,
+            position: 0,
+            delay: 0,
+            motor_on: false,
+            reset_transfer: false,
+            read_mode: true,
+            crc_control: false,
+            previous_crc_control: false,
+            transfer_enabled: false,
+            irq_enabled: false,
+            is_scanning: false,
+            end_of_head: true,
+            gap_ended: false,
+            transfer_complete: false,
+            read_data: 0x00,
+            write_data: 0x00,
+            crc: 0,
+            irq: false,
+        }
+    }
+
+    fn is_inserted(&self) -> bool {
+        self.side.is_some() && self.insert_delay == 0
+    }
+
+    /// $4025
+    fn control(&mut self, data: u8) {
+        self.motor_on = data & 0b0000_0001 != 0;
+        self.reset_transfer = data & 0b0000_0010 != 0;
+        self.read_mode = data & 0b0000_0100 != 0;
+        self.crc_control = data & 0b0001_0000 != 0;
+        self.transfer_enabled = data & 0b0100_0000 != 0;
+        self.irq_enabled = data & 0b1000_0000 != 0;
+        self.irq = false;
+    }
+
+    fn clock(&mut self) {
+        if self.insert_delay > 0 {
+            self.insert_delay -= 1;
+            return;
+        }
+        let Some(side) = self.side.filter(|_| self.motor_on) else {
+            self.end_of_head = true;
+            self.is_scanning = false;
+            return;
+        };
+        if self.reset_transfer && !self.is_scanning {
+            return;
+        }
+        if self.end_of_head {
+            self.delay = HEAD_RETURN_CYCLES;
+            self.end_of_head = false;
+            self.position = 0;
+            self.gap_ended = false;
+            return;
+        }
+        if self.delay > 0 {
+            self.delay -= 1;
+            return;
+        }
+
+        self.is_scanning = true;
+        let disk = &mut self.sides[side];
+        if self.read_mode {
+            let data = disk[self.position];
+            if !self.previous_crc_control {
+                self.crc = crc(self.crc, data);
+            }
+            if !self.transfer_enabled {
+                self.gap_ended = false;
+                self.crc = 0;
+            } else if data != 0 && !self.gap_ended {
+                // The start mark ends the gap and is not transferred.
+                self.gap_ended = true;
+            } else if self.gap_ended {
+                self.transfer_complete = true;
+                self.read_data = data;
+                self.irq |= self.irq_enabled;
+            }
+        } else {
+            let data = if self.crc_control {
+                if !self.previous_crc_control {
+                    self.crc = crc(crc(self.crc, 0x00), 0x00);
+                }
+                let [lo, hi] = self.crc.to_le_bytes();
+                self.crc = u16::from(hi);
+                lo
+            } else {
+                self.transfer_complete = true;
+                self.irq |= self.irq_enabled;
+                let data = if self.transfer_enabled {
+                    self.write_data
+                } else {
+                    0x00
+                };
+                self.crc = crc(self.crc, data);
+                data
+            };
+            disk[self.position] = data;
+            self.gap_ended = false;
+        }
+        self.previous_crc_control = self.crc_control;
+
+        self.position += 1;
+        if self.position >= disk.len() {
+            self.motor_on = false;
+            self.end_of_head = true;
+        } else {
+            self.delay = BYTE_TRANSFER_CYCLES;
+        }
+    }
+}
+
+/// CRC-16 with polynomial 0x8408, fed one byte at a time starting from the block's start mark.
+fn crc(mut crc: u16, data: u8) -> u16 {
+    for bit in 0..8 {
+        let carry = crc & 1 != 0;
+        crc >>= 1;
+        if carry {
+            crc ^= 0x8408;
+        }
+        if data & (1 << bit) != 0 {
+            crc ^= 0x8000;
+        }
+    }
+    crc
+}
+
+/// Lays a side out as it is on the disk: gaps, start marks and CRCs around each block.
+fn with_gaps(side: &[u8]) -> Vec<u8> {
+    let mut disk = vec![0x00; LEADING_GAP_LEN];
+    let mut rest = side;
+    loop {
+        let len = match rest.first() {
+            Some(1) => 56,
+            Some(2) => 2,
+            Some(3) => 16,
+            Some(4) => {
+                // File size is in the preceding file header.
+                let header = &disk[(disk.len() - BLOCK_GAP_LEN - 2 - 16)..];
+                1 + usize::from(u16::from_le_bytes([header[13], header[14]]))
+            }
+            _ => break,
+        };
+        let Some(block) = rest.get(..len) else {
+            break;
+        };
+
+        disk.push(BLOCK_START_MARK);
+        disk.extend_from_slice(block);
+        let crc = [BLOCK_START_MARK]
+            .iter()
+            .chain(block)
+            .chain(&[0x00, 0x00])
+            .fold(0, |crc, &b| self::crc(crc, b));
+        disk.extend(crc.to_le_bytes());
+        disk.resize(disk.len() + BLOCK_GAP_LEN, 0x00);
+        rest = &rest[len..];
+    }
+    disk.resize(disk.len().max(LEADING_GAP_LEN + rom::fds::SIDE_LEN), 0x00);
+    disk
+}
@@ -0,0 +1,242 @@
+//! FDS wavetable channel with its frequency modulator.
+
+use crate::audio::PULSE_LEVEL;
+
+/// Master volume multipliers for $4089 settings 2/2, 2/3, 2/4 and 2/5.
+const MASTER_VOLUME: [u32; 4] = [36, 24, 17, 14];
+/// Modulation table entries: counter adjustments, with 4 resetting it.
+const MOD_ADJUSTMENTS: [i8; 8] = [0, 1, 2, 4, 0, -4, -2, -1];
+/// The FDS at full volume is about 2.4 times as loud as one APU pulse.
+const FULL_LEVEL: f32 = 2.4 * PULSE_LEVEL;
+
+pub struct Audio {
+    wave: [u8; 64],
+    wave_write: bool,
+    wave_halt: bool,
+    wave_position: usize,
+    wave_accumulator: u16,
+    frequency: u16,
+    envelopes_halt: bool,
+    master_volume: usize,
+    master_envelope_speed: u8,
+    volume: Envelope,
+    mod_envelope: Envelope,
+    mod_table: [u8; 64],
+    mod_position: usize,
+    mod_accumulator: u16,
+    mod_frequency: u16,
+    mod_halt: bool,
+    /// 7-bit signed
+    mod_counter: i8,
+    /// Pitch adjustment computed from the modulator.
+    mod_output: i32,
+    output: u8,
+}
+
+impl Default for Audio {
+    fn default() -> Self {
+        Self {
+            wave: [0; 64],
+            wave_write: false,
+            wave_halt: true,
+            wave_position: 0,
+            wave_accumulator: 0,
+            frequency: 0,
+            envelopes_halt: false,
+            master_volume: 0,
+            master_envelope_speed: 0xE8,
+            volume: Default::default(),
+            mod_envelope: Default::default(),
+            mod_table: [0; 64],
+            mod_position: 0,
+            mod_accumulator: 0,
+            mod_frequency: 0,
+            mod_halt: true,
+            mod_counter: 0,
+            mod_output: 0,
+            output: 0,
+        }
+    }
+}
+
+impl Audio {
+    pub fn read(&mut self, address: u16) -> u8 {
+        match address {
+            0x4040..=0x407F => self.wave[usize::from(address - 0x4040)] | 0x40,
+            0x4090 => self.volume.gain | 0x40,
+            0x4092 => self.mod_envelope.gain | 0x40,
+            _ => 0x00,
+        }
+    }
+
+    pub fn write(&mut self, address: u16, data: u8) {
+        match address {
+            0x4040..=0x407F if self.wave_write => {
+                self.wave[usize::from(address - 0x4040)] = data & 0x3F;
+            }
+            0x4080 => self.volume.write(data, self.master_envelope_speed),
+            0x4082 => self.frequency = self.frequency & 0x0F00 | u16::from(data),
+            0x4083 => {
+                self.frequency = self.frequency & 0x00FF | u16::from(data & 0x0F) << 8;
+                self.wave_halt = data & 0x80 != 0;
+                self.envelopes_halt = data & 0x40 != 0;
+                if self.wave_halt {
+                    self.wave_position = 0;
+                    self.wave_accumulator = 0;
+                }
+                if self.envelopes_halt {
+                    self.volume.reset_timer(self.master_envelope_speed);
+                    self.mod_envelope.reset_timer(self.master_envelope_speed);
+                }
+            }
+            0x4084 => {
+                self.mod_envelope.write(data, self.master_envelope_speed);
+                self.update_mod_output();
+            }
+            0x4085 => {
+                self.set_mod_counter(data & 0x7F);
+                self.update_mod_output();
+            }
+            0x4086 => self.mod_frequency = self.mod_frequency & 0x0F00 | u16::from(data),
+            0x4087 => {
+                self.mod_frequency = self.mod_frequency & 0x00FF | u16::from(data & 0x0F) << 8;
+                self.mod_halt = data & 0x80 != 0;
+                if self.mod_halt {
+                    self.mod_accumulator = 0;
+                }
+            }
+            // The 32-entry table is written in pairs while the modulator is halted.
+            0x4088 if self.mod_halt => {
+                self.mod_table[self.mod_position] = data & 0b111;
+                self.mod_table[(self.mod_position + 1) & 0x3F] = data & 0b111;
+                self.mod_position = (self.mod_position + 2) & 0x3F;
+            }
+            0x4089 => {
+                self.master_volume = usize::from(data & 0b11);
+                self.wave_write = data & 0x80 != 0;
+            }
+            0x408A => self.master_envelope_speed = data,
+            _ => {}
+        }
+    }
+
+    pub fn clock(&mut self) {
+        if !self.wave_halt && !self.envelopes_halt && self.master_envelope_speed != 0 {
+            self.volume.clock(self.master_envelope_speed);
+            if self.mod_envelope.clock(self.master_envelope_speed) {
+                self.update_mod_output();
+            }
+        }
+
+        if !self.mod_halt && self.mod_frequency != 0 {
+            let (accumulator, overflow) = self.mod_accumulator.overflowing_add(self.mod_frequency);
+            self.mod_accumulator = accumulator;
+            if overflow {
+                match self.mod_table[self.mod_position] {
+                    4 => self.mod_counter = 0,
+                    step => self.set_mod_counter(
+                        (self.mod_counter + MOD_ADJUSTMENTS[usize::from(step)]) as u8,
+                    ),
+                }
+                self.mod_position = (self.mod_position + 1) & 0x3F;
+                self.update_mod_output();
+            }
+        }
+
+        if self.wave_halt {
+            self.wave_position = 0;
+        } else {
+            let pitch = i32::from(self.frequency) + self.mod_output;
+            if pitch > 0 && !self.wave_write {
+                let (accumulator, overflow) = self.wave_accumulator.overflowing_add(pitch as u16);
+                self.wave_accumulator = accumulator;
+                if overflow {
+                    self.wave_position = (self.wave_position + 1) & 0x3F;
+                }
+            }
+        }
+
+        // The output holds its last value while the wave table is being written.
+        if !self.wave_write {
+            let gain = u32::from(self.volume.gain.min(32));
+            let level = gain * MASTER_VOLUME[self.master_volume];
+            self.output = (u32::from(self.wave[self.wave_position]) * level / 1152) as u8;
+        }
+    }
+
+    pub fn output(&self) -> f32 {
+        f32::from(self.output) / 63.0 * FULL_LEVEL
+    }
+
+    /// Stores a 7-bit two's complement value.
+    fn set_mod_counter(&mut self, value: u8) {
+        self.mod_counter = ((value << 1) as i8) >> 1;
+    }
+
+    fn update_mod_output(&mut self) {
+        let counter = i32::from(self.mod_counter);
+        let mut temp = counter * i32::from(self.mod_envelope.gain);
+        let remainder = temp & 0x0F;
+        temp >>= 4;
+        if remainder > 0 && temp & 0x80 == 0 {
+            temp += if counter < 0 { -1 } else { 2 };
+        }
+        if temp >= 192 {
+            temp -= 256;
+        } else if temp < -64 {
+            temp += 256;
+        }
+
+        temp *= i32::from(self.frequency);
+        let remainder = temp & 0x3F;
+        temp >>= 6;
+        if remainder >= 32 {
+            temp += 1;
+        }
+        self.mod_output = temp;
+    }
+}
+
+/// Volume and modulation gain envelopes at $4080 and $4084.
+#[derive(Default)]
+struct Envelope {
+    gain: u8,
+    speed: u8,
+    increase: bool,
+    disabled: bool,
+    timer: u32,
+}
+
+impl Envelope {
+    fn write(&mut self, data: u8, master_speed: u8) {
+        self.speed = data & 0x3F;
+        self.increase = data & 0x40 != 0;
+        self.disabled = data & 0x80 != 0;
+        if self.disabled {
+            self.gain = self.speed;
+        }
+        self.reset_timer(master_speed);
+    }
+
+    fn reset_timer(&mut self, master_speed: u8) {
+        self.timer = 8 * (u32::from(self.speed) + 1) * u32::from(master_speed);
+    }
+
+    /// Returns whether the gain was stepped.
+    fn clock(&mut self, master_speed: u8) -> bool {
+        if self.disabled {
+            return false;
+        }
+        self.timer = self.timer.saturating_sub(1);
+        if self.timer > 0 {
+            return false;
+        }
+        self.reset_timer(master_speed);
+        if self.increase && self.gain < 32 {
+            self.gain += 1;
+        } else if !self.increase && self.gain > 0 {
+            self.gain -= 1;
+        }
+        true
+    }
+}
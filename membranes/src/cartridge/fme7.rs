@@ -0,0 +1,157 @@
+//! Sunsoft FME-7 and 5B (mapper 69): registers written through a command port at
+//! $8000 and a parameter port at $A000, and a 16-bit CPU cycle IRQ counter.
+//!
+//! The 5B adds audio ports at $C000 and $E000. Headers can't tell the chips apart,
+//! and FME-7 games never write there, so the audio is always present.
+
+mod audio;
+
+use super::{last_bank, Cartridge, Chr, Mirroring};
+use crate::rom::Header;
+use audio::Audio;
+
+const PRG_BANK_LEN: usize = 0x2000;
+const CHR_BANK_LEN: usize = 0x0400;
+
+pub struct Fme7 {
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    chr: Chr,
+    command: u8,
+    chr_banks: [u8; 8],
+    /// $6000 bank, and whether it selects RAM and enables it.
+    prg_ram_bank: u8,
+    prg_banks: [u8; 3],
+    mirroring: Mirroring,
+    irq_enabled: bool,
+    irq_counter_enabled: bool,
+    irq_counter: u16,
+    irq_pending: bool,
+    audio: Audio,
+}
+
+impl Fme7 {
+    pub fn new(header: &Header, prg_rom: &[u8], chr_rom: &[u8]) -> Self {
+        Self {
+            prg_rom: prg_rom.to_vec(),
+            prg_ram: vec![0x00; header.prg_ram_len + header.prg_nvram_len],
+            chr: Chr::new(chr_rom, header.chr_ram_len + header.chr_nvram_len),
+            command: 0,
+            chr_banks: [0; 8],
+            prg_ram_bank: 0,
+            prg_banks: [0; 3],
+            mirroring: header.mirroring.into(),
+            irq_enabled: false,
+            irq_counter_enabled: false,
+            irq_counter: 0,
+            irq_pending: false,
+            audio: Audio::default(),
+        }
+    }
+
+    fn write_parameter(&mut self, data: u8) {
+        match self.command {
+            0x0..=0x7 => self.chr_banks[usize::from(self.command)] = data,
+            0x8 => self.prg_ram_bank = data,
+            0x9..=0xB => self.prg_banks[usize::from(self.command - 0x9)] = data & 0x3F,
+            0xC => {
+                self.mirroring = match data & 0b11 {
+                    0 => Mirroring::Vertical,
+                    1 => Mirroring::Horizontal,
+                    2 => Mirroring::SingleScreenLower,
+                    _ => Mirroring::SingleScreenUpper,
+                };
+            }
+            0xD => {
+                self.irq_enabled = data & 0x01 != 0;
+                self.irq_counter_enabled = data & 0x80 != 0;
+                self.irq_pending = false;
+            }
+            0xE => self.irq_counter = self.irq_counter & 0xFF00 | u16::from(data),
+            _ => self.irq_counter = self.irq_counter & 0x00FF | u16::from(data) << 8,
+        }
+    }
+
+    /// Whether $6000-$7FFF is RAM, and if so whether it's enabled.
+    fn prg_ram_selected(&self) -> (bool, bool) {
+        let ram = self.prg_ram_bank & 0x40 != 0;
+        (
+            ram,
+            ram && self.prg_ram_bank & 0x80 != 0 && !self.prg_ram.is_empty(),
+        )
+    }
+
+    fn prg_rom_offset(&self, address: u16) -> usize {
+        let bank = match address {
+            0x6000..=0x7FFF => usize::from(self.prg_ram_bank & 0x3F),
+            0x8000..=0xDFFF => usize::from(self.prg_banks[usize::from(address - 0x8000) / 0x2000]),
+            _ => last_bank(self.prg_rom.len(), PRG_BANK_LEN),
+        };
+        (bank * PRG_BANK_LEN + usize::from(address & 0x1FFF)) % self.prg_rom.len()
+    }
+
+    fn chr_offset(&self, address: u16) -> usize {
+        let bank = self.chr_banks[usize::from(address & 0x1FFF) / CHR_BANK_LEN];
+        usize::from(bank) * CHR_BANK_LEN + usize::from(address & 0x03FF)
+    }
+}
+
+impl Cartridge for Fme7 {
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        match address {
+            0x6000..=0x7FFF => match self.prg_ram_selected() {
+                (false, _) => self.prg_rom[self.prg_rom_offset(address)],
+                (true, true) => self.prg_ram[usize::from(address - 0x6000) % self.prg_ram.len()],
+                (true, false) => 0x00,
+            },
+            0x8000..=0xFFFF => self.prg_rom[self.prg_rom_offset(address)],
+            _ => 0x00,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, data: u8) {
+        match address {
+            0x6000..=0x7FFF => {
+                if let (true, true) = self.prg_ram_selected() {
+                    let len = self.prg_ram.len();
+                    self.prg_ram[usize::from(address - 0x6000) % len] = data;
+                }
+            }
+            0x8000..=0x9FFF => self.command = data & 0x0F,
+            0xA000..=0xBFFF => self.write_parameter(data),
+            0xC000..=0xDFFF => self.audio.select(data),
+            0xE000..=0xFFFF => self.audio.write(data),
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        self.chr.read(self.chr_offset(address))
+    }
+
+    fn ppu_write(&mut self, address: u16, data: u8) {
+        self.chr.write(self.chr_offset(address), data);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn clock(&mut self) {
+        if self.irq_counter_enabled {
+            self.irq_counter = self.irq_counter.wrapping_sub(1);
+            if self.irq_counter == 0xFFFF && self.irq_enabled {
+                self.irq_pending = true;
+            }
+        }
+        self.audio.clock();
+    }
+
+    fn irq(&self) -> bool {
+        self.irq_pending
+    }
+
+    fn audio(&self) -> f32 {
+        self.audio.output()
+    }
+}
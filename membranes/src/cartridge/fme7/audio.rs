@@ -0,0 +1,198 @@
+//! Sunsoft 5B audio, a YM2149F core: three square channels that can each mix in a
+//! shared noise generator and use a shared envelope in place of their volume.
+
+use crate::audio::PULSE_LEVEL;
+
+/// The chip runs at half the CPU clock and steps its counters every 8 of its cycles.
+const TICK_CYCLES: u8 = 16;
+/// Volume 12 is mixed about as loud as an APU pulse at full volume.
+const VOLUME_12_LEVEL: f32 = PULSE_LEVEL;
+
+pub struct Audio {
+    address: u8,
+    /// Whether the address written to $C000 selects a register at all.
+    selected: bool,
+    tones: [Tone; 3],
+    noise_period: u8,
+    noise_counter: u8,
+    /// 17-bit LFSR, output in bit 0.
+    noise: u32,
+    /// Disable bits for tones then noise, from register 7.
+    mixer: u8,
+    /// Volume in bits 0-3, envelope mode in bit 4.
+    volumes: [u8; 3],
+    envelope: Envelope,
+    divider: u8,
+    /// Amplitudes for the 32 logarithmic steps, 1.5 dB apart.
+    levels: [f32; 32],
+}
+
+impl Default for Audio {
+    fn default() -> Self {
+        Self {
+            address: 0,
+            selected: true,
+            tones: Default::default(),
+            noise_period: 0,
+            noise_counter: 0,
+            noise: 1,
+            mixer: 0,
+            volumes: [0; 3],
+            envelope: Default::default(),
+            divider: 0,
+            levels: std::array::from_fn(|level| match level {
+                0 => 0.0,
+                _ => VOLUME_12_LEVEL * 10f32.powf((level as f32 - 25.0) * 1.5 / 20.0),
+            }),
+        }
+    }
+}
+
+impl Audio {
+    /// Writes to $C000. Addresses with the top bits set select nothing.
+    pub fn select(&mut self, data: u8) {
+        self.address = data & 0x0F;
+        self.selected = data & 0xF0 == 0;
+    }
+
+    /// Writes to $E000.
+    pub fn write(&mut self, data: u8) {
+        if !self.selected {
+            return;
+        }
+        match self.address {
+            0x0..=0x5 => {
+                let tone = &mut self.tones[usize::from(self.address / 2)];
+                tone.period = if self.address & 0b1 == 0 {
+                    tone.period & 0x0F00 | u16::from(data)
+                } else {
+                    tone.period & 0x00FF | u16::from(data & 0x0F) << 8
+                };
+            }
+            0x6 => self.noise_period = data & 0x1F,
+            0x7 => self.mixer = data,
+            0x8..=0xA => self.volumes[usize::from(self.address - 0x8)] = data & 0x1F,
+            0xB => self.envelope.period = self.envelope.period & 0xFF00 | u16::from(data),
+            0xC => self.envelope.period = self.envelope.period & 0x00FF | u16::from(data) << 8,
+            0xD => self.envelope.start(data),
+            _ => {}
+        }
+    }
+
+    pub fn clock(&mut self) {
+        self.divider += 1;
+        if self.divider < TICK_CYCLES {
+            return;
+        }
+        self.divider = 0;
+
+        self.tones.iter_mut().for_each(Tone::tick);
+        // Noise runs at half the tone rate.
+        self.noise_counter += 1;
+        if self.noise_counter >= self.noise_period.max(1) * 2 {
+            self.noise_counter = 0;
+            let feedback = (self.noise ^ self.noise >> 3) & 0b1;
+            self.noise = self.noise >> 1 | feedback << 16;
+        }
+        self.envelope.tick();
+    }
+
+    pub fn output(&self) -> f32 {
+        let noise = self.noise & 0b1 != 0;
+        (0..3)
+            .map(|i| {
+                let tone_enabled = self.mixer >> i & 0b1 == 0;
+                let noise_enabled = self.mixer >> (i + 3) & 0b1 == 0;
+                if (tone_enabled && !self.tones[i].high) || (noise_enabled && !noise) {
+                    return 0.0;
+                }
+                let level = match self.volumes[i] {
+                    volume if volume & 0x10 != 0 => self.envelope.level,
+                    0 => 0,
+                    volume => volume << 1 | 0b1,
+                };
+                self.levels[usize::from(level)]
+            })
+            .sum()
+    }
+}
+
+#[derive(Default)]
+struct Tone {
+    period: u16,
+    counter: u16,
+    high: bool,
+}
+
+impl Tone {
+    fn tick(&mut self) {
+        self.counter += 1;
+        if self.counter >= self.period.max(1) {
+            self.counter = 0;
+            self.high = !self.high;
+        }
+    }
+}
+
+#[derive(Default)]
+struct Envelope {
+    period: u16,
+    counter: u16,
+    /// 5-bit level.
+    level: u8,
+    rising: bool,
+    /// Shape bits: continue, attack, alternate, hold.
+    shape: u8,
+    holding: bool,
+}
+
+impl Envelope {
+    fn start(&mut self, shape: u8) {
+        self.shape = shape & 0x0F;
+        self.rising = self.shape & 0b0100 != 0;
+        self.level = if self.rising { 0 } else { 31 };
+        self.counter = 0;
+        self.holding = false;
+    }
+
+    fn tick(&mut self) {
+        if self.holding {
+            return;
+        }
+        self.counter += 1;
+        if self.counter < self.period.max(1) {
+            return;
+        }
+        self.counter = 0;
+
+        let end = if self.rising { 31 } else { 0 };
+        if self.level != end {
+            self.level = if self.rising {
+                self.level + 1
+            } else {
+                self.level - 1
+            };
+            return;
+        }
+
+        let (continues, alternate, hold) = (
+            self.shape & 0b1000 != 0,
+            self.shape & 0b0010 != 0,
+            self.shape & 0b0001 != 0,
+        );
+        if !continues {
+            self.level = 0;
+            self.holding = true;
+        } else if hold {
+            if alternate {
+                self.level = 31 - self.level;
+            }
+            self.holding = true;
+        } else if alternate {
+            // The level at the end starts the ramp back.
+            self.rising = !self.rising;
+        } else {
+            self.level = 31 - end;
+        }
+    }
+}
@@ -0,0 +1,177 @@
+//! MMC1 (mapper 1) and its SxROM boards.
+//!
+//! The board is inferred from the memory sizes: 512 KiB of PRG-ROM selects the outer
+//! 256 KiB bank with CHR bank bit 4 (SUROM, SXROM), 16 or 32 KiB of PRG-RAM is banked
+//! with bits 3 or 2-3 (SOROM, SXROM), and 8 KiB of CHR-RAM with at most 256 KiB of
+//! PRG-ROM lets bit 4 disable PRG-RAM (SNROM).
+
+use super::{last_bank, Cartridge, Chr, Mirroring};
+use crate::rom::Header;
+
+const PRG_BANK_LEN: usize = 0x4000;
+const CHR_BANK_LEN: usize = 0x1000;
+const PRG_RAM_BANK_LEN: usize = 0x2000;
+const OUTER_PRG_LEN: usize = 0x40000;
+
+pub struct Mmc1 {
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    chr: Chr,
+    /// Serial data, shifted in from bit 4 downwards.
+    shift: u8,
+    shift_count: u8,
+    control: u8,
+    chr_bank_0: u8,
+    chr_bank_1: u8,
+    prg_bank: u8,
+    /// Writes on consecutive cycles, as done by read-modify-write instructions,
+    /// only register the first one.
+    wrote_this_cycle: bool,
+}
+
+impl Mmc1 {
+    pub fn new(header: &Header, prg_rom: &[u8], chr_rom: &[u8]) -> Self {
+        Self {
+            prg_rom: prg_rom.to_vec(),
+            prg_ram: vec![0x00; header.prg_ram_len + header.prg_nvram_len],
+            chr: Chr::new(chr_rom, header.chr_ram_len + header.chr_nvram_len),
+            shift: 0,
+            shift_count: 0,
+            // Starts with the last bank fixed at $C000.
+            control: 0x0C,
+            chr_bank_0: 0,
+            chr_bank_1: 0,
+            prg_bank: 0,
+            wrote_this_cycle: false,
+        }
+    }
+
+    fn write_register(&mut self, address: u16, data: u8) {
+        if data & 0x80 != 0 {
+            self.shift = 0;
+            self.shift_count = 0;
+            self.control |= 0x0C;
+            return;
+        }
+
+        self.shift = self.shift >> 1 | (data & 0b1) << 4;
+        self.shift_count += 1;
+        if self.shift_count < 5 {
+            return;
+        }
+
+        let value = self.shift;
+        match address {
+            0x8000..=0x9FFF => self.control = value,
+            0xA000..=0xBFFF => self.chr_bank_0 = value,
+            0xC000..=0xDFFF => self.chr_bank_1 = value,
+            _ => self.prg_bank = value,
+        }
+        self.shift = 0;
+        self.shift_count = 0;
+    }
+
+    /// CHR bank bits that the SxROM boards wire to PRG lines instead.
+    /// Games use the same value in both CHR registers, so only the first is used.
+    fn board_bits(&self) -> u8 {
+        if self.chr.len() <= 0x2000 {
+            self.chr_bank_0
+        } else {
+            0
+        }
+    }
+
+    fn prg_ram_enabled(&self) -> bool {
+        let snrom_disabled = self.prg_rom.len() <= OUTER_PRG_LEN && self.board_bits() & 0x10 != 0;
+        !self.prg_ram.is_empty() && self.prg_bank & 0x10 == 0 && !snrom_disabled
+    }
+
+    fn prg_ram_offset(&self, address: u16) -> usize {
+        let bank = match self.prg_ram.len() {
+            0x4000 => usize::from(self.board_bits() >> 3 & 0b1),
+            0x8000 => usize::from(self.board_bits() >> 2 & 0b11),
+            _ => 0,
+        };
+        (bank * PRG_RAM_BANK_LEN + usize::from(address - 0x6000)) % self.prg_ram.len()
+    }
+
+    fn prg_rom_offset(&self, address: u16) -> usize {
+        let outer = if self.prg_rom.len() > OUTER_PRG_LEN {
+            usize::from(self.board_bits() & 0x10) * OUTER_PRG_LEN / 0x10
+        } else {
+            0
+        };
+        let bank = usize::from(self.prg_bank & 0x0F);
+        let last = last_bank(OUTER_PRG_LEN.min(self.prg_rom.len()), PRG_BANK_LEN);
+        let is_upper = address >= 0xC000;
+
+        let bank = match (self.control >> 2 & 0b11, is_upper) {
+            (0 | 1, _) => (bank & !1) | usize::from(is_upper),
+            (2, false) => 0,
+            (2, true) => bank,
+            (_, false) => bank,
+            (_, true) => last,
+        };
+        let offset = outer + bank * PRG_BANK_LEN + usize::from(address & 0x3FFF);
+        offset % self.prg_rom.len()
+    }
+
+    fn chr_offset(&self, address: u16) -> usize {
+        let is_upper = address >= 0x1000;
+        let bank = if self.control & 0x10 == 0 {
+            usize::from(self.chr_bank_0 & !1) | usize::from(is_upper)
+        } else if is_upper {
+            usize::from(self.chr_bank_1)
+        } else {
+            usize::from(self.chr_bank_0)
+        };
+        bank * CHR_BANK_LEN + usize::from(address & 0x0FFF)
+    }
+}
+
+impl Cartridge for Mmc1 {
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        match address {
+            0x6000..=0x7FFF if self.prg_ram_enabled() => self.prg_ram[self.prg_ram_offset(address)],
+            0x8000..=0xFFFF => self.prg_rom[self.prg_rom_offset(address)],
+            _ => 0x00,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, data: u8) {
+        match address {
+            0x6000..=0x7FFF if self.prg_ram_enabled() => {
+                let offset = self.prg_ram_offset(address);
+                self.prg_ram[offset] = data;
+            }
+            0x8000..=0xFFFF => {
+                if !self.wrote_this_cycle {
+                    self.write_register(address, data);
+                }
+                self.wrote_this_cycle = true;
+            }
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        self.chr.read(self.chr_offset(address))
+    }
+
+    fn ppu_write(&mut self, address: u16, data: u8) {
+        self.chr.write(self.chr_offset(address), data);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.control & 0b11 {
+            0 => Mirroring::SingleScreenLower,
+            1 => Mirroring::SingleScreenUpper,
+            2 => Mirroring::Vertical,
+            _ => Mirroring::Horizontal,
+        }
+    }
+
+    fn clock(&mut self) {
+        self.wrote_this_cycle = false;
+    }
+}
@@ -0,0 +1,141 @@
+//! MMC2 (mapper 9) and MMC4 (mapper 10): CHR banks switched by latches that flip
+//! when the PPU fetches tiles $FD or $FE.
+
+use super::{Cartridge, Chr, Mirroring};
+use crate::rom::Header;
+
+const CHR_BANK_LEN: usize = 0x1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chip {
+    /// 8 KiB PRG bank at $8000 and the last three fixed.
+    Mmc2,
+    /// 16 KiB PRG bank at $8000, the last one fixed, and 8 KiB PRG-RAM.
+    Mmc4,
+}
+
+pub struct Mmc2 {
+    chip: Chip,
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    chr: Chr,
+    prg_bank: u8,
+    /// CHR banks for each pattern table, selected by latch $FD (0) or $FE (1).
+    chr_banks: [[u8; 2]; 2],
+    latches: [usize; 2],
+    mirroring: Mirroring,
+}
+
+impl Mmc2 {
+    pub fn new(header: &Header, prg_rom: &[u8], chr_rom: &[u8]) -> Self {
+        let chip = match header.mapper {
+            9 => Chip::Mmc2,
+            _ => Chip::Mmc4,
+        };
+        Self {
+            chip,
+            prg_rom: prg_rom.to_vec(),
+            prg_ram: match chip {
+                Chip::Mmc2 => Vec::new(),
+                Chip::Mmc4 => vec![0x00; 0x2000],
+            },
+            chr: Chr::new(chr_rom, header.chr_ram_len + header.chr_nvram_len),
+            prg_bank: 0,
+            chr_banks: [[0; 2]; 2],
+            latches: [1, 1],
+            mirroring: header.mirroring.into(),
+        }
+    }
+
+    pub fn chip(&self) -> Chip {
+        self.chip
+    }
+
+    fn prg_rom_offset(&self, address: u16) -> usize {
+        let (bank_len, switchable) = match self.chip {
+            Chip::Mmc2 => (0x2000, 0x8000..=0x9FFF),
+            Chip::Mmc4 => (0x4000, 0x8000..=0xBFFF),
+        };
+        let bank = if switchable.contains(&address) {
+            usize::from(self.prg_bank)
+        } else {
+            // Fixed banks count back from the end of the ROM.
+            let banks = self.prg_rom.len() / bank_len;
+            let from_end = (0x10000 - usize::from(address)).div_ceil(bank_len);
+            banks.saturating_sub(from_end)
+        };
+        (bank * bank_len + usize::from(address) % bank_len) % self.prg_rom.len()
+    }
+
+    fn chr_offset(&self, address: u16) -> usize {
+        let table = usize::from(address & 0x1FFF) / CHR_BANK_LEN;
+        let bank = self.chr_banks[table][self.latches[table]];
+        usize::from(bank) * CHR_BANK_LEN + usize::from(address & 0x0FFF)
+    }
+
+    /// The MMC2 only watches the exact left table addresses, while other
+    /// ranges trigger on all 8 bytes of the tile's second plane.
+    fn update_latch(&mut self, address: u16) {
+        let exact = self.chip == Chip::Mmc2 && address < 0x1000;
+        let tile = match (address & 0x0FFF, exact) {
+            (0x0FD8, _) => 0,
+            (0x0FE8, _) => 1,
+            (0x0FD9..=0x0FDF, false) => 0,
+            (0x0FE9..=0x0FEF, false) => 1,
+            _ => return,
+        };
+        self.latches[usize::from(address & 0x1FFF) / CHR_BANK_LEN] = tile;
+    }
+}
+
+impl Cartridge for Mmc2 {
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        match address {
+            0x6000..=0x7FFF if !self.prg_ram.is_empty() => {
+                self.prg_ram[usize::from(address - 0x6000)]
+            }
+            0x8000..=0xFFFF => self.prg_rom[self.prg_rom_offset(address)],
+            _ => 0x00,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, data: u8) {
+        match address {
+            0x6000..=0x7FFF if !self.prg_ram.is_empty() => {
+                self.prg_ram[usize::from(address - 0x6000)] = data;
+            }
+            0xA000..=0xAFFF => self.prg_bank = data & 0x0F,
+            0xB000..=0xEFFF => {
+                let register = usize::from(address - 0xB000) / 0x1000;
+                self.chr_banks[register / 2][register % 2] = data & 0x1F;
+            }
+            0xF000..=0xFFFF => {
+                self.mirroring = if data & 0b1 == 0 {
+                    Mirroring::Vertical
+                } else {
+                    Mirroring::Horizontal
+                };
+            }
+            _ => {}
+        }
+    }
+
+    /// The latch flips after the fetch, so the tile itself still comes from the old bank.
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        let data = self.chr.read(self.chr_offset(address));
+        self.update_latch(address);
+        data
+    }
+
+    fn ppu_peek(&mut self, address: u16) -> u8 {
+        self.chr.read(self.chr_offset(address))
+    }
+
+    fn ppu_write(&mut self, address: u16, data: u8) {
+        self.chr.write(self.chr_offset(address), data);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
@@ -0,0 +1,242 @@
+//! MMC3 (mapper 4) and MMC6, with the scanline counter clocked by PPU A12.
+//!
+//! Submapper 1 selects the MMC6 and its 1 KiB of internal PRG-RAM, submapper 4 the
+//! MMC3A's IRQ behaviour. Anything else is the common MMC3B/C.
+
+use super::{last_bank, Cartridge, Chr, Mirroring};
+use crate::rom::Header;
+
+const PRG_BANK_LEN: usize = 0x2000;
+const CHR_BANK_LEN: usize = 0x0400;
+const MMC6_PRG_RAM_LEN: usize = 0x0400;
+/// CPU cycles A12 must stay low for its next rise to clock the counter, filtering
+/// out the brief drops between sprite pattern fetches.
+const A12_LOW_CYCLES: u8 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Revision {
+    /// MMC3B/C: raises IRQ whenever the counter is 0 after being clocked.
+    Mmc3,
+    /// MMC3A: only raises IRQ when the counter reaches 0 by decrementing or
+    /// by a $C001 reload.
+    Mmc3A,
+    Mmc6,
+}
+
+pub struct Mmc3 {
+    revision: Revision,
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    chr: Chr,
+    bank_select: u8,
+    banks: [u8; 8],
+    mirroring: Mirroring,
+    four_screen: bool,
+    prg_ram_protect: u8,
+    irq_latch: u8,
+    irq_counter: u8,
+    irq_reload: bool,
+    irq_enabled: bool,
+    irq_pending: bool,
+    a12: bool,
+    a12_low_cycles: u8,
+}
+
+impl Mmc3 {
+    pub fn new(header: &Header, prg_rom: &[u8], chr_rom: &[u8]) -> Self {
+        let revision = match header.submapper {
+            1 => Revision::Mmc6,
+            4 => Revision::Mmc3A,
+            _ => Revision::Mmc3,
+        };
+        let prg_ram_len = match revision {
+            Revision::Mmc6 => MMC6_PRG_RAM_LEN,
+            _ => header.prg_ram_len + header.prg_nvram_len,
+        };
+        let mirroring = header.mirroring.into();
+
+        Self {
+            revision,
+            prg_rom: prg_rom.to_vec(),
+            prg_ram: vec![0x00; prg_ram_len],
+            chr: Chr::new(chr_rom, header.chr_ram_len + header.chr_nvram_len),
+            bank_select: 0,
+            banks: [0, 2, 4, 5, 6, 7, 0, 1],
+            mirroring,
+            four_screen: mirroring == Mirroring::FourScreen,
+            // MMC3 RAM starts enabled, as some games never write $A001.
+            prg_ram_protect: match revision {
+                Revision::Mmc6 => 0x00,
+                _ => 0x80,
+            },
+            irq_latch: 0,
+            irq_counter: 0,
+            irq_reload: false,
+            irq_enabled: false,
+            irq_pending: false,
+            a12: false,
+            a12_low_cycles: 0,
+        }
+    }
+
+    pub fn revision(&self) -> Revision {
+        self.revision
+    }
+
+    fn write_register(&mut self, address: u16, data: u8) {
+        match (address & 0xE000, address & 0b1) {
+            (0x8000, 0) => self.bank_select = data,
+            (0x8000, _) => self.banks[usize::from(self.bank_select & 0b111)] = data,
+            (0xA000, 0) => {
+                if !self.four_screen {
+                    self.mirroring = if data & 0b1 == 0 {
+                        Mirroring::Vertical
+                    } else {
+                        Mirroring::Horizontal
+                    };
+                }
+            }
+            (0xA000, _) => self.prg_ram_protect = data,
+            (0xC000, 0) => self.irq_latch = data,
+            (0xC000, _) => {
+                self.irq_counter = 0;
+                self.irq_reload = true;
+            }
+            (0xE000, 0) => {
+                self.irq_enabled = false;
+                self.irq_pending = false;
+            }
+            _ => self.irq_enabled = true,
+        }
+    }
+
+    fn clock_irq_counter(&mut self) {
+        let count = self.irq_counter;
+        if self.irq_counter == 0 || self.irq_reload {
+            self.irq_counter = self.irq_latch;
+        } else {
+            self.irq_counter -= 1;
+        }
+
+        let raise = match self.revision {
+            Revision::Mmc3A | Revision::Mmc6 => {
+                (count > 0 || self.irq_reload) && self.irq_counter == 0
+            }
+            Revision::Mmc3 => self.irq_counter == 0,
+        };
+        if raise && self.irq_enabled {
+            self.irq_pending = true;
+        }
+        self.irq_reload = false;
+    }
+
+    fn prg_rom_offset(&self, address: u16) -> usize {
+        let last = last_bank(self.prg_rom.len(), PRG_BANK_LEN);
+        let swapped = self.bank_select & 0x40 != 0;
+        let bank = match ((address - 0x8000) / 0x2000, swapped) {
+            (0, false) | (2, true) => usize::from(self.banks[6] & 0x3F),
+            (1, _) => usize::from(self.banks[7] & 0x3F),
+            (0, true) | (2, false) => last.saturating_sub(1),
+            _ => last,
+        };
+        (bank * PRG_BANK_LEN + usize::from(address & 0x1FFF)) % self.prg_rom.len()
+    }
+
+    fn chr_offset(&self, address: u16) -> usize {
+        let inverted = self.bank_select & 0x80 != 0;
+        let mut window = usize::from(address & 0x1FFF) / CHR_BANK_LEN;
+        if inverted {
+            window ^= 4;
+        }
+        let bank = match window {
+            0 => self.banks[0] & !1,
+            1 => self.banks[0] | 1,
+            2 => self.banks[1] & !1,
+            3 => self.banks[1] | 1,
+            _ => self.banks[window - 2],
+        };
+        usize::from(bank) * CHR_BANK_LEN + usize::from(address & 0x03FF)
+    }
+
+    /// Whether PRG-RAM can be (read, written) at an address in $6000-$7FFF.
+    /// The MMC6 protects each 512 byte half separately and needs reads enabled to write.
+    fn prg_ram_access(&self, address: u16) -> (bool, bool) {
+        match self.revision {
+            Revision::Mmc6 => {
+                let enabled = self.bank_select & 0x20 != 0;
+                let shift = if address & 0x0200 == 0 { 4 } else { 6 };
+                let bits = self.prg_ram_protect >> shift;
+                let read = enabled && address >= 0x7000 && bits & 0b10 != 0;
+                (read, read && bits & 0b01 != 0)
+            }
+            _ => {
+                let enabled = !self.prg_ram.is_empty() && self.prg_ram_protect & 0x80 != 0;
+                (enabled, enabled && self.prg_ram_protect & 0x40 == 0)
+            }
+        }
+    }
+}
+
+impl Cartridge for Mmc3 {
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        match address {
+            0x6000..=0x7FFF => match self.prg_ram_access(address) {
+                (true, _) => self.prg_ram[usize::from(address) % self.prg_ram.len()],
+                _ => 0x00,
+            },
+            0x8000..=0xFFFF => self.prg_rom[self.prg_rom_offset(address)],
+            _ => 0x00,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, data: u8) {
+        match address {
+            0x6000..=0x7FFF => {
+                if let (_, true) = self.prg_ram_access(address) {
+                    let len = self.prg_ram.len();
+                    self.prg_ram[usize::from(address) % len] = data;
+                }
+            }
+            // The MMC6 ignores $A001 while its RAM is disabled in $8000.
+            0xA000..=0xBFFF
+                if address & 0b1 == 1
+                    && self.revision == Revision::Mmc6
+                    && self.bank_select & 0x20 == 0 => {}
+            0x8000..=0xFFFF => self.write_register(address, data),
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        self.chr.read(self.chr_offset(address))
+    }
+
+    fn ppu_write(&mut self, address: u16, data: u8) {
+        self.chr.write(self.chr_offset(address), data);
+    }
+
+    fn ppu_address(&mut self, address: u16) {
+        let a12 = address & 0x1000 != 0;
+        if a12 && !self.a12 && self.a12_low_cycles >= A12_LOW_CYCLES {
+            self.clock_irq_counter();
+        }
+        if !a12 && self.a12 {
+            self.a12_low_cycles = 0;
+        }
+        self.a12 = a12;
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn clock(&mut self) {
+        if !self.a12 {
+            self.a12_low_cycles = self.a12_low_cycles.saturating_add(1);
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.irq_pending
+    }
+}
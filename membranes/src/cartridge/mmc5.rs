@@ -0,0 +1,470 @@
+//! MMC5 (mapper 5): PRG/CHR banking, ExRAM, fill mode, extended attributes,
+//! vertical split screen, scanline IRQ, multiplier and expansion audio.
+//!
+//! Like the real chip, it has no view of the PPU's state beyond snooped $2000/$2001
+//! writes and the reads on the PPU bus. Three reads from the same address mark the
+//! start of a scanline, counting reads from there tells what the PPU is fetching,
+//! and a few CPU cycles without any read mean rendering has stopped.
+
+mod audio;
+
+use super::{Cartridge, Chr, Mirroring};
+use crate::rom::Header;
+use audio::Audio;
+
+const PRG_BANK_LEN: usize = 0x2000;
+const CHR_BANK_LEN: usize = 0x0400;
+const EXRAM_LEN: usize = 0x0400;
+/// CPU cycles without PPU reads after which the frame is considered over.
+const IDLE_CYCLES: u8 = 3;
+
+/// PPU reads in a scanline, counted from the first nametable fetch at dot 1.
+const BG_READS: u16 = 128;
+const SPRITE_READS: u16 = 32;
+const PREFETCH_READS: u16 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Fetch {
+    Nametable { column: u8, next_line: bool },
+    Attribute { column: u8, next_line: bool },
+    Pattern { column: u8, next_line: bool },
+    Sprite,
+    Other,
+}
+
+pub struct Mmc5 {
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    chr: Chr,
+    exram: [u8; EXRAM_LEN],
+
+    prg_mode: u8,
+    chr_mode: u8,
+    prg_ram_protect: [u8; 2],
+    exram_mode: u8,
+    nametable_mapping: u8,
+    fill_tile: u8,
+    fill_attribute: u8,
+    /// $5113-$5117
+    prg_banks: [u8; 5],
+    /// $5120-$512B, with the upper bits from $5130 applied.
+    chr_banks: [u16; 12],
+    chr_upper: u8,
+    /// Whether $5128-$512B were written after $5120-$5127.
+    background_set_last: bool,
+
+    split_control: u8,
+    split_scroll: u8,
+    split_bank: u8,
+
+    irq_compare: u8,
+    irq_enabled: bool,
+    irq_pending: bool,
+    multiplicand: u8,
+    multiplier: u8,
+
+    sprite_8x16: bool,
+    rendering_enabled: bool,
+    in_frame: bool,
+    scanline: u8,
+    last_read: u16,
+    same_reads: u8,
+    /// PPU reads since the scanline started.
+    reads: u16,
+    idle_cycles: u8,
+    /// ExRAM byte for the tile being fetched in extended attribute mode.
+    extended_attribute: u8,
+    /// Tile number fetched from ExRAM inside the split.
+    split_tile: u8,
+
+    audio: Audio,
+}
+
+impl Mmc5 {
+    pub fn new(header: &Header, prg_rom: &[u8], chr_rom: &[u8]) -> Self {
+        Self {
+            prg_rom: prg_rom.to_vec(),
+            prg_ram: vec![0x00; header.prg_ram_len + header.prg_nvram_len],
+            chr: Chr::new(chr_rom, header.chr_ram_len + header.chr_nvram_len),
+            exram: [0x00; EXRAM_LEN],
+            prg_mode: 3,
+            chr_mode: 3,
+            prg_ram_protect: [0, 0],
+            exram_mode: 0,
+            nametable_mapping: 0,
+            fill_tile: 0,
+            fill_attribute: 0,
+            prg_banks: [0, 0, 0, 0, 0xFF],
+            chr_banks: [0; 12],
+            chr_upper: 0,
+            background_set_last: false,
+            split_control: 0,
+            split_scroll: 0,
+            split_bank: 0,
+            irq_compare: 0,
+            irq_enabled: false,
+            irq_pending: false,
+            multiplicand: 0xFF,
+            multiplier: 0xFF,
+            sprite_8x16: false,
+            rendering_enabled: false,
+            in_frame: false,
+            scanline: 0,
+            last_read: 0,
+            same_reads: 0,
+            reads: 0,
+            idle_cycles: 0,
+            extended_attribute: 0,
+            split_tile: 0,
+            audio: Default::default(),
+        }
+    }
+
+    pub fn in_frame(&self) -> bool {
+        self.in_frame
+    }
+
+    fn read_register(&mut self, address: u16) -> u8 {
+        match address {
+            0x5010 | 0x5015 => self.audio.read(address),
+            0x5204 => {
+                let data = u8::from(self.irq_pending) << 7 | u8::from(self.in_frame) << 6;
+                self.irq_pending = false;
+                data
+            }
+            0x5205 => self.product().to_le_bytes()[0],
+            0x5206 => self.product().to_le_bytes()[1],
+            0x5C00..=0x5FFF if self.exram_mode >= 2 => self.exram[usize::from(address - 0x5C00)],
+            _ => 0x00,
+        }
+    }
+
+    fn write_register(&mut self, address: u16, data: u8) {
+        match address {
+            0x5000..=0x5015 => self.audio.write(address, data),
+            0x5100 => self.prg_mode = data & 0b11,
+            0x5101 => self.chr_mode = data & 0b11,
+            0x5102 | 0x5103 => self.prg_ram_protect[usize::from(address - 0x5102)] = data & 0b11,
+            0x5104 => self.exram_mode = data & 0b11,
+            0x5105 => self.nametable_mapping = data,
+            0x5106 => self.fill_tile = data,
+            0x5107 => self.fill_attribute = data & 0b11,
+            0x5113..=0x5117 => self.prg_banks[usize::from(address - 0x5113)] = data,
+            0x5120..=0x512B => {
+                let register = usize::from(address - 0x5120);
+                self.chr_banks[register] = u16::from(data) | u16::from(self.chr_upper) << 8;
+                self.background_set_last = register >= 8;
+            }
+            0x5130 => self.chr_upper = data & 0b11,
+            0x5200 => self.split_control = data,
+            0x5201 => self.split_scroll = data,
+            0x5202 => self.split_bank = data,
+            0x5203 => self.irq_compare = data,
+            0x5204 => self.irq_enabled = data & 0x80 != 0,
+            0x5205 => self.multiplicand = data,
+            0x5206 => self.multiplier = data,
+            // While not rendering, ExRAM used by the PPU can only be cleared.
+            0x5C00..=0x5FFF => match self.exram_mode {
+                0 | 1 => {
+                    let data = if self.in_frame { data } else { 0x00 };
+                    self.exram[usize::from(address - 0x5C00)] = data;
+                }
+                2 => self.exram[usize::from(address - 0x5C00)] = data,
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    fn product(&self) -> u16 {
+        u16::from(self.multiplicand) * u16::from(self.multiplier)
+    }
+
+    /// (Is ROM, offset) of a CPU address in $6000-$FFFF.
+    fn prg_offset(&self, address: u16) -> (bool, usize) {
+        let (register, banks) = match (self.prg_mode, address) {
+            (_, 0x6000..=0x7FFF) => (0, 1),
+            (0, _) => (4, 4),
+            (1, 0x8000..=0xBFFF) | (2, 0x8000..=0xBFFF) => (2, 2),
+            (1, _) => (4, 2),
+            (2, 0xC000..=0xDFFF) => (3, 1),
+            (2, _) => (4, 1),
+            _ => (usize::from(address - 0x8000) / PRG_BANK_LEN + 1, 1),
+        };
+        let value = self.prg_banks[register];
+        let is_rom = register == 4 || (register > 0 && value & 0x80 != 0);
+        let bank = usize::from(value & 0x7F) & !(banks - 1);
+        let offset = bank * PRG_BANK_LEN + usize::from(address) % (banks * PRG_BANK_LEN);
+        (is_rom, offset)
+    }
+
+    fn prg_ram_writable(&self) -> bool {
+        self.prg_ram_protect == [0b10, 0b01]
+    }
+
+    fn rendering(&self) -> bool {
+        self.in_frame && self.rendering_enabled
+    }
+
+    /// Tracks the PPU's reads to find scanline boundaries and what is being fetched.
+    fn observe_ppu_read(&mut self, address: u16) -> Fetch {
+        self.idle_cycles = 0;
+        if address == self.last_read {
+            self.same_reads = self.same_reads.saturating_add(1);
+        } else {
+            self.same_reads = 0;
+        }
+        self.last_read = address;
+
+        if self.same_reads == 2 && (0x2000..=0x2FFF).contains(&address) {
+            self.start_scanline();
+        }
+
+        let fetch = self.fetch();
+        self.reads = self.reads.saturating_add(1);
+        fetch
+    }
+
+    fn start_scanline(&mut self) {
+        if self.in_frame {
+            self.scanline = self.scanline.wrapping_add(1);
+            if self.scanline == self.irq_compare && self.irq_compare != 0 {
+                self.irq_pending = true;
+            }
+        } else {
+            self.in_frame = true;
+            self.scanline = 0;
+            self.irq_pending = false;
+        }
+        self.reads = 0;
+    }
+
+    fn fetch(&self) -> Fetch {
+        if !self.rendering() {
+            return Fetch::Other;
+        }
+        let (tile_read, next_line) = match self.reads {
+            r if r < BG_READS => (r, false),
+            r if r < BG_READS + SPRITE_READS => return Fetch::Sprite,
+            r if r < BG_READS + SPRITE_READS + PREFETCH_READS => (r - SPRITE_READS, true),
+            _ => return Fetch::Other,
+        };
+        // Tiles 0 and 1 are fetched at the end of the previous scanline.
+        let column = ((tile_read / 4 + 2) % 34) as u8;
+        match tile_read % 4 {
+            0 => Fetch::Nametable { column, next_line },
+            1 => Fetch::Attribute { column, next_line },
+            _ => Fetch::Pattern { column, next_line },
+        }
+    }
+
+    fn in_split(&self, column: u8) -> bool {
+        let threshold = self.split_control & 0x1F;
+        let enabled = self.split_control & 0x80 != 0 && self.exram_mode <= 1;
+        let right = self.split_control & 0x40 != 0;
+        enabled
+            && (if right {
+                column >= threshold
+            } else {
+                column < threshold
+            })
+    }
+
+    fn split_y(&self, next_line: bool) -> usize {
+        let scanline = usize::from(self.scanline) + usize::from(next_line);
+        (usize::from(self.split_scroll) + scanline) % 240
+    }
+
+    fn chr_offset(&self, address: u16, fetch: Fetch) -> usize {
+        match fetch {
+            Fetch::Pattern { column, next_line } if self.in_split(column) => {
+                let y = self.split_y(next_line);
+                let plane = usize::from(address & 0x08);
+                let tile = usize::from(self.split_tile) * 16 + plane + y % 8;
+                usize::from(self.split_bank) * 0x1000 + tile
+            }
+            Fetch::Pattern { .. } if self.exram_mode == 1 => {
+                let bank =
+                    usize::from(self.extended_attribute & 0x3F) | usize::from(self.chr_upper) << 6;
+                bank * 0x1000 + usize::from(address & 0x0FFF)
+            }
+            _ => {
+                // With 8x16 sprites, the two register sets serve sprites and background.
+                let background = if self.sprite_8x16 && self.rendering() {
+                    fetch != Fetch::Sprite
+                } else {
+                    self.background_set_last
+                };
+                self.bank_offset(address, background)
+            }
+        }
+    }
+
+    fn bank_offset(&self, address: u16, background: bool) -> usize {
+        let address = usize::from(address & 0x1FFF);
+        let banks = 8 >> self.chr_mode;
+        let window = address / (banks * CHR_BANK_LEN);
+        let register = if background {
+            // $5128-$512B cover $0000-$0FFF and repeat at $1000-$1FFF.
+            let window = window % (4 / banks).max(1);
+            8 + (window + 1) * banks.min(4) - 1
+        } else {
+            (window + 1) * banks - 1
+        };
+        let bank = usize::from(self.chr_banks[register]);
+        bank * banks * CHR_BANK_LEN + address % (banks * CHR_BANK_LEN)
+    }
+
+    fn nametable_source(&self, address: u16) -> u8 {
+        let nametable = (address >> 10) & 0b11;
+        self.nametable_mapping >> (nametable * 2) & 0b11
+    }
+}
+
+impl Cartridge for Mmc5 {
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        let data = match address {
+            0x5000..=0x5FFF => self.read_register(address),
+            0x6000..=0xFFFF => match self.prg_offset(address) {
+                (true, offset) => self.prg_rom[offset % self.prg_rom.len()],
+                (false, _) if self.prg_ram.is_empty() => 0x00,
+                (false, offset) => self.prg_ram[offset % self.prg_ram.len()],
+            },
+            _ => 0x00,
+        };
+        self.audio.observe_read(address, data);
+        data
+    }
+
+    fn cpu_peek(&mut self, address: u16) -> u8 {
+        match address {
+            0x5010 | 0x5204 => 0x00,
+            _ => self.cpu_read(address),
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, data: u8) {
+        match address {
+            0x5000..=0x5FFF => self.write_register(address, data),
+            0x6000..=0xFFFF => match self.prg_offset(address) {
+                (false, offset) if self.prg_ram_writable() && !self.prg_ram.is_empty() => {
+                    let len = self.prg_ram.len();
+                    self.prg_ram[offset % len] = data;
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        let fetch = self.observe_ppu_read(address);
+        self.chr.read(self.chr_offset(address, fetch))
+    }
+
+    fn ppu_peek(&mut self, address: u16) -> u8 {
+        self.chr.read(self.chr_offset(address, Fetch::Other))
+    }
+
+    fn ppu_write(&mut self, address: u16, data: u8) {
+        let offset = self.bank_offset(address, self.background_set_last);
+        self.chr.write(offset, data);
+    }
+
+    fn nametable_read(&mut self, address: u16, ciram: u8) -> u8 {
+        let fetch = self.observe_ppu_read(address);
+        let offset = usize::from(address & 0x03FF);
+
+        match fetch {
+            Fetch::Nametable { column, next_line } if self.in_split(column) => {
+                let y = self.split_y(next_line);
+                self.split_tile = self.exram[y / 8 * 32 + usize::from(column)];
+                return self.split_tile;
+            }
+            Fetch::Attribute { column, next_line } if self.in_split(column) => {
+                let y = self.split_y(next_line);
+                let attribute = self.exram[0x3C0 + y / 32 * 8 + usize::from(column) / 4];
+                let shift = (y / 16 % 2) * 4 + usize::from(column / 2 % 2) * 2;
+                return (attribute >> shift & 0b11) * 0x55;
+            }
+            Fetch::Nametable { .. } if self.exram_mode == 1 => {
+                self.extended_attribute = self.exram[offset];
+            }
+            Fetch::Attribute { .. } if self.exram_mode == 1 => {
+                return (self.extended_attribute >> 6) * 0x55;
+            }
+            _ => {}
+        }
+        self.nametable_peek(address, ciram)
+    }
+
+    fn nametable_peek(&mut self, address: u16, ciram: u8) -> u8 {
+        let offset = usize::from(address & 0x03FF);
+        match self.nametable_source(address) {
+            0 | 1 => ciram,
+            2 if self.exram_mode <= 1 => self.exram[offset],
+            2 => 0x00,
+            _ if offset >= 0x3C0 => self.fill_attribute * 0x55,
+            _ => self.fill_tile,
+        }
+    }
+
+    fn nametable_write(&mut self, address: u16, data: u8) -> bool {
+        match self.nametable_source(address) {
+            0 | 1 => false,
+            2 => {
+                if self.exram_mode <= 1 {
+                    self.exram[usize::from(address & 0x03FF)] = data;
+                }
+                true
+            }
+            _ => true,
+        }
+    }
+
+    fn ppu_register_write(&mut self, address: u16, data: u8) {
+        match address {
+            0x2000 => self.sprite_8x16 = data & 0x20 != 0,
+            0x2001 => {
+                self.rendering_enabled = data & 0b0001_1000 != 0;
+                if !self.rendering_enabled {
+                    self.in_frame = false;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.nametable_mapping {
+            0x00 => Mirroring::SingleScreenLower,
+            0x55 => Mirroring::SingleScreenUpper,
+            0x44 => Mirroring::Vertical,
+            _ => Mirroring::Horizontal,
+        }
+    }
+
+    fn ciram_page(&self, nametable: usize) -> usize {
+        usize::from(self.nametable_mapping >> (nametable * 2) & 0b1)
+    }
+
+    fn clock(&mut self) {
+        self.audio.clock();
+        if self.in_frame {
+            self.idle_cycles += 1;
+            if self.idle_cycles >= IDLE_CYCLES {
+                self.in_frame = false;
+                self.last_read = 0;
+                self.same_reads = 0;
+            }
+        }
+    }
+
+    fn irq(&self) -> bool {
+        (self.irq_pending && self.irq_enabled) || self.audio.irq()
+    }
+
+    fn audio(&self) -> f32 {
+        self.audio.output()
+    }
+}
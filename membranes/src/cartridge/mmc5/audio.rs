@@ -0,0 +1,196 @@
+//! MMC5 expansion audio: two APU-style pulse channels without sweep, and 8-bit PCM.
+
+/// CPU cycles between envelope and length counter clocks, from the MMC5's own
+/// 240 Hz timer rather than the APU frame counter.
+const QUARTER_FRAME_CYCLES: u16 = 7457;
+/// PCM at full scale is about as loud as the APU's DMC at full scale.
+const PCM_LEVEL: f32 = 0.5745;
+
+const LENGTHS: [u8; 32] = [
+    10, 254, 20, 2, 40, 4, 80, 6, 160, 8, 60, 10, 14, 12, 26, 14, 12, 16, 24, 18, 48, 20, 96, 22,
+    192, 24, 72, 26, 16, 28, 32, 30,
+];
+
+const DUTIES: [[u8; 8]; 4] = [
+    [0, 1, 0, 0, 0, 0, 0, 0],
+    [0, 1, 1, 0, 0, 0, 0, 0],
+    [0, 1, 1, 1, 1, 0, 0, 0],
+    [1, 0, 0, 1, 1, 1, 1, 1],
+];
+
+#[derive(Default)]
+pub struct Audio {
+    pulses: [Pulse; 2],
+    pcm_read_mode: bool,
+    pcm_irq_enabled: bool,
+    pcm_irq: bool,
+    pcm: u8,
+    odd_cycle: bool,
+    quarter_frame_timer: u16,
+}
+
+impl Audio {
+    pub fn read(&mut self, address: u16) -> u8 {
+        match address {
+            0x5010 => {
+                let data = u8::from(self.pcm_irq) << 7 | u8::from(self.pcm_read_mode);
+                self.pcm_irq = false;
+                data
+            }
+            0x5015 => {
+                u8::from(self.pulses[1].length > 0) << 1 | u8::from(self.pulses[0].length > 0)
+            }
+            _ => 0x00,
+        }
+    }
+
+    pub fn write(&mut self, address: u16, data: u8) {
+        match address {
+            0x5000..=0x5007 => {
+                let pulse = &mut self.pulses[usize::from(address - 0x5000) / 4];
+                pulse.write(address & 0b11, data);
+            }
+            0x5010 => {
+                self.pcm_read_mode = data & 0b1 != 0;
+                self.pcm_irq_enabled = data & 0x80 != 0;
+            }
+            0x5011 if !self.pcm_read_mode => self.write_pcm(data),
+            0x5015 => {
+                for (i, pulse) in self.pulses.iter_mut().enumerate() {
+                    pulse.set_enabled(data >> i & 0b1 != 0);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// In read mode, the PCM level follows CPU reads from $8000-$BFFF.
+    pub fn observe_read(&mut self, address: u16, data: u8) {
+        if self.pcm_read_mode && (0x8000..=0xBFFF).contains(&address) {
+            self.write_pcm(data);
+        }
+    }
+
+    /// Zero can't be played, it raises IRQ instead.
+    fn write_pcm(&mut self, data: u8) {
+        if data == 0 {
+            self.pcm_irq = true;
+        } else {
+            self.pcm = data;
+        }
+    }
+
+    pub fn irq(&self) -> bool {
+        self.pcm_irq && self.pcm_irq_enabled
+    }
+
+    pub fn clock(&mut self) {
+        self.odd_cycle = !self.odd_cycle;
+        if self.odd_cycle {
+            self.pulses.iter_mut().for_each(Pulse::clock_timer);
+        }
+
+        self.quarter_frame_timer += 1;
+        if self.quarter_frame_timer >= QUARTER_FRAME_CYCLES {
+            self.quarter_frame_timer = 0;
+            self.pulses.iter_mut().for_each(Pulse::clock_frame);
+        }
+    }
+
+    pub fn output(&self) -> f32 {
+        let pulses = f32::from(self.pulses[0].output() + self.pulses[1].output());
+        let pulses = if pulses == 0.0 {
+            0.0
+        } else {
+            95.88 / (8128.0 / pulses + 100.0)
+        };
+        pulses + f32::from(self.pcm) / 255.0 * PCM_LEVEL
+    }
+}
+
+#[derive(Default)]
+struct Pulse {
+    enabled: bool,
+    duty: usize,
+    step: usize,
+    period: u16,
+    timer: u16,
+    length: u8,
+    /// Also halts the length counter.
+    envelope_loop: bool,
+    constant_volume: bool,
+    volume: u8,
+    envelope_start: bool,
+    envelope_divider: u8,
+    envelope_decay: u8,
+}
+
+impl Pulse {
+    fn write(&mut self, register: u16, data: u8) {
+        match register {
+            0 => {
+                self.duty = usize::from(data >> 6);
+                self.envelope_loop = data & 0x20 != 0;
+                self.constant_volume = data & 0x10 != 0;
+                self.volume = data & 0x0F;
+            }
+            2 => self.period = self.period & 0x0700 | u16::from(data),
+            3 => {
+                self.period = self.period & 0x00FF | u16::from(data & 0b111) << 8;
+                if self.enabled {
+                    self.length = LENGTHS[usize::from(data >> 3)];
+                }
+                self.step = 0;
+                self.envelope_start = true;
+            }
+            _ => {}
+        }
+    }
+
+    fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.length = 0;
+        }
+    }
+
+    fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.period;
+            self.step = (self.step + 1) % 8;
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    fn clock_frame(&mut self) {
+        if self.envelope_start {
+            self.envelope_start = false;
+            self.envelope_decay = 15;
+            self.envelope_divider = self.volume;
+        } else if self.envelope_divider == 0 {
+            self.envelope_divider = self.volume;
+            if self.envelope_decay > 0 {
+                self.envelope_decay -= 1;
+            } else if self.envelope_loop {
+                self.envelope_decay = 15;
+            }
+        } else {
+            self.envelope_divider -= 1;
+        }
+
+        if !self.envelope_loop && self.length > 0 {
+            self.length -= 1;
+        }
+    }
+
+    fn output(&self) -> u8 {
+        if self.length == 0 || DUTIES[self.duty][self.step] == 0 {
+            0
+        } else if self.constant_volume {
+            self.volume
+        } else {
+            self.envelope_decay
+        }
+    }
+}
@@ -0,0 +1,234 @@
+//! Namco 163 (mapper 19): 8 KiB PRG banks, 1 KiB CHR banks that can also select
+//! nametable RAM, a CPU cycle IRQ counter and wavetable audio.
+//!
+//! The chip drives CIRAM's enable and A10, so the board keeps the 2 KiB of
+//! nametable RAM here. NES 2.0 submapper 2 boards leave the audio unconnected.
+
+mod audio;
+
+use super::{last_bank, Cartridge, Chr, Mirroring};
+use crate::rom::Header;
+use audio::Audio;
+
+const PRG_BANK_LEN: usize = 0x2000;
+const CHR_BANK_LEN: usize = 0x0400;
+/// Banks from $E0 select nametable RAM instead of CHR-ROM.
+const CIRAM_BANKS: u8 = 0xE0;
+const IRQ_MAX: u16 = 0x7FFF;
+
+pub struct Namco163 {
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    chr: Chr,
+    ciram: [u8; 0x800],
+    battery: bool,
+    prg_banks: [u8; 3],
+    /// Pattern table banks followed by nametable banks.
+    chr_banks: [u8; 12],
+    /// Whether $E0-$FF select CHR-ROM for the low and high pattern tables.
+    ciram_disabled: [bool; 2],
+    /// $F800, shared with the audio address port.
+    write_protect: u8,
+    irq_counter: u16,
+    irq_enabled: bool,
+    irq_pending: bool,
+    audio_connected: bool,
+    audio_disabled: bool,
+    audio: Audio,
+}
+
+impl Namco163 {
+    pub fn new(header: &Header, prg_rom: &[u8], chr_rom: &[u8]) -> Self {
+        Self {
+            prg_rom: prg_rom.to_vec(),
+            prg_ram: vec![0x00; header.prg_ram_len + header.prg_nvram_len],
+            chr: Chr::new(chr_rom, header.chr_ram_len + header.chr_nvram_len),
+            ciram: [0x00; 0x800],
+            battery: header.has_battery,
+            prg_banks: [0; 3],
+            chr_banks: [0; 12],
+            ciram_disabled: [false; 2],
+            write_protect: 0,
+            irq_counter: 0,
+            irq_enabled: false,
+            irq_pending: false,
+            audio_connected: header.submapper != 2,
+            audio_disabled: false,
+            audio: Audio::default(),
+        }
+    }
+
+    fn write_register(&mut self, address: u16, data: u8) {
+        match address {
+            0x8000..=0xDFFF => self.chr_banks[usize::from(address - 0x8000) / 0x800] = data,
+            0xE000..=0xE7FF => {
+                self.prg_banks[0] = data & 0x3F;
+                self.audio_disabled = data & 0x40 != 0;
+            }
+            0xE800..=0xEFFF => {
+                self.prg_banks[1] = data & 0x3F;
+                self.ciram_disabled = [data & 0x40 != 0, data & 0x80 != 0];
+            }
+            0xF000..=0xF7FF => self.prg_banks[2] = data & 0x3F,
+            _ => {
+                self.write_protect = data;
+                self.audio.select(data);
+            }
+        }
+    }
+
+    /// Writes need $F800 to read 0100 in its top bits and the 2 KiB window's bit clear.
+    fn prg_ram_writable(&self, address: u16) -> bool {
+        let window = (address - 0x6000) / 0x800;
+        self.write_protect & 0xF0 == 0x40 && self.write_protect >> window & 0b1 == 0
+    }
+
+    fn prg_rom_offset(&self, address: u16) -> usize {
+        let bank = match address {
+            0x8000..=0xDFFF => usize::from(self.prg_banks[usize::from(address - 0x8000) / 0x2000]),
+            _ => last_bank(self.prg_rom.len(), PRG_BANK_LEN),
+        };
+        (bank * PRG_BANK_LEN + usize::from(address & 0x1FFF)) % self.prg_rom.len()
+    }
+
+    /// CIRAM offset if a 1 KiB window at $0000-$2FFF selects nametable RAM.
+    fn ciram_offset(&self, address: u16) -> Option<usize> {
+        let window = window(address);
+        let bank = self.chr_banks[window];
+        let disabled = window < 8 && self.ciram_disabled[window / 4];
+        (bank >= CIRAM_BANKS && !disabled)
+            .then(|| usize::from(bank & 0b1) * 0x400 + usize::from(address & 0x03FF))
+    }
+
+    fn chr_offset(&self, address: u16) -> usize {
+        let bank = self.chr_banks[window(address)];
+        usize::from(bank) * CHR_BANK_LEN + usize::from(address & 0x03FF)
+    }
+}
+
+/// Bank register for a PPU address: 8 pattern table windows, then 4 nametables.
+fn window(address: u16) -> usize {
+    match address {
+        0x0000..=0x1FFF => usize::from(address) / CHR_BANK_LEN,
+        _ => 8 + usize::from(address >> 10 & 0b11),
+    }
+}
+
+impl Cartridge for Namco163 {
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        match address {
+            0x4800..=0x4FFF => self.audio.read(),
+            0x5000..=0x57FF => self.irq_counter as u8,
+            0x5800..=0x5FFF => u8::from(self.irq_enabled) << 7 | (self.irq_counter >> 8) as u8,
+            0x6000..=0x7FFF if !self.prg_ram.is_empty() => {
+                self.prg_ram[usize::from(address - 0x6000) % self.prg_ram.len()]
+            }
+            0x8000..=0xFFFF => self.prg_rom[self.prg_rom_offset(address)],
+            _ => 0x00,
+        }
+    }
+
+    fn cpu_peek(&mut self, address: u16) -> u8 {
+        match address {
+            0x4800..=0x4FFF => self.audio.peek(),
+            _ => self.cpu_read(address),
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, data: u8) {
+        match address {
+            0x4800..=0x4FFF => self.audio.write(data),
+            0x5000..=0x57FF => {
+                self.irq_counter = self.irq_counter & 0x7F00 | u16::from(data);
+                self.irq_pending = false;
+            }
+            0x5800..=0x5FFF => {
+                self.irq_counter = self.irq_counter & 0x00FF | u16::from(data & 0x7F) << 8;
+                self.irq_enabled = data & 0x80 != 0;
+                self.irq_pending = false;
+            }
+            0x6000..=0x7FFF if !self.prg_ram.is_empty() && self.prg_ram_writable(address) => {
+                let len = self.prg_ram.len();
+                self.prg_ram[usize::from(address - 0x6000) % len] = data;
+            }
+            0x8000..=0xFFFF => self.write_register(address, data),
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        match self.ciram_offset(address) {
+            Some(offset) => self.ciram[offset],
+            None => self.chr.read(self.chr_offset(address)),
+        }
+    }
+
+    fn ppu_write(&mut self, address: u16, data: u8) {
+        match self.ciram_offset(address) {
+            Some(offset) => self.ciram[offset] = data,
+            None => self.chr.write(self.chr_offset(address), data),
+        }
+    }
+
+    fn nametable_read(&mut self, address: u16, _ciram: u8) -> u8 {
+        self.ppu_read(address)
+    }
+
+    fn nametable_write(&mut self, address: u16, data: u8) -> bool {
+        self.ppu_write(address, data);
+        true
+    }
+
+    /// Only meaningful while every nametable selects CIRAM.
+    fn mirroring(&self) -> Mirroring {
+        match [8, 9, 10, 11].map(|i| self.chr_banks[i] & 0b1) {
+            [0, 0, 0, 0] => Mirroring::SingleScreenLower,
+            [1, 1, 1, 1] => Mirroring::SingleScreenUpper,
+            [0, 0, 1, 1] => Mirroring::Horizontal,
+            _ => Mirroring::Vertical,
+        }
+    }
+
+    fn ciram_page(&self, nametable: usize) -> usize {
+        usize::from(self.chr_banks[8 + nametable % 4] & 0b1)
+    }
+
+    fn clock(&mut self) {
+        if self.irq_enabled && self.irq_counter < IRQ_MAX {
+            self.irq_counter += 1;
+            if self.irq_counter == IRQ_MAX {
+                self.irq_pending = true;
+            }
+        }
+        self.audio.clock();
+    }
+
+    fn irq(&self) -> bool {
+        self.irq_pending
+    }
+
+    fn audio(&self) -> f32 {
+        if self.audio_connected && !self.audio_disabled {
+            self.audio.output()
+        } else {
+            0.0
+        }
+    }
+
+    /// PRG-RAM followed by the internal RAM, which holds save data on some boards.
+    fn battery_ram(&self) -> Vec<u8> {
+        if !self.battery {
+            return Vec::new();
+        }
+        [&self.prg_ram[..], self.audio.ram()].concat()
+    }
+
+    fn load_battery_ram(&mut self, data: &[u8]) {
+        if !self.battery {
+            return;
+        }
+        let (prg_ram, internal) = data.split_at(self.prg_ram.len().min(data.len()));
+        self.prg_ram[..prg_ram.len()].copy_from_slice(prg_ram);
+        self.audio.load_ram(internal);
+    }
+}
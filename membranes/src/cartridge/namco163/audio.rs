@@ -0,0 +1,123 @@
+//! Namco 163 wavetable audio: up to 8 channels reading 4-bit samples from the
+//! 128 bytes of internal RAM that also hold their registers.
+//!
+//! One DAC is shared by the enabled channels, switching between them every 15 CPU
+//! cycles. With many channels enabled, the switching rate becomes audible as on hardware.
+
+use crate::audio::PULSE_LEVEL;
+
+const RAM_LEN: usize = 0x80;
+/// CPU cycles spent on each channel.
+const CHANNEL_CYCLES: u8 = 15;
+/// Channel registers are 8 bytes each, channel 7's at the top of RAM.
+const REGISTERS: usize = 0x40;
+/// A full-scale sample at full volume is mixed at twice an APU pulse at full volume.
+const STEP_LEVEL: f32 = 2.0 * PULSE_LEVEL / 225.0;
+
+pub struct Audio {
+    ram: [u8; RAM_LEN],
+    address: u8,
+    auto_increment: bool,
+    timer: u8,
+    /// Channel being updated and output, counting down from 7.
+    channel: usize,
+    output: u8,
+}
+
+impl Default for Audio {
+    fn default() -> Self {
+        Self {
+            ram: [0x00; RAM_LEN],
+            address: 0,
+            auto_increment: false,
+            timer: 0,
+            channel: 7,
+            output: 0,
+        }
+    }
+}
+
+impl Audio {
+    /// Writes to the address port at $F800.
+    pub fn select(&mut self, data: u8) {
+        self.address = data & 0x7F;
+        self.auto_increment = data & 0x80 != 0;
+    }
+
+    pub fn read(&mut self) -> u8 {
+        let data = self.peek();
+        self.increment();
+        data
+    }
+
+    pub fn peek(&self) -> u8 {
+        self.ram[usize::from(self.address)]
+    }
+
+    pub fn write(&mut self, data: u8) {
+        self.ram[usize::from(self.address)] = data;
+        self.increment();
+    }
+
+    fn increment(&mut self) {
+        if self.auto_increment {
+            self.address = (self.address + 1) & 0x7F;
+        }
+    }
+
+    pub fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    pub fn load_ram(&mut self, data: &[u8]) {
+        let len = data.len().min(RAM_LEN);
+        self.ram[..len].copy_from_slice(&data[..len]);
+    }
+
+    /// Enabled channels, from $7F.
+    fn channel_count(&self) -> usize {
+        usize::from(self.ram[0x7F] >> 4 & 0b111) + 1
+    }
+
+    pub fn clock(&mut self) {
+        self.timer += 1;
+        if self.timer < CHANNEL_CYCLES {
+            return;
+        }
+        self.timer = 0;
+        self.channel = if self.channel > 8 - self.channel_count() {
+            self.channel - 1
+        } else {
+            7
+        };
+        self.update_channel();
+    }
+
+    /// Advances the current channel's phase and latches its sample.
+    fn update_channel(&mut self) {
+        let base = REGISTERS + self.channel * 8;
+        let registers = &self.ram[base..base + 8];
+        let frequency = u32::from(registers[4] & 0b11) << 16
+            | u32::from(registers[2]) << 8
+            | u32::from(registers[0]);
+        let phase =
+            u32::from(registers[5]) << 16 | u32::from(registers[3]) << 8 | u32::from(registers[1]);
+        let length = 256 - u32::from(registers[4] & 0xFC);
+        let offset = u32::from(registers[6]);
+        let volume = registers[7] & 0x0F;
+
+        let phase = (phase + frequency) % (length << 16);
+        let nibble = ((phase >> 16) + offset) as usize & 0xFF;
+        let sample = self.ram[nibble / 2] >> (nibble % 2 * 4) & 0x0F;
+        self.output = sample * volume;
+
+        let registers = &mut self.ram[base..base + 8];
+        registers[1] = phase as u8;
+        registers[3] = (phase >> 8) as u8;
+        registers[5] = (phase >> 16) as u8;
+    }
+
+    pub fn output(&self) -> f32 {
+        f32::from(self.output) * STEP_LEVEL
+    }
+}
@@ -0,0 +1,72 @@
+//! NROM (mapper 0): 16 or 32 KiB PRG-ROM and 8 KiB CHR without banking.
+
+use super::{Cartridge, Chr, Mirroring};
+use crate::rom::Header;
+
+pub struct Nrom {
+    /// 16 KiB boards mirror their PRG-ROM into $C000-$FFFF.
+    prg_rom: Vec<u8>,
+    /// Only present on Family BASIC boards.
+    prg_ram: Vec<u8>,
+    chr: Chr,
+    mirroring: Mirroring,
+}
+
+impl Nrom {
+    pub fn new(header: &Header, prg_rom: &[u8], chr_rom: &[u8]) -> Self {
+        Self {
+            prg_rom: prg_rom.to_vec(),
+            prg_ram: vec![0x00; header.prg_ram_len + header.prg_nvram_len],
+            chr: Chr::new(chr_rom, header.chr_ram_len + header.chr_nvram_len),
+            mirroring: header.mirroring.into(),
+        }
+    }
+}
+
+impl Default for Nrom {
+    /// Blank 32 KiB board, used before anything is loaded.
+    fn default() -> Self {
+        Self {
+            prg_rom: vec![0x00; 0x8000],
+            prg_ram: Vec::new(),
+            chr: Chr::new(&[], 0x2000),
+            mirroring: Mirroring::Horizontal,
+        }
+    }
+}
+
+impl Cartridge for Nrom {
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        match address {
+            0x6000..=0x7FFF if !self.prg_ram.is_empty() => {
+                self.prg_ram[usize::from(address - 0x6000) % self.prg_ram.len()]
+            }
+            0x8000..=0xFFFF if !self.prg_rom.is_empty() => {
+                self.prg_rom[usize::from(address - 0x8000) % self.prg_rom.len()]
+            }
+            _ => 0x00,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, data: u8) {
+        match address {
+            0x6000..=0x7FFF if !self.prg_ram.is_empty() => {
+                let len = self.prg_ram.len();
+                self.prg_ram[usize::from(address - 0x6000) % len] = data;
+            }
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        self.chr.read(usize::from(address & 0x1FFF))
+    }
+
+    fn ppu_write(&mut self, address: u16, data: u8) {
+        self.chr.write(usize::from(address & 0x1FFF), data);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
@@ -0,0 +1,203 @@
+//! Konami VRC2 and VRC4 (mappers 21, 22, 23 and 25).
+//!
+//! Boards connect different CPU address lines to the chip's two register selects.
+//! NES 2.0 submappers name the exact wiring, otherwise every candidate for the
+//! mapper is decoded at once, as the combinations never overlap in practice.
+//! Mapper 23 boards start out as VRC2 and switch to VRC4 once the game touches
+//! a register only VRC4 has.
+
+use super::{last_bank, vrc_irq::VrcIrq, Cartridge, Chr, Mirroring};
+use crate::rom::Header;
+
+const PRG_BANK_LEN: usize = 0x2000;
+const CHR_BANK_LEN: usize = 0x0400;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chip {
+    /// No IRQ or PRG swapping, and a one bit latch at $6000 on boards without RAM.
+    Vrc2,
+    Vrc4,
+}
+
+pub struct Vrc2 {
+    chip: Chip,
+    /// CPU address lines wired to the chip's A0 and A1.
+    lines: (u16, u16),
+    /// Set while the chip is unknown: the lines only the VRC4 candidate uses.
+    vrc4_lines: Option<u16>,
+    /// VRC2a leaves out the lowest CHR bank bit.
+    chr_shift: u8,
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    chr: Chr,
+    prg_banks: [u8; 2],
+    prg_swapped: bool,
+    chr_banks: [u16; 8],
+    mirroring: Mirroring,
+    microwire: u8,
+    /// iNES 1.0 headers claim PRG-RAM for every board. Without a battery to back
+    /// that up, VRC2 boards get the latch instead, until they turn out to be VRC4.
+    ram_unconfirmed: bool,
+    irq: VrcIrq,
+}
+
+impl Vrc2 {
+    /// `is_nes2` says whether the header's PRG-RAM size can be trusted.
+    pub fn new(header: &Header, is_nes2: bool, prg_rom: &[u8], chr_rom: &[u8]) -> Self {
+        let (chip, lines) = match (header.mapper, header.submapper) {
+            (21, 1) => (Chip::Vrc4, (0x02, 0x04)),
+            (21, 2) => (Chip::Vrc4, (0x40, 0x80)),
+            (21, _) => (Chip::Vrc4, (0x42, 0x84)),
+            (22, _) => (Chip::Vrc2, (0x02, 0x01)),
+            (23, 1) => (Chip::Vrc4, (0x01, 0x02)),
+            (23, 2) => (Chip::Vrc4, (0x04, 0x08)),
+            (23, 3) => (Chip::Vrc2, (0x01, 0x02)),
+            (23, _) => (Chip::Vrc2, (0x05, 0x0A)),
+            (_, 1) => (Chip::Vrc4, (0x02, 0x01)),
+            (_, 2) => (Chip::Vrc4, (0x08, 0x04)),
+            (_, 3) => (Chip::Vrc2, (0x02, 0x01)),
+            _ => (Chip::Vrc4, (0x0A, 0x05)),
+        };
+        // VRC2b and VRC4e share mapper 23, told apart by VRC4e's A2 and A3.
+        let vrc4_lines = match (header.mapper, header.submapper) {
+            (23, 0) => Some(0x0C),
+            _ => None,
+        };
+        Self {
+            chip,
+            lines,
+            vrc4_lines,
+            chr_shift: u8::from(header.mapper == 22),
+            prg_rom: prg_rom.to_vec(),
+            prg_ram: vec![0x00; header.prg_ram_len + header.prg_nvram_len],
+            chr: Chr::new(chr_rom, header.chr_ram_len + header.chr_nvram_len),
+            prg_banks: [0, 1],
+            prg_swapped: false,
+            chr_banks: [0; 8],
+            mirroring: header.mirroring.into(),
+            microwire: 0,
+            ram_unconfirmed: !is_nes2 && !header.has_battery,
+            irq: VrcIrq::default(),
+        }
+    }
+
+    pub fn chip(&self) -> Chip {
+        self.chip
+    }
+
+    fn prg_ram_enabled(&self) -> bool {
+        !self.prg_ram.is_empty() && (!self.ram_unconfirmed || self.chip == Chip::Vrc4)
+    }
+
+    /// Normalizes a board address to $x000-$x003.
+    fn register(&self, address: u16) -> u16 {
+        let (a0, a1) = self.lines;
+        address & 0xF000 | u16::from(address & a1 != 0) << 1 | u16::from(address & a0 != 0)
+    }
+
+    fn write_register(&mut self, register: u16, data: u8) {
+        match (register, self.chip) {
+            (0x8000..=0x8003, _) => self.prg_banks[0] = data & 0x1F,
+            (0x9000..=0x9003, Chip::Vrc2) => self.mirroring = mirroring(data & 0b1),
+            (0x9000..=0x9001, Chip::Vrc4) => self.mirroring = mirroring(data & 0b11),
+            (0x9002..=0x9003, Chip::Vrc4) => self.prg_swapped = data & 0b10 != 0,
+            (0xA000..=0xA003, _) => self.prg_banks[1] = data & 0x1F,
+            (0xB000..=0xEFFF, _) => {
+                let bank =
+                    usize::from(register - 0xB000) / 0x1000 * 2 + usize::from(register & 0b10) / 2;
+                let data = u16::from(data);
+                let bank = &mut self.chr_banks[bank];
+                *bank = if register & 0b1 == 0 {
+                    *bank & 0x1F0 | data & 0x0F
+                } else {
+                    *bank & 0x00F | (data & 0x1F) << 4
+                };
+            }
+            (0xF000, Chip::Vrc4) => self.irq.write_latch_low(data),
+            (0xF001, Chip::Vrc4) => self.irq.write_latch_high(data),
+            (0xF002, Chip::Vrc4) => self.irq.write_control(data),
+            (0xF003, Chip::Vrc4) => self.irq.acknowledge(),
+            _ => {}
+        }
+    }
+
+    fn prg_rom_offset(&self, address: u16) -> usize {
+        let last = last_bank(self.prg_rom.len(), PRG_BANK_LEN);
+        let bank = match ((address - 0x8000) / 0x2000, self.prg_swapped) {
+            (0, false) | (2, true) => usize::from(self.prg_banks[0]),
+            (1, _) => usize::from(self.prg_banks[1]),
+            (0, true) | (2, false) => last.saturating_sub(1),
+            _ => last,
+        };
+        (bank * PRG_BANK_LEN + usize::from(address & 0x1FFF)) % self.prg_rom.len()
+    }
+
+    fn chr_offset(&self, address: u16) -> usize {
+        let bank = self.chr_banks[usize::from(address & 0x1FFF) / CHR_BANK_LEN] >> self.chr_shift;
+        usize::from(bank) * CHR_BANK_LEN + usize::from(address & 0x03FF)
+    }
+}
+
+/// VRC2 only has the low bit.
+fn mirroring(data: u8) -> Mirroring {
+    match data {
+        0 => Mirroring::Vertical,
+        1 => Mirroring::Horizontal,
+        2 => Mirroring::SingleScreenLower,
+        _ => Mirroring::SingleScreenUpper,
+    }
+}
+
+impl Cartridge for Vrc2 {
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        match address {
+            0x6000..=0x7FFF if self.prg_ram_enabled() => {
+                self.prg_ram[usize::from(address - 0x6000) % self.prg_ram.len()]
+            }
+            0x6000..=0x6FFF if self.chip == Chip::Vrc2 => self.microwire,
+            0x8000..=0xFFFF => self.prg_rom[self.prg_rom_offset(address)],
+            _ => 0x00,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, data: u8) {
+        match address {
+            0x6000..=0x7FFF if self.prg_ram_enabled() => {
+                let len = self.prg_ram.len();
+                self.prg_ram[usize::from(address - 0x6000) % len] = data;
+            }
+            0x6000..=0x6FFF if self.chip == Chip::Vrc2 => self.microwire = data & 0b1,
+            0x8000..=0xFFFF => {
+                if let Some(lines) = self.vrc4_lines {
+                    // VRC2 has no registers at $F000, its IRQ counter.
+                    if address >= 0xF000 || address & lines != 0 {
+                        self.chip = Chip::Vrc4;
+                        self.vrc4_lines = None;
+                    }
+                }
+                self.write_register(self.register(address), data);
+            }
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        self.chr.read(self.chr_offset(address))
+    }
+
+    fn ppu_write(&mut self, address: u16, data: u8) {
+        self.chr.write(self.chr_offset(address), data);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn clock(&mut self) {
+        self.irq.clock();
+    }
+
+    fn irq(&self) -> bool {
+        self.irq.pending()
+    }
+}
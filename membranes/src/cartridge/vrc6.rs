@@ -0,0 +1,153 @@
+//! Konami VRC6 (mappers 24 and 26) with its two pulse and sawtooth channels.
+//!
+//! Mapper 26 boards swap the chip's A0 and A1.
+
+mod audio;
+
+use super::{last_bank, vrc_irq::VrcIrq, Cartridge, Chr, Mirroring};
+use crate::rom::Header;
+use audio::Audio;
+
+const CHR_BANK_LEN: usize = 0x0400;
+
+pub struct Vrc6 {
+    swapped_lines: bool,
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    chr: Chr,
+    /// 16 KiB bank at $8000 and 8 KiB bank at $C000.
+    prg_banks: [u8; 2],
+    chr_banks: [u8; 8],
+    /// $B003
+    banking_mode: u8,
+    irq: VrcIrq,
+    audio: Audio,
+}
+
+impl Vrc6 {
+    pub fn new(header: &Header, prg_rom: &[u8], chr_rom: &[u8]) -> Self {
+        Self {
+            swapped_lines: header.mapper == 26,
+            prg_rom: prg_rom.to_vec(),
+            prg_ram: vec![0x00; header.prg_ram_len + header.prg_nvram_len],
+            chr: Chr::new(chr_rom, header.chr_ram_len + header.chr_nvram_len),
+            prg_banks: [0, 0],
+            chr_banks: [0; 8],
+            banking_mode: 0,
+            irq: VrcIrq::default(),
+            audio: Default::default(),
+        }
+    }
+
+    /// Normalizes a board address to $x000-$x003.
+    fn register(&self, address: u16) -> u16 {
+        if self.swapped_lines {
+            address & 0xF000 | (address & 0b01) << 1 | (address & 0b10) >> 1
+        } else {
+            address & 0xF003
+        }
+    }
+
+    fn write_register(&mut self, register: u16, data: u8) {
+        match register {
+            0x8000..=0x8003 => self.prg_banks[0] = data & 0x0F,
+            0x9000..=0x9003 | 0xA000..=0xA002 | 0xB000..=0xB002 => {
+                self.audio.write(register, data);
+            }
+            0xB003 => self.banking_mode = data,
+            0xC000..=0xC003 => self.prg_banks[1] = data & 0x1F,
+            0xD000..=0xE003 => {
+                let bank =
+                    usize::from(register - 0xD000) / 0x1000 * 4 + usize::from(register & 0b11);
+                self.chr_banks[bank] = data;
+            }
+            0xF000 => self.irq.write_latch(data),
+            0xF001 => self.irq.write_control(data),
+            0xF002 => self.irq.acknowledge(),
+            _ => {}
+        }
+    }
+
+    fn prg_ram_enabled(&self) -> bool {
+        !self.prg_ram.is_empty() && self.banking_mode & 0x80 != 0
+    }
+
+    fn prg_rom_offset(&self, address: u16) -> usize {
+        let last = last_bank(self.prg_rom.len(), 0x2000);
+        let offset = match address {
+            0x8000..=0xBFFF => {
+                usize::from(self.prg_banks[0]) * 0x4000 + usize::from(address & 0x3FFF)
+            }
+            0xC000..=0xDFFF => {
+                usize::from(self.prg_banks[1]) * 0x2000 + usize::from(address & 0x1FFF)
+            }
+            _ => last * 0x2000 + usize::from(address & 0x1FFF),
+        };
+        offset % self.prg_rom.len()
+    }
+
+    /// Modes 2 and 3 only differ in how nametables are sourced.
+    fn chr_offset(&self, address: u16) -> usize {
+        let window = usize::from(address & 0x1FFF) / CHR_BANK_LEN;
+        let (bank, len) = match (self.banking_mode & 0b11, window) {
+            (0, _) => (self.chr_banks[window], CHR_BANK_LEN),
+            (1, _) => (self.chr_banks[window / 2], 2 * CHR_BANK_LEN),
+            (_, 0..=3) => (self.chr_banks[window], CHR_BANK_LEN),
+            _ => (self.chr_banks[4 + (window - 4) / 2], 2 * CHR_BANK_LEN),
+        };
+        usize::from(bank) * len + usize::from(address) % len
+    }
+}
+
+impl Cartridge for Vrc6 {
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        match address {
+            0x6000..=0x7FFF if self.prg_ram_enabled() => {
+                self.prg_ram[usize::from(address - 0x6000) % self.prg_ram.len()]
+            }
+            0x8000..=0xFFFF => self.prg_rom[self.prg_rom_offset(address)],
+            _ => 0x00,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, data: u8) {
+        match address {
+            0x6000..=0x7FFF if self.prg_ram_enabled() => {
+                let len = self.prg_ram.len();
+                self.prg_ram[usize::from(address - 0x6000) % len] = data;
+            }
+            0x8000..=0xFFFF => self.write_register(self.register(address), data),
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        self.chr.read(self.chr_offset(address))
+    }
+
+    fn ppu_write(&mut self, address: u16, data: u8) {
+        self.chr.write(self.chr_offset(address), data);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.banking_mode >> 2 & 0b11 {
+            0 => Mirroring::Vertical,
+            1 => Mirroring::Horizontal,
+            2 => Mirroring::SingleScreenLower,
+            _ => Mirroring::SingleScreenUpper,
+        }
+    }
+
+    fn clock(&mut self) {
+        self.irq.clock();
+        self.audio.clock();
+    }
+
+    fn irq(&self) -> bool {
+        self.irq.pending()
+    }
+
+    fn audio(&self) -> f32 {
+        self.audio.output()
+    }
+}
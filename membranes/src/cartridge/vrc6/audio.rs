@@ -0,0 +1,165 @@
+//! VRC6 expansion audio: two pulse channels with 8 duty cycles and a sawtooth.
+
+use crate::audio::PULSE_LEVEL;
+
+/// A VRC6 pulse at full volume is about as loud as an APU pulse at full volume.
+const STEP_LEVEL: f32 = PULSE_LEVEL / 15.0;
+
+#[derive(Default)]
+pub struct Audio {
+    pulses: [Pulse; 2],
+    sawtooth: Sawtooth,
+    halt: bool,
+    /// Right shift applied to every channel's period, from $9003.
+    period_shift: u8,
+}
+
+impl Audio {
+    pub fn write(&mut self, address: u16, data: u8) {
+        match address {
+            0x9003 => {
+                self.halt = data & 0b001 != 0;
+                self.period_shift = if data & 0b100 != 0 {
+                    8
+                } else if data & 0b010 != 0 {
+                    4
+                } else {
+                    0
+                };
+            }
+            0x9000..=0x9002 => self.pulses[0].write(address & 0b11, data),
+            0xA000..=0xA002 => self.pulses[1].write(address & 0b11, data),
+            0xB000..=0xB002 => self.sawtooth.write(address & 0b11, data),
+            _ => {}
+        }
+    }
+
+    pub fn clock(&mut self) {
+        if self.halt {
+            return;
+        }
+        for pulse in &mut self.pulses {
+            if pulse.timer.clock(self.period_shift) {
+                pulse.step = pulse.step.wrapping_sub(1) & 0x0F;
+            }
+        }
+        let sawtooth = &mut self.sawtooth;
+        if sawtooth.timer.clock(self.period_shift) {
+            sawtooth.step += 1;
+            if sawtooth.step == 14 {
+                sawtooth.step = 0;
+                sawtooth.accumulator = 0;
+            } else if sawtooth.step & 0b1 == 0 {
+                sawtooth.accumulator = sawtooth.accumulator.wrapping_add(sawtooth.rate);
+            }
+        }
+    }
+
+    pub fn output(&self) -> f32 {
+        let level = self.pulses[0].output() + self.pulses[1].output() + self.sawtooth.output();
+        f32::from(level) * STEP_LEVEL
+    }
+}
+
+/// 12-bit period divider, stopped while its channel is disabled.
+#[derive(Default)]
+struct Timer {
+    enabled: bool,
+    period: u16,
+    counter: u16,
+}
+
+impl Timer {
+    fn write_low(&mut self, data: u8) {
+        self.period = self.period & 0x0F00 | u16::from(data);
+    }
+
+    fn write_high(&mut self, data: u8) {
+        self.period = self.period & 0x00FF | u16::from(data & 0x0F) << 8;
+        self.enabled = data & 0x80 != 0;
+    }
+
+    /// Returns whether the channel steps.
+    fn clock(&mut self, shift: u8) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        if self.counter == 0 {
+            self.counter = self.period >> shift;
+            true
+        } else {
+            self.counter -= 1;
+            false
+        }
+    }
+}
+
+#[derive(Default)]
+struct Pulse {
+    timer: Timer,
+    /// Ignores the duty cycle and outputs the volume constantly.
+    digitized: bool,
+    duty: u8,
+    volume: u8,
+    step: u8,
+}
+
+impl Pulse {
+    fn write(&mut self, register: u16, data: u8) {
+        match register {
+            0 => {
+                self.digitized = data & 0x80 != 0;
+                self.duty = data >> 4 & 0b111;
+                self.volume = data & 0x0F;
+            }
+            1 => self.timer.write_low(data),
+            _ => {
+                self.timer.write_high(data);
+                if !self.timer.enabled {
+                    self.step = 15;
+                }
+            }
+        }
+    }
+
+    fn output(&self) -> u8 {
+        if self.timer.enabled && (self.digitized || self.step <= self.duty) {
+            self.volume
+        } else {
+            0
+        }
+    }
+}
+
+#[derive(Default)]
+struct Sawtooth {
+    timer: Timer,
+    rate: u8,
+    step: u8,
+    accumulator: u8,
+}
+
+impl Sawtooth {
+    fn write(&mut self, register: u16, data: u8) {
+        match register {
+            0 => self.rate = data & 0x3F,
+            1 => self.timer.write_low(data),
+            _ => {
+                self.timer.write_high(data);
+                if !self.timer.enabled {
+                    self.step = 0;
+                    self.accumulator = 0;
+                }
+            }
+        }
+    }
+
+    /// The top 5 bits of the accumulator.
+    fn output(&self) -> u8 {
+        if self.timer.enabled {
+            self.accumulator >> 3
+        } else {
+            0
+        }
+    }
+}
@@ -0,0 +1,168 @@
+//! Konami VRC7 (mapper 85). Its FM audio needs the `opll` feature, and is silent without it.
+//!
+//! Submapper 1 selects the VRC7b, which decodes its second registers on A3, and
+//! submapper 2 the VRC7a, which uses A4. Otherwise both are decoded.
+
+#[cfg(feature = "opll")]
+mod opll;
+
+use super::{last_bank, vrc_irq::VrcIrq, Cartridge, Chr, Mirroring};
+use crate::rom::Header;
+#[cfg(feature = "opll")]
+use opll::Opll;
+
+const PRG_BANK_LEN: usize = 0x2000;
+const CHR_BANK_LEN: usize = 0x0400;
+
+pub struct Vrc7 {
+    /// CPU address lines selecting the second register of each pair.
+    line: u16,
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    chr: Chr,
+    prg_banks: [u8; 3],
+    chr_banks: [u8; 8],
+    /// $E000
+    control: u8,
+    irq: VrcIrq,
+    #[cfg(feature = "opll")]
+    opll: Opll,
+}
+
+impl Vrc7 {
+    pub fn new(header: &Header, prg_rom: &[u8], chr_rom: &[u8]) -> Self {
+        Self {
+            line: match header.submapper {
+                1 => 0x08,
+                2 => 0x10,
+                _ => 0x18,
+            },
+            prg_rom: prg_rom.to_vec(),
+            prg_ram: vec![0x00; header.prg_ram_len + header.prg_nvram_len],
+            chr: Chr::new(chr_rom, header.chr_ram_len + header.chr_nvram_len),
+            prg_banks: [0; 3],
+            chr_banks: [0; 8],
+            control: 0,
+            irq: VrcIrq::default(),
+            #[cfg(feature = "opll")]
+            opll: Opll::default(),
+        }
+    }
+
+    fn write_register(&mut self, address: u16, data: u8) {
+        // The audio ports decode A4 and A5 on both variants.
+        match address & 0xF030 {
+            0x9010 => {
+                #[cfg(feature = "opll")]
+                self.opll.select(data);
+                return;
+            }
+            0x9030 => {
+                #[cfg(feature = "opll")]
+                self.opll.write(data);
+                return;
+            }
+            _ => {}
+        }
+
+        let second = address & self.line != 0;
+        match (address & 0xF000, second) {
+            (0x8000, false) => self.prg_banks[0] = data & 0x3F,
+            (0x8000, true) => self.prg_banks[1] = data & 0x3F,
+            (0x9000, false) => self.prg_banks[2] = data & 0x3F,
+            (0xA000..=0xD000, _) => {
+                let bank = usize::from(address - 0xA000) / 0x1000 * 2 + usize::from(second);
+                self.chr_banks[bank] = data;
+            }
+            (0xE000, false) => {
+                self.control = data;
+                #[cfg(feature = "opll")]
+                if data & 0x40 != 0 {
+                    self.opll = Opll::default();
+                }
+            }
+            (0xE000, true) => self.irq.write_latch(data),
+            (0xF000, false) => self.irq.write_control(data),
+            (0xF000, true) => self.irq.acknowledge(),
+            _ => {}
+        }
+    }
+
+    fn prg_ram_enabled(&self) -> bool {
+        !self.prg_ram.is_empty() && self.control & 0x80 != 0
+    }
+
+    fn prg_rom_offset(&self, address: u16) -> usize {
+        let bank = match address {
+            0x8000..=0xDFFF => usize::from(self.prg_banks[usize::from(address - 0x8000) / 0x2000]),
+            _ => last_bank(self.prg_rom.len(), PRG_BANK_LEN),
+        };
+        (bank * PRG_BANK_LEN + usize::from(address & 0x1FFF)) % self.prg_rom.len()
+    }
+
+    fn chr_offset(&self, address: u16) -> usize {
+        let bank = self.chr_banks[usize::from(address & 0x1FFF) / CHR_BANK_LEN];
+        usize::from(bank) * CHR_BANK_LEN + usize::from(address & 0x03FF)
+    }
+}
+
+impl Cartridge for Vrc7 {
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        match address {
+            0x6000..=0x7FFF if self.prg_ram_enabled() => {
+                self.prg_ram[usize::from(address - 0x6000) % self.prg_ram.len()]
+            }
+            0x8000..=0xFFFF => self.prg_rom[self.prg_rom_offset(address)],
+            _ => 0x00,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, data: u8) {
+        match address {
+            0x6000..=0x7FFF if self.prg_ram_enabled() => {
+                let len = self.prg_ram.len();
+                self.prg_ram[usize::from(address - 0x6000) % len] = data;
+            }
+            0x8000..=0xFFFF => self.write_register(address, data),
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        self.chr.read(self.chr_offset(address))
+    }
+
+    fn ppu_write(&mut self, address: u16, data: u8) {
+        self.chr.write(self.chr_offset(address), data);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.control & 0b11 {
+            0 => Mirroring::Vertical,
+            1 => Mirroring::Horizontal,
+            2 => Mirroring::SingleScreenLower,
+            _ => Mirroring::SingleScreenUpper,
+        }
+    }
+
+    fn clock(&mut self) {
+        self.irq.clock();
+        #[cfg(feature = "opll")]
+        if self.control & 0x40 == 0 {
+            self.opll.clock();
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.irq.pending()
+    }
+
+    #[cfg(feature = "opll")]
+    fn audio(&self) -> f32 {
+        if self.control & 0x40 == 0 {
+            self.opll.output()
+        } else {
+            0.0
+        }
+    }
+}
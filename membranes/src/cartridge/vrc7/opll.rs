@@ -0,0 +1,440 @@
+//! The VRC7's 6-channel FM synthesizer, a cut-down YM2413 (OPLL) without rhythm mode.
+//!
+//! Each channel is a modulator operator feeding a carrier. Operators work in the
+//! log domain like the real chip: a log-sine lookup plus attenuation, converted
+//! back through an exponent table.
+
+use crate::audio::PULSE_LEVEL;
+use std::f64::consts::PI;
+
+/// The chip makes one sample every 72 cycles of its 3.58 MHz clock.
+const SAMPLE_CYCLES: u8 = 36;
+const CHANNELS: usize = 6;
+/// Peak operator output.
+const MAX_OUTPUT: i32 = 4095;
+/// A channel at full volume swings as far as an APU pulse at full volume.
+const CHANNEL_LEVEL: f32 = PULSE_LEVEL / 2.0;
+/// Envelope attenuation in 0.375 dB steps at which an operator is cut off.
+const SILENT: u8 = 127;
+
+/// Instruments 1-15, as dumped from the chip.
+const PATCHES: [[u8; 8]; 15] = [
+    [0x03, 0x21, 0x05, 0x06, 0xE8, 0x81, 0x42, 0x27],
+    [0x13, 0x41, 0x14, 0x0D, 0xD8, 0xF6, 0x23, 0x12],
+    [0x11, 0x11, 0x08, 0x08, 0xFA, 0xB2, 0x20, 0x12],
+    [0x31, 0x61, 0x0C, 0x07, 0xA8, 0x64, 0x61, 0x27],
+    [0x32, 0x21, 0x1E, 0x06, 0xE1, 0x76, 0x01, 0x28],
+    [0x02, 0x01, 0x06, 0x00, 0xA3, 0xE2, 0xF4, 0xF4],
+    [0x21, 0x61, 0x1D, 0x07, 0x82, 0x81, 0x11, 0x07],
+    [0x23, 0x21, 0x22, 0x17, 0xA2, 0x72, 0x01, 0x17],
+    [0x35, 0x11, 0x25, 0x00, 0x40, 0x73, 0x72, 0x01],
+    [0xB5, 0x01, 0x0F, 0x0F, 0xA8, 0xA5, 0x51, 0x02],
+    [0x17, 0xC1, 0x24, 0x07, 0xF8, 0xF8, 0x22, 0x12],
+    [0x71, 0x23, 0x11, 0x06, 0x65, 0x74, 0x18, 0x16],
+    [0x01, 0x02, 0xD3, 0x05, 0xC9, 0x95, 0x03, 0x02],
+    [0x61, 0x63, 0x0C, 0x00, 0x94, 0xC0, 0x33, 0xF6],
+    [0x21, 0x72, 0x0D, 0x00, 0xC1, 0xD5, 0x56, 0x06],
+];
+
+/// Frequency multipliers, doubled.
+const MULTIPLIERS: [u32; 16] = [1, 2, 4, 6, 8, 10, 12, 14, 16, 18, 20, 20, 24, 24, 30, 30];
+/// Key scale attenuation for the top 4 F-number bits at block 7, in 0.75 dB steps.
+const KEY_SCALES: [u8; 16] = [
+    0, 24, 32, 37, 40, 43, 45, 47, 48, 50, 51, 52, 53, 54, 55, 56,
+];
+/// Envelope increments for the two low rate bits over 8 timer steps.
+const ENVELOPE_STEPS: [[u8; 8]; 4] = [
+    [0, 1, 0, 1, 0, 1, 0, 1],
+    [0, 1, 0, 1, 1, 1, 0, 1],
+    [0, 1, 1, 1, 0, 1, 1, 1],
+    [0, 1, 1, 1, 1, 1, 1, 1],
+];
+/// Vibrato F-number offsets, scaled by the top F-number bits.
+const VIBRATO: [i32; 8] = [0, 1, 2, 1, 0, -1, -2, -1];
+/// Samples per vibrato step, for about 6.1 Hz.
+const VIBRATO_PERIOD: u32 = 1024;
+/// Samples per tremolo step, for about 3.7 Hz over 210 steps.
+const TREMOLO_PERIOD: u32 = 64;
+const TREMOLO_STEPS: u8 = 210;
+
+pub struct Opll {
+    address: u8,
+    custom: [u8; 8],
+    channels: [Channel; CHANNELS],
+    tables: Tables,
+    divider: u8,
+    samples: u32,
+    vibrato: usize,
+    tremolo: u8,
+    output: i32,
+}
+
+impl Default for Opll {
+    fn default() -> Self {
+        Self {
+            address: 0,
+            custom: [0; 8],
+            channels: Default::default(),
+            tables: Tables::new(),
+            divider: 0,
+            samples: 0,
+            vibrato: 0,
+            tremolo: 0,
+            output: 0,
+        }
+    }
+}
+
+impl Opll {
+    pub fn select(&mut self, data: u8) {
+        self.address = data;
+    }
+
+    pub fn write(&mut self, data: u8) {
+        let channel = usize::from(self.address & 0x0F);
+        match self.address {
+            0x00..=0x07 => self.custom[usize::from(self.address)] = data,
+            0x10..=0x15 => {
+                self.channels[channel].f_number =
+                    self.channels[channel].f_number & 0x100 | u16::from(data);
+            }
+            0x20..=0x25 => {
+                let channel = &mut self.channels[channel];
+                channel.f_number = channel.f_number & 0xFF | u16::from(data & 0b1) << 8;
+                channel.block = data >> 1 & 0b111;
+                channel.sustain = data & 0x20 != 0;
+                channel.set_key(data & 0x10 != 0);
+            }
+            0x30..=0x35 => {
+                self.channels[channel].instrument = data >> 4;
+                self.channels[channel].volume = data & 0x0F;
+            }
+            _ => {}
+        }
+    }
+
+    pub fn clock(&mut self) {
+        self.divider += 1;
+        if self.divider == SAMPLE_CYCLES {
+            self.divider = 0;
+            self.sample();
+        }
+    }
+
+    pub fn output(&self) -> f32 {
+        self.output as f32 / MAX_OUTPUT as f32 * CHANNEL_LEVEL
+    }
+
+    fn sample(&mut self) {
+        self.samples = self.samples.wrapping_add(1);
+        if self.samples & (VIBRATO_PERIOD - 1) == 0 {
+            self.vibrato = (self.vibrato + 1) % VIBRATO.len();
+        }
+        if self.samples & (TREMOLO_PERIOD - 1) == 0 {
+            self.tremolo = (self.tremolo + 1) % TREMOLO_STEPS;
+        }
+        // Triangle between 0 and 13, about 4.8 dB.
+        let tremolo = self.tremolo.min(TREMOLO_STEPS - 1 - self.tremolo) >> 3;
+
+        let mut output = 0;
+        for channel in &mut self.channels {
+            let patch = match channel.instrument {
+                0 => self.custom,
+                instrument => PATCHES[usize::from(instrument) - 1],
+            };
+            let operators = [Operator::modulator(&patch), Operator::carrier(&patch)];
+            let rate_keys = operators
+                .each_ref()
+                .map(|operator| channel.rate_key(operator.key_scale_rate));
+            let attenuations = operators.each_ref().map(|operator| {
+                operator.key_scale_attenuation(channel.f_number, channel.block)
+                    + u32::from(operator.tremolo) * u32::from(tremolo)
+            });
+
+            for ((slot, operator), rate_key) in
+                channel.slots.iter_mut().zip(&operators).zip(rate_keys)
+            {
+                slot.clock_envelope(operator, channel.sustain, rate_key, self.samples);
+
+                let mut f_number = i32::from(channel.f_number);
+                if operator.vibrato {
+                    f_number += ((f_number >> 6) * VIBRATO[self.vibrato]) >> 1;
+                }
+                let step = (f_number as u32) << channel.block;
+                slot.phase = slot
+                    .phase
+                    .wrapping_add((step * MULTIPLIERS[operator.multiplier]) >> 2);
+            }
+
+            let [modulator, carrier] = &mut channel.slots;
+            let [modulator_op, carrier_op] = &operators;
+
+            let feedback = match modulator_op.feedback {
+                0 => 0,
+                feedback => (modulator.outputs[0] + modulator.outputs[1]) >> (8 - feedback),
+            };
+            let attenuation = u32::from(modulator.level)
+                + u32::from(modulator_op.total_level) * 2
+                + attenuations[0];
+            let modulation = match modulator.level {
+                SILENT => 0,
+                _ => self.tables.output(
+                    modulator.phase_index() + feedback,
+                    attenuation,
+                    modulator_op.rectified,
+                ),
+            };
+            modulator.outputs = [modulator.outputs[1], modulation];
+
+            let attenuation =
+                u32::from(carrier.level) + u32::from(channel.volume) * 8 + attenuations[1];
+            if carrier.level != SILENT {
+                output += self.tables.output(
+                    carrier.phase_index() + modulation,
+                    attenuation,
+                    carrier_op.rectified,
+                );
+            }
+        }
+        self.output = output;
+    }
+}
+
+struct Tables {
+    /// Log-sine of a quarter wave, in 1/256 octaves of attenuation.
+    log_sin: [u16; 256],
+    /// 2^(-i/256) scaled to `MAX_OUTPUT`.
+    exp: [u16; 256],
+}
+
+impl Tables {
+    fn new() -> Self {
+        Self {
+            log_sin: std::array::from_fn(|i| {
+                let sin = ((i as f64 + 0.5) * PI / 512.0).sin();
+                (-sin.log2() * 256.0).round() as u16
+            }),
+            exp: std::array::from_fn(|i| {
+                (f64::from(MAX_OUTPUT) * (-(i as f64) / 256.0).exp2()).round() as u16
+            }),
+        }
+    }
+
+    /// Sine output at a 10-bit phase, attenuated in 0.375 dB steps.
+    fn output(&self, phase: i32, attenuation: u32, rectified: bool) -> i32 {
+        let phase = phase as u32 & 0x3FF;
+        let negative = phase & 0x200 != 0;
+        if negative && rectified {
+            return 0;
+        }
+        let quarter = if phase & 0x100 == 0 {
+            phase & 0xFF
+        } else {
+            0xFF - (phase & 0xFF)
+        };
+        // 0.375 dB is 16/256 of an octave.
+        let log = u32::from(self.log_sin[quarter as usize]) + (attenuation << 4);
+        let value = match log >> 8 {
+            shift @ 0..=11 => i32::from(self.exp[(log & 0xFF) as usize] >> shift),
+            _ => 0,
+        };
+        if negative {
+            -value
+        } else {
+            value
+        }
+    }
+}
+
+#[derive(Default)]
+struct Channel {
+    f_number: u16,
+    block: u8,
+    key: bool,
+    /// Slows the release after key off.
+    sustain: bool,
+    instrument: u8,
+    volume: u8,
+    slots: [Slot; 2],
+}
+
+impl Channel {
+    fn set_key(&mut self, key: bool) {
+        if key && !self.key {
+            for slot in &mut self.slots {
+                slot.phase = 0;
+                slot.state = Envelope::Attack;
+            }
+        } else if !key && self.key {
+            for slot in &mut self.slots {
+                slot.state = Envelope::Release;
+            }
+        }
+        self.key = key;
+    }
+
+    /// Envelope rate offset from the block and top F-number bit.
+    fn rate_key(&self, key_scale_rate: bool) -> u8 {
+        let key = self.block << 1 | (self.f_number >> 8) as u8;
+        if key_scale_rate {
+            key
+        } else {
+            key >> 2
+        }
+    }
+}
+
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+enum Envelope {
+    Attack,
+    Decay,
+    Sustain,
+    #[default]
+    Release,
+}
+
+struct Slot {
+    /// 18-bit phase, one wave per overflow.
+    phase: u32,
+    state: Envelope,
+    /// Envelope attenuation in 0.375 dB steps.
+    level: u8,
+    /// Last two outputs, for feedback.
+    outputs: [i32; 2],
+}
+
+impl Default for Slot {
+    fn default() -> Self {
+        Self {
+            phase: 0,
+            state: Envelope::Release,
+            level: SILENT,
+            outputs: [0; 2],
+        }
+    }
+}
+
+impl Slot {
+    fn phase_index(&self) -> i32 {
+        (self.phase >> 8 & 0x3FF) as i32
+    }
+
+    fn clock_envelope(&mut self, operator: &Operator, sustain: bool, rate_key: u8, timer: u32) {
+        let rate = match self.state {
+            Envelope::Attack => operator.attack,
+            Envelope::Decay => operator.decay,
+            Envelope::Sustain if operator.sustained => 0,
+            Envelope::Sustain => operator.release,
+            Envelope::Release if sustain => 5,
+            Envelope::Release if operator.sustained => operator.release,
+            Envelope::Release => 7,
+        };
+        let rate = match rate {
+            0 => 0,
+            rate => (rate * 4 + rate_key).min(63),
+        };
+
+        if self.state == Envelope::Attack {
+            if rate >= 60 {
+                self.level = 0;
+            } else {
+                let step = i32::from(envelope_increment(rate, timer));
+                let level = i32::from(self.level);
+                self.level = (level + ((!level * step) >> 4)).max(0) as u8;
+            }
+            if self.level == 0 {
+                self.state = Envelope::Decay;
+            }
+            return;
+        }
+
+        self.level = (self.level + envelope_increment(rate, timer)).min(SILENT);
+        if self.state == Envelope::Decay && self.level >= operator.sustain_level {
+            self.state = Envelope::Sustain;
+        }
+    }
+}
+
+/// Steps by the fraction of an effective rate's period that falls on this timer value.
+fn envelope_increment(rate: u8, timer: u32) -> u8 {
+    if rate == 0 {
+        return 0;
+    }
+    let (high, low) = (rate >> 2, usize::from(rate & 0b11));
+    if high < 12 {
+        let shift = 12 - high;
+        if timer & ((1 << shift) - 1) == 0 {
+            ENVELOPE_STEPS[low][(timer >> shift) as usize & 7]
+        } else {
+            0
+        }
+    } else {
+        (1 + ENVELOPE_STEPS[low][timer as usize & 7]) << (high - 12)
+    }
+}
+
+/// One operator's half of a patch.
+struct Operator {
+    tremolo: bool,
+    vibrato: bool,
+    /// Holds the sustain level while the key is on, rather than decaying on.
+    sustained: bool,
+    key_scale_rate: bool,
+    multiplier: usize,
+    key_scale_level: u8,
+    /// Only the modulator has one, in 0.75 dB steps.
+    total_level: u8,
+    /// Half sine, silent for the negative half.
+    rectified: bool,
+    feedback: u8,
+    attack: u8,
+    decay: u8,
+    sustain_level: u8,
+    release: u8,
+}
+
+impl Operator {
+    fn modulator(patch: &[u8; 8]) -> Self {
+        Self {
+            total_level: patch[2] & 0x3F,
+            rectified: patch[3] & 0x08 != 0,
+            feedback: patch[3] & 0b111,
+            ..Self::new(patch, 0)
+        }
+    }
+
+    fn carrier(patch: &[u8; 8]) -> Self {
+        Self {
+            key_scale_level: patch[3] >> 6,
+            rectified: patch[3] & 0x10 != 0,
+            ..Self::new(patch, 1)
+        }
+    }
+
+    fn new(patch: &[u8; 8], slot: usize) -> Self {
+        Self {
+            tremolo: patch[slot] & 0x80 != 0,
+            vibrato: patch[slot] & 0x40 != 0,
+            sustained: patch[slot] & 0x20 != 0,
+            key_scale_rate: patch[slot] & 0x10 != 0,
+            multiplier: usize::from(patch[slot] & 0x0F),
+            key_scale_level: patch[2] >> 6,
+            total_level: 0,
+            rectified: false,
+            feedback: 0,
+            attack: patch[4 + slot] >> 4,
+            decay: patch[4 + slot] & 0x0F,
+            sustain_level: (patch[6 + slot] >> 4) << 3,
+            release: patch[6 + slot] & 0x0F,
+        }
+    }
+
+    /// In 0.375 dB steps. Levels 1-3 give 1.5, 3 and 6 dB per octave.
+    fn key_scale_attenuation(&self, f_number: u16, block: u8) -> u32 {
+        if self.key_scale_level == 0 {
+            return 0;
+        }
+        let base = i32::from(KEY_SCALES[usize::from(f_number >> 5)]) - 8 * i32::from(7 - block);
+        (base.max(0) as u32 * 2) >> (3 - self.key_scale_level)
+    }
+}
@@ -0,0 +1,74 @@
+//! IRQ counter shared by Konami's VRC4, VRC6 and VRC7.
+
+/// PPU dots per scanline, counted down by 3 each CPU cycle in scanline mode.
+const PRESCALER_PERIOD: i16 = 341;
+
+#[derive(Default)]
+pub struct VrcIrq {
+    latch: u8,
+    counter: u8,
+    prescaler: i16,
+    enabled: bool,
+    enabled_after_ack: bool,
+    /// Counts CPU cycles instead of scanlines.
+    cycle_mode: bool,
+    pending: bool,
+}
+
+impl VrcIrq {
+    pub fn write_latch(&mut self, data: u8) {
+        self.latch = data;
+    }
+
+    pub fn write_latch_low(&mut self, data: u8) {
+        self.latch = self.latch & 0xF0 | data & 0x0F;
+    }
+
+    pub fn write_latch_high(&mut self, data: u8) {
+        self.latch = self.latch & 0x0F | (data & 0x0F) << 4;
+    }
+
+    pub fn write_control(&mut self, data: u8) {
+        self.enabled_after_ack = data & 0b001 != 0;
+        self.enabled = data & 0b010 != 0;
+        self.cycle_mode = data & 0b100 != 0;
+        self.pending = false;
+        if self.enabled {
+            self.counter = self.latch;
+            self.prescaler = PRESCALER_PERIOD;
+        }
+    }
+
+    pub fn acknowledge(&mut self) {
+        self.pending = false;
+        self.enabled = self.enabled_after_ack;
+    }
+
+    pub fn pending(&self) -> bool {
+        self.pending
+    }
+
+    pub fn clock(&mut self) {
+        if !self.enabled {
+            return;
+        }
+        if self.cycle_mode {
+            self.clock_counter();
+        } else {
+            self.prescaler -= 3;
+            if self.prescaler <= 0 {
+                self.prescaler += PRESCALER_PERIOD;
+                self.clock_counter();
+            }
+        }
+    }
+
+    fn clock_counter(&mut self) {
+        if self.counter == 0xFF {
+            self.counter = self.latch;
+            self.pending = true;
+        } else {
+            self.counter += 1;
+        }
+    }
+}
@@ -1,16 +1,36 @@
+use apu::Dmc;
+use audio::Mixer;
+use cartridge::{Cartridge, Fds, Mirroring, Nrom};
 use membranes_cpu::Cpu;
-use membranes_gamepad::Gamepad;
+use membranes_gamepad::{ButtonFlags, Gamepad};
+use membranes_ppu::{
+    MemoryMap, NtscFilter, NtscSettings, Palette, Ppu, Region, SpriteView, CIRAM_LEN,
+};
+use scheduler::Scheduler;
 use wasm_bindgen::prelude::*;
 
 pub use membranes_cpu as cpu;
 pub use membranes_gamepad as gamepad;
+pub use membranes_ppu as ppu;
 pub use membranes_rom as rom;
 
+pub mod apu;
+pub mod audio;
+pub mod cartridge;
+pub mod nsf;
+pub mod scheduler;
+
 #[wasm_bindgen]
 pub struct Nes {
     pub cpu: Cpu,
-    #[wasm_bindgen(getter_with_clone)]
+    #[wasm_bindgen(skip)]
     pub bus: Bus,
+    /// Colors for `frame_rgba` and `frame_rgb24`.
+    #[wasm_bindgen(skip)]
+    pub palette: Palette,
+    /// Settings for `frame_ntsc_rgba`.
+    #[wasm_bindgen(skip)]
+    pub ntsc: NtscFilter,
 }
 
 impl Default for Nes {
@@ -19,10 +39,20 @@ impl Default for Nes {
             cpu: Cpu::new(),
             bus: Bus {
                 ram: vec![0x00; 0x2000],
-                prg_rom: vec![0x00; 0x7FFF],
+                cartridge: Box::<Nrom>::default(),
+                ppu: Default::default(),
+                ciram: vec![0x00; CIRAM_LEN],
+                oam_dma: None,
+                dmc: Default::default(),
+                lead_cycles: 0,
                 gamepad_1: Default::default(),
                 gamepad_2: Default::default(),
+                mixer: Default::default(),
+                scheduler: Default::default(),
+                cycles: 0,
             },
+            palette: Default::default(),
+            ntsc: Default::default(),
         }
     }
 }
@@ -34,33 +64,385 @@ impl Nes {
         Default::default()
     }
 
-    /// Returns Err if the program is too long to fit into prg_rom.
+    /// Returns Err if the ROM is malformed or its mapper is unsupported.
     pub fn load(&mut self, rom: &[u8]) -> Result<(), String> {
         let ines = rom::INesV1::parse(rom).map_err(|e| format!("{:?}", e))?;
-        let prg = ines.prg_rom();
-        let rom = self.bus.prg_rom.get_mut(..prg.len()).unwrap();
-        rom.copy_from_slice(prg);
+        self.bus.cartridge = cartridge::from_ines(&ines)?;
+        self.set_region(match ines.header().timing {
+            rom::Timing::Pal => Region::Pal,
+            rom::Timing::Dendy => Region::Dendy,
+            rom::Timing::Ntsc | rom::Timing::MultiRegion => Region::Ntsc,
+        });
+        Ok(())
+    }
+
+    /// Loads a Famicom Disk System image. The FDS BIOS (disksys.rom) must be supplied.
+    pub fn load_fds(&mut self, disk: &[u8], bios: &[u8]) -> Result<(), String> {
+        let fds = Fds::new(disk, bios).map_err(|e| e.to_string())?;
+        self.bus.cartridge = Box::new(fds);
+        self.set_region(Region::Ntsc);
         Ok(())
     }
 
+    /// Inserts an FDS disk side. Does nothing if no disk image is loaded.
+    /// Returns Err if the disk has no such side.
+    pub fn insert_disk(&mut self, side: usize) -> Result<(), String> {
+        match self.fds_mut() {
+            Some(fds) => fds.insert(side).map_err(|e| e.to_string()),
+            None => Ok(()),
+        }
+    }
+
+    pub fn eject_disk(&mut self) {
+        if let Some(fds) = self.fds_mut() {
+            fds.eject();
+        }
+    }
+
+    pub fn disk_side_count(&mut self) -> usize {
+        self.fds_mut().map_or(0, |fds| fds.side_count())
+    }
+
+    /// Nametable mirroring currently selected by the cartridge.
+    pub fn mirroring(&self) -> Mirroring {
+        self.bus.cartridge.mirroring()
+    }
+
+    /// Battery-backed cartridge RAM to save, empty if the cartridge has none.
+    pub fn battery_ram(&self) -> Vec<u8> {
+        self.bus.cartridge.battery_ram()
+    }
+
+    /// Restores RAM saved from `battery_ram` after loading the same ROM.
+    pub fn load_battery_ram(&mut self, data: &[u8]) {
+        self.bus.cartridge.load_battery_ram(data);
+    }
+
+    /// `set_region` for JavaScript.
+    #[wasm_bindgen(js_name = set_region)]
+    pub fn set_web_region(&mut self, region: WebRegion) {
+        self.set_region(region.into());
+    }
+
+    /// Frames per second, counting NTSC's odd frames as half a dot short.
+    pub fn frame_rate(&self) -> f64 {
+        let region = self.region();
+        let dots_per_second = f64::from(audio::cpu_clock(region))
+            * f64::from(scheduler::dots_per_cycle(region))
+            / 5.0;
+        let skipped = if region.skips_odd_dot() { 0.5 } else { 0.0 };
+        dots_per_second / (f64::from(ppu::DOTS) * f64::from(region.scanlines()) - skipped)
+    }
+
+    pub fn reset(&mut self) {
+        self.cpu.reset(&mut self.bus);
+    }
+
+    /// Applies an IPS, UPS or BPS patch to a copy of the ROM before loading it.
+    pub fn load_patched(&mut self, rom: &[u8], patch: &[u8]) -> Result<(), String> {
+        let rom = rom::patch::apply(rom, patch).map_err(|e| format!("{:?}", e))?;
+        self.load(&rom)
+    }
+
     pub fn tick(&mut self) -> cpu::Effects {
-        self.cpu.tick(&mut self.bus)
+        if self.bus.ppu.take_nmi() {
+            self.cpu.nmi(&mut self.bus);
+            self.bus.clock(cpu::INTERRUPT_CYCLES);
+        } else if self.bus.irq() && self.cpu.irq(&mut self.bus) {
+            self.bus.clock(cpu::INTERRUPT_CYCLES);
+        }
+        let opcode = cpu::Bus::peek_u8(&mut self.bus, self.cpu.regs.pc);
+        self.bus.lead_cycles = cpu::op::Op::decode(opcode).map_or(0, |op| op.cycles - 1);
+        let start = self.bus.cycles;
+        let effects = self.cpu.tick(&mut self.bus);
+        self.bus.lead_cycles = 0;
+        let elapsed = (self.bus.cycles - start) as u8;
+        self.bus.clock(effects.op.cycles - elapsed);
+        self.bus.run_oam_dma();
+        self.bus.run_dmc_dma();
+        effects
+    }
+
+    /// Runs until the PPU finishes the frame in progress.
+    pub fn run_frame(&mut self) {
+        let frame = self.bus.ppu.frame;
+        while self.bus.ppu.frame == frame {
+            self.tick();
+        }
+    }
+
+    /// Holds down buttons, as `gamepad::ButtonFlags` bits, on controller 1 or 2.
+    pub fn press(&mut self, controller: u8, buttons: u8) {
+        self.gamepad(controller)
+            .press(ButtonFlags::from_bits_truncate(buttons));
+    }
+
+    pub fn release(&mut self, controller: u8, buttons: u8) {
+        self.gamepad(controller)
+            .release(ButtonFlags::from_bits_truncate(buttons));
     }
 
     pub fn ram(&mut self) -> *const u8 {
         self.bus.ram.as_ptr()
     }
+
+    /// The last picture drawn: `ppu::WIDTH` x `ppu::HEIGHT` pixels, row by row, each a
+    /// color (0-63) in bits 0-5 with PPUMASK's emphasis bits in bits 6-8.
+    pub fn framebuffer(&self) -> *const u16 {
+        self.bus.ppu.framebuffer.as_ptr()
+    }
+
+    /// Replaces the built-in palette with a .pal file of 64 or 512 colors.
+    pub fn set_palette(&mut self, pal: &[u8]) -> Result<(), String> {
+        self.palette = Palette::from_pal(pal)?;
+        Ok(())
+    }
+
+    pub fn reset_palette(&mut self) {
+        self.palette = Palette::ntsc();
+    }
+
+    /// The last picture drawn, 4 bytes per pixel.
+    pub fn frame_rgba(&self) -> Vec<u8> {
+        self.palette.to_rgba(&self.bus.ppu.framebuffer)
+    }
+
+    /// The last picture drawn, 3 bytes per pixel.
+    pub fn frame_rgb24(&self) -> Vec<u8> {
+        self.palette.to_rgb24(&self.bus.ppu.framebuffer)
+    }
+
+    /// The last picture drawn through the NTSC filter, `ppu::NTSC_WIDTH` x
+    /// `ppu::HEIGHT` at 4 bytes per pixel.
+    pub fn frame_ntsc_rgba(&mut self) -> Vec<u8> {
+        let ppu = &self.bus.ppu;
+        // The frame counter has already moved on to the next frame.
+        self.ntsc
+            .filter_rgba(&ppu.framebuffer, ppu.frame.wrapping_sub(1))
+    }
+
+    /// Tunes the NTSC filter. Each setting ranges over -1 to 1, with 0 the default.
+    pub fn set_ntsc(&mut self, sharpness: f32, saturation: f32, hue: f32, artifacts: f32) {
+        self.ntsc.settings = NtscSettings {
+            sharpness,
+            saturation,
+            hue,
+            artifacts,
+        };
+    }
+
+    /// Both pattern tables colored with palette 0-7, `ppu::debug::PATTERN_TABLES_WIDTH` x
+    /// `ppu::debug::PATTERN_TABLES_HEIGHT` at 4 bytes per pixel.
+    pub fn debug_pattern_tables(&mut self, palette: u8) -> Vec<u8> {
+        let mut bus = MemoryMap {
+            ciram: &mut self.bus.ciram,
+            memory: self.bus.cartridge.as_mut(),
+        };
+        self.bus
+            .ppu
+            .debug_pattern_tables(palette, &self.palette, &mut bus)
+    }
+
+    /// The four nametables with the screen outlined, `ppu::debug::NAMETABLES_WIDTH` x
+    /// `ppu::debug::NAMETABLES_HEIGHT` at 4 bytes per pixel.
+    pub fn debug_nametables(&mut self) -> Vec<u8> {
+        let mut bus = MemoryMap {
+            ciram: &mut self.bus.ciram,
+            memory: self.bus.cartridge.as_mut(),
+        };
+        self.bus.ppu.debug_nametables(&self.palette, &mut bus)
+    }
+
+    /// The 64 sprites, `ppu::debug::OAM_WIDTH` x `ppu::debug::OAM_HEIGHT` at 4 bytes
+    /// per pixel. Their positions and attributes are in [`Nes::oam`].
+    pub fn debug_oam(&mut self) -> Vec<u8> {
+        let mut bus = MemoryMap {
+            ciram: &mut self.bus.ciram,
+            memory: self.bus.cartridge.as_mut(),
+        };
+        self.bus.ppu.debug_oam(&self.palette, &mut bus)
+    }
+
+    /// Palette RAM, `ppu::debug::PALETTE_WIDTH` x `ppu::debug::PALETTE_HEIGHT` at
+    /// 4 bytes per pixel.
+    pub fn debug_palette(&self) -> Vec<u8> {
+        self.bus.ppu.debug_palette(&self.palette)
+    }
+
+    /// OAM's 64 entries of Y, tile, attributes and X.
+    pub fn oam(&self) -> Vec<u8> {
+        self.bus.ppu.oam.to_vec()
+    }
+
+    /// Audio samples at [`audio::SAMPLE_RATE`] produced since the last call.
+    pub fn audio_samples(&mut self) -> Vec<f32> {
+        self.bus.mixer.take_samples()
+    }
+}
+
+impl Nes {
+    /// Switches the console's timing. Loading a ROM selects the region from its
+    /// header, so call this afterwards to override it.
+    pub fn set_region(&mut self, region: Region) {
+        self.bus.ppu.region = region;
+        self.bus.scheduler = Scheduler::new(scheduler::dots_per_cycle(region));
+        self.bus.mixer.set_cpu_clock(audio::cpu_clock(region));
+        self.bus.dmc.set_region(region);
+    }
+
+    pub fn region(&self) -> Region {
+        self.bus.ppu.region
+    }
+
+    fn gamepad(&mut self, controller: u8) -> &mut Gamepad {
+        match controller {
+            2 => &mut self.bus.gamepad_2,
+            _ => &mut self.bus.gamepad_1,
+        }
+    }
+
+    pub fn fds_mut(&mut self) -> Option<&mut Fds> {
+        let cartridge: &mut dyn std::any::Any = self.bus.cartridge.as_mut();
+        cartridge.downcast_mut()
+    }
+
+    /// Each OAM entry with its image.
+    pub fn debug_sprites(&mut self) -> Vec<SpriteView> {
+        let mut bus = MemoryMap {
+            ciram: &mut self.bus.ciram,
+            memory: self.bus.cartridge.as_mut(),
+        };
+        self.bus.ppu.debug_sprites(&self.palette, &mut bus)
+    }
+}
+
+/// [`Region`] as JavaScript sees it.
+#[wasm_bindgen(js_name = Region)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebRegion {
+    Ntsc,
+    Pal,
+    Dendy,
+}
+
+impl From<WebRegion> for Region {
+    fn from(region: WebRegion) -> Self {
+        match region {
+            WebRegion::Ntsc => Region::Ntsc,
+            WebRegion::Pal => Region::Pal,
+            WebRegion::Dendy => Region::Dendy,
+        }
+    }
 }
 
-#[wasm_bindgen]
-#[derive(Clone)]
 pub struct Bus {
-    #[wasm_bindgen(getter_with_clone)]
     pub ram: Vec<u8>,
-    #[wasm_bindgen(getter_with_clone)]
-    pub prg_rom: Vec<u8>,
+    pub cartridge: Box<dyn Cartridge>,
+    pub ppu: Ppu,
+    /// The console's 2 KiB of nametable VRAM, followed by four-screen boards' extra 2 KiB.
+    pub ciram: Vec<u8>,
+    /// Page written to $4014, copied to OAM once the writing instruction finishes.
+    pub oam_dma: Option<u8>,
+    /// The APU's sample channel, whose fetches halt the CPU.
+    pub dmc: Dmc,
+    /// Cycles of the running instruction before its last, which PPU register
+    /// accesses are taken to land on. They are clocked ahead of such an access
+    /// so the PPU sees it at the right dot.
+    pub lead_cycles: u8,
     pub gamepad_1: Gamepad,
     pub gamepad_2: Gamepad,
+    pub mixer: Mixer,
+    pub scheduler: Scheduler,
+    /// CPU cycles since power-on.
+    pub cycles: u64,
+}
+
+impl Bus {
+    /// Advances everything clocked by the CPU.
+    pub fn clock(&mut self, cycles: u8) {
+        for _ in 0..cycles {
+            let mut bus = MemoryMap {
+                ciram: &mut self.ciram,
+                memory: self.cartridge.as_mut(),
+            };
+            for _ in 0..self.scheduler.dots() {
+                self.ppu.clock(&mut bus);
+            }
+            self.cartridge.clock();
+            self.dmc.clock();
+            self.mixer.push(self.dmc.audio() + self.cartridge.audio());
+            self.cycles += 1;
+        }
+    }
+
+    /// Copies a pending OAM DMA page, halting the CPU for 513 cycles, or 514 when
+    /// an extra cycle is needed to line the reads up with even cycles.
+    /// A DMC fetch requested meanwhile takes over a read cycle, delaying the copy
+    /// by 2 more cycles. Returns the cycles taken.
+    pub fn run_oam_dma(&mut self) -> u16 {
+        let page = match self.oam_dma.take() {
+            Some(page) => page,
+            None => return 0,
+        };
+        let start = self.cycles;
+        self.clock(1);
+        if self.cycles & 1 != 0 {
+            self.clock(1);
+        }
+        for low in 0..=0xFF {
+            if self.dmc.dma_request().is_some() {
+                // Its read, then a cycle to realign.
+                self.fetch_dmc_sample();
+                self.clock(1);
+            }
+            let data = membranes_cpu::Bus::read_u8(self, u16::from_be_bytes([page, low]));
+            self.clock(1);
+            self.ppu.write_oam(data);
+            self.clock(1);
+        }
+        (self.cycles - start) as u16
+    }
+
+    /// Fetches a pending DMC sample byte, halting the CPU for 4 cycles, or 3 when
+    /// the read already lands on an even cycle. Returns the cycles taken.
+    pub fn run_dmc_dma(&mut self) -> u16 {
+        if self.dmc.dma_request().is_none() {
+            return 0;
+        }
+        let start = self.cycles;
+        // Halt, then a dummy cycle.
+        self.clock(2);
+        if self.cycles & 1 != 0 {
+            self.clock(1);
+        }
+        self.fetch_dmc_sample();
+        (self.cycles - start) as u16
+    }
+
+    /// Reads the pending DMC byte in one cycle.
+    fn fetch_dmc_sample(&mut self) {
+        if let Some(address) = self.dmc.dma_request() {
+            let data = membranes_cpu::Bus::read_u8(self, address);
+            self.dmc.load_sample(data);
+            self.clock(1);
+        }
+    }
+
+    /// Clocks the running instruction up to its last cycle.
+    fn catch_up(&mut self) {
+        let cycles = std::mem::take(&mut self.lead_cycles);
+        self.clock(cycles);
+    }
+
+    /// $4015: DMC IRQ in bit 7 and DMC activity in bit 4.
+    // todo: the other channels and the frame IRQ
+    fn apu_status(&self) -> u8 {
+        u8::from(self.dmc.irq()) << 7 | u8::from(self.dmc.is_active()) << 4
+    }
+
+    pub fn irq(&self) -> bool {
+        self.cartridge.irq() || self.dmc.irq()
+    }
 }
 
 impl membranes_cpu::Bus for Bus {
@@ -71,17 +453,29 @@ impl membranes_cpu::Bus for Bus {
                 self.ram[usize::from(address)]
             }
             0x2000..=0x3FFF => {
-                let _address = address & 0b00100000_00000111;
-                // todo: remap address
-                todo!("PPU")
+                self.catch_up();
+                let mut bus = MemoryMap {
+                    ciram: &mut self.ciram,
+                    memory: self.cartridge.as_mut(),
+                };
+                self.ppu.read_register(address, &mut bus)
             }
+            0x4015 => self.apu_status(),
             0x4016 => self.gamepad_1.read_u8(),
             0x4017 => self.gamepad_2.read_u8(),
-            0x8000..=0xFFFF => {
-                let address = (usize::from(address) - 0x8000) % 0x4000;
-                self.prg_rom[address]
-            }
-            _ => todo!(),
+            // Write-only or unused
+            0x4000..=0x401F => 0x00,
+            0x4020..=0xFFFF => self.cartridge.cpu_read(address),
+        }
+    }
+
+    fn peek_u8(&mut self, address: u16) -> u8 {
+        match address {
+            0x2000..=0x3FFF => self.ppu.peek_register(address),
+            0x4015 => self.apu_status(),
+            0x4000..=0x401F => 0x00,
+            0x4020..=0xFFFF => self.cartridge.cpu_peek(address),
+            _ => self.read_u8(address),
         }
     }
 
@@ -92,14 +486,26 @@ impl membranes_cpu::Bus for Bus {
                 self.ram[address] = data;
             }
             0x2000..=0x3FFF => {
-                let _address = address & 0b00100000_00000111;
-                // todo: remap address
-                todo!("PPU")
+                self.catch_up();
+                let address = address & 0b00100000_00000111;
+                self.cartridge.ppu_register_write(address, data);
+                let mut bus = MemoryMap {
+                    ciram: &mut self.ciram,
+                    memory: self.cartridge.as_mut(),
+                };
+                self.ppu.write_register(address, data, &mut bus);
+            }
+            0x4010..=0x4013 => self.dmc.write_register(address, data),
+            0x4014 => self.oam_dma = Some(data),
+            0x4015 => self.dmc.set_enabled(data & 0x10 != 0),
+            // Both controllers share the strobe.
+            0x4016 => {
+                self.gamepad_1.write_u8(data);
+                self.gamepad_2.write_u8(data);
             }
-            0x4016 => self.gamepad_1.write_u8(data),
-            0x4017 => self.gamepad_2.write_u8(data),
-            0x8000..=0xFFFF => panic!("Cannot write to ROM"),
-            _ => todo!(),
+            // todo: the other APU channels, and the frame counter at $4017
+            0x4000..=0x401F => {}
+            0x4020..=0xFFFF => self.cartridge.cpu_write(address, data),
         }
     }
 }
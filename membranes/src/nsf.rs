@@ -0,0 +1,239 @@
+//! NSF player driving the tune's INIT and PLAY routines.
+
+use crate::audio;
+use crate::rom::nsf::{Nsf, NsfRegion};
+use crate::WebRegion;
+use membranes_cpu::{Bus, Cpu};
+use membranes_ppu::Region;
+use wasm_bindgen::prelude::*;
+
+const BANK_LEN: usize = 0x1000;
+
+/// INIT and PLAY are called with this address - 1 pushed as their return address.
+/// Nothing is mapped there, so reaching it means the routine has returned.
+const RETURN_ADDRESS: u16 = 0x4100;
+/// Routines that don't return within this many cycles are abandoned.
+const MAX_ROUTINE_CYCLES: u64 = audio::NTSC_CPU_CLOCK as u64;
+
+#[wasm_bindgen]
+pub struct NsfPlayer {
+    pub cpu: Cpu,
+    #[wasm_bindgen(skip)]
+    pub bus: NsfBus,
+    init_address: u16,
+    play_address: u16,
+    bankswitch: Option<[u8; 8]>,
+    track: u8,
+    track_count: u8,
+    region: Region,
+    ntsc_speed: u16,
+    pal_speed: u16,
+    play_period: u64,
+    cycles: u64,
+}
+
+#[wasm_bindgen]
+impl NsfPlayer {
+    /// Loads an NSF or NSFe file and initializes its starting track.
+    #[wasm_bindgen(constructor)]
+    pub fn new(nsf: &[u8]) -> Result<NsfPlayer, String> {
+        let nsf = Nsf::parse(nsf).map_err(|e| format!("{:?}", e))?;
+        let mut player = Self {
+            cpu: Cpu::new(),
+            bus: NsfBus::new(&nsf),
+            init_address: nsf.init_address,
+            play_address: nsf.play_address,
+            bankswitch: nsf.bankswitch,
+            track: nsf.starting_song,
+            track_count: nsf.songs,
+            region: Region::Ntsc,
+            ntsc_speed: nsf.ntsc_speed,
+            pal_speed: nsf.pal_speed,
+            play_period: 0,
+            cycles: 0,
+        };
+        player.set_region(match nsf.region {
+            NsfRegion::Pal => Region::Pal,
+            NsfRegion::Ntsc | NsfRegion::Dual => Region::Ntsc,
+        });
+        Ok(player)
+    }
+
+    /// `set_region` for JavaScript.
+    #[wasm_bindgen(js_name = set_region)]
+    pub fn set_web_region(&mut self, region: WebRegion) {
+        self.set_region(region.into());
+    }
+
+    /// Zero-based index of the current track.
+    pub fn track(&self) -> u8 {
+        self.track
+    }
+
+    pub fn track_count(&self) -> u8 {
+        self.track_count
+    }
+
+    /// CPU cycles between PLAY calls.
+    pub fn play_period(&self) -> u64 {
+        self.play_period
+    }
+
+    /// CPU cycles elapsed since the track was selected.
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// Resets the machine and runs INIT for a zero-based track index.
+    pub fn select_track(&mut self, track: u8) {
+        let track = track.min(self.track_count.saturating_sub(1));
+        self.track = track;
+        self.cycles = 0;
+        self.bus.reset(self.bankswitch);
+
+        for address in (0x4000..=0x4013).chain([0x4015]) {
+            self.bus.write_u8(address, 0x00);
+        }
+        self.bus.write_u8(0x4015, 0x0F);
+        self.bus.write_u8(0x4017, 0x40);
+        self.bus.apu_writes.clear();
+
+        self.cpu = Cpu::new();
+        self.cpu.regs.a = track;
+        self.cpu.regs.x = (self.region == Region::Pal).into();
+        self.call(self.init_address);
+    }
+
+    /// Runs PLAY once and advances time by one play period.
+    pub fn play(&mut self) {
+        let start = self.cycles;
+        self.call(self.play_address);
+        self.cycles = self.cycles.max(start + self.play_period);
+    }
+
+    pub fn ram(&mut self) -> *const u8 {
+        self.bus.ram.as_ptr()
+    }
+}
+
+impl NsfPlayer {
+    /// Switches the console's timing and restarts the current track. The tune's
+    /// region selects it on loading. Dendy calls PLAY at PAL's rate, but tunes
+    /// are told they run on NTSC, whose APU timing it shares.
+    pub fn set_region(&mut self, region: Region) {
+        let speed = match region {
+            Region::Ntsc => self.ntsc_speed,
+            Region::Pal | Region::Dendy => self.pal_speed,
+        };
+        self.region = region;
+        self.play_period = u64::from(speed) * u64::from(audio::cpu_clock(region)) / 1_000_000;
+        self.select_track(self.track);
+    }
+
+    pub fn region(&self) -> Region {
+        self.region
+    }
+
+    /// APU register writes since the last call, timestamped in CPU cycles.
+    pub fn take_apu_writes(&mut self) -> Vec<ApuWrite> {
+        std::mem::take(&mut self.bus.apu_writes)
+    }
+
+    /// Calls a routine like JSR would and runs it until it returns.
+    fn call(&mut self, address: u16) {
+        let [hi, lo] = (RETURN_ADDRESS - 1).to_be_bytes();
+        let sp = self.cpu.regs.sp;
+        self.bus.write_u8(0x0100 + u16::from(sp), hi);
+        self.bus
+            .write_u8(0x0100 + u16::from(sp.wrapping_sub(1)), lo);
+        self.cpu.regs.sp = sp.wrapping_sub(2);
+        self.cpu.regs.pc = address;
+
+        let start = self.cycles;
+        while self.cpu.regs.pc != RETURN_ADDRESS && self.cycles - start < MAX_ROUTINE_CYCLES {
+            self.bus.cycles = self.cycles;
+            let effects = self.cpu.tick(&mut self.bus);
+            self.cycles += u64::from(effects.op.cycles);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ApuWrite {
+    pub cycle: u64,
+    pub address: u16,
+    pub data: u8,
+}
+
+/// NSF memory map: RAM, $6000-$7FFF WRAM and the tune's data in 4 KiB banks from $8000.
+pub struct NsfBus {
+    pub ram: Vec<u8>,
+    pub wram: Vec<u8>,
+    pub banks: [u8; 8],
+    pub apu_writes: Vec<ApuWrite>,
+    rom: Vec<u8>,
+    cycles: u64,
+}
+
+impl NsfBus {
+    fn new(nsf: &Nsf) -> Self {
+        // Unbanked tunes are placed at their load address; banked ones are padded
+        // by the load address' offset within its bank.
+        let offset = if nsf.bankswitch.is_some() {
+            usize::from(nsf.load_address) % BANK_LEN
+        } else {
+            usize::from(nsf.load_address.saturating_sub(0x8000))
+        };
+        let len = (offset + nsf.data.len())
+            .next_multiple_of(BANK_LEN)
+            .max(0x8000);
+        let mut rom = vec![0x00; len];
+        rom[offset..(offset + nsf.data.len())].copy_from_slice(nsf.data);
+
+        Self {
+            ram: vec![0x00; 0x800],
+            wram: vec![0x00; 0x2000],
+            banks: [0, 1, 2, 3, 4, 5, 6, 7],
+            apu_writes: Vec::new(),
+            rom,
+            cycles: 0,
+        }
+    }
+
+    fn reset(&mut self, bankswitch: Option<[u8; 8]>) {
+        self.ram.fill(0x00);
+        self.wram.fill(0x00);
+        self.banks = bankswitch.unwrap_or([0, 1, 2, 3, 4, 5, 6, 7]);
+    }
+}
+
+impl Bus for NsfBus {
+    fn read_u8(&mut self, address: u16) -> u8 {
+        match address {
+            0x0000..=0x1FFF => self.ram[usize::from(address & 0x07FF)],
+            0x6000..=0x7FFF => self.wram[usize::from(address - 0x6000)],
+            0x8000..=0xFFFF => {
+                let address = usize::from(address - 0x8000);
+                let bank = usize::from(self.banks[address / BANK_LEN]);
+                let address = (bank * BANK_LEN + address % BANK_LEN) % self.rom.len();
+                self.rom[address]
+            }
+            // todo: APU status
+            _ => 0x00,
+        }
+    }
+
+    fn write_u8(&mut self, address: u16, data: u8) {
+        match address {
+            0x0000..=0x1FFF => self.ram[usize::from(address & 0x07FF)] = data,
+            0x4000..=0x4017 => self.apu_writes.push(ApuWrite {
+                cycle: self.cycles,
+                address,
+                data,
+            }),
+            0x5FF8..=0x5FFF => self.banks[usize::from(address - 0x5FF8)] = data,
+            0x6000..=0x7FFF => self.wram[usize::from(address - 0x6000)] = data,
+            _ => {}
+        }
+    }
+}
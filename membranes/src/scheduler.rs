@@ -0,0 +1,44 @@
+//! Interleaves the PPU with the CPU.
+
+use membranes_ppu::Region;
+
+/// PPU dots per CPU cycle, in fifths so PAL's 3.2 stays exact.
+pub const NTSC_DOTS_PER_CYCLE: u8 = 15;
+pub const PAL_DOTS_PER_CYCLE: u8 = 16;
+
+/// Dendy divides its PAL master clock by 15 for the CPU, keeping NTSC's 3 dots per cycle.
+pub fn dots_per_cycle(region: Region) -> u8 {
+    match region {
+        Region::Ntsc | Region::Dendy => NTSC_DOTS_PER_CYCLE,
+        Region::Pal => PAL_DOTS_PER_CYCLE,
+    }
+}
+
+pub struct Scheduler {
+    /// In fifths of a dot
+    pub dots_per_cycle: u8,
+    /// Fifths of a dot carried over from earlier cycles.
+    remainder: u8,
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self::new(NTSC_DOTS_PER_CYCLE)
+    }
+}
+
+impl Scheduler {
+    pub fn new(dots_per_cycle: u8) -> Self {
+        Self {
+            dots_per_cycle,
+            remainder: 0,
+        }
+    }
+
+    /// PPU dots to run alongside the next CPU cycle.
+    pub fn dots(&mut self) -> u8 {
+        let fifths = self.remainder + self.dots_per_cycle;
+        self.remainder = fifths % 5;
+        fifths / 5
+    }
+}
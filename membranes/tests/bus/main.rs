@@ -0,0 +1,119 @@
+use membranes::{cpu::Bus as _, ppu::Region, Nes};
+
+/// NROM image with CHR-RAM.
+fn nes() -> Nes {
+    let mut rom = vec![b'N', b'E', b'S', 0x1A, 1, 0, 0x00, 0x00];
+    rom.resize(16 + 0x4000, 0x00);
+    let mut nes = Nes::new();
+    nes.load(&rom).unwrap();
+    nes
+}
+
+/// NROM image whose PRG-ROM is filled with `sample`.
+fn dmc_nes(sample: u8) -> Nes {
+    let mut rom = vec![b'N', b'E', b'S', 0x1A, 1, 0, 0x00, 0x00];
+    rom.resize(16, 0x00);
+    rom.resize(16 + 0x4000, sample);
+    let mut nes = Nes::new();
+    nes.load(&rom).unwrap();
+    nes
+}
+
+/// Plays a one byte DMC sample from $C000, with `flags` for $4010.
+fn play_dmc(nes: &mut Nes, flags: u8) {
+    nes.bus.write_u8(0x4010, flags);
+    nes.bus.write_u8(0x4012, 0x00);
+    nes.bus.write_u8(0x4013, 0x00);
+    nes.bus.write_u8(0x4015, 0x10);
+    nes.bus.run_dmc_dma();
+}
+
+#[test]
+fn apu_registers_ignored() {
+    let mut nes = nes();
+    for address in (0x4000..=0x400F).chain([0x4017]) {
+        nes.bus.write_u8(address, 0xFF);
+    }
+    assert_eq!(nes.bus.read_u8(0x4015), 0x00);
+    assert_eq!(nes.bus.read_u8(0x4018), 0x00);
+}
+
+#[test]
+fn dmc_output() {
+    let mut nes = dmc_nes(0b1111_0000);
+    nes.bus.write_u8(0x4011, 64);
+    play_dmc(&mut nes, 0x0F);
+    assert_eq!(nes.bus.read_u8(0x4015), 0x00);
+    for _ in 0..10 {
+        nes.bus.clock(200);
+    }
+    // Four steps down, four up, then silence.
+    assert_eq!(nes.bus.dmc.level(), 64);
+    assert!(nes.bus.dmc.audio() > 0.0);
+
+    let mut nes = dmc_nes(0xFF);
+    nes.bus.write_u8(0x4011, 120);
+    play_dmc(&mut nes, 0x0F);
+    for _ in 0..10 {
+        nes.bus.clock(200);
+    }
+    // Clamped at the top.
+    assert_eq!(nes.bus.dmc.level(), 126);
+}
+
+#[test]
+fn dmc_rate_follows_region() {
+    // The first bit plays 8 periods in, after the empty shift register.
+    let level_after = |region, cycles: u16| {
+        let mut nes = dmc_nes(0xFF);
+        nes.set_region(region);
+        play_dmc(&mut nes, 0x00);
+        for _ in 0..cycles / 100 {
+            nes.bus.clock(100);
+        }
+        nes.bus.dmc.level()
+    };
+    assert_eq!(level_after(Region::Pal, 3700), 2);
+    assert_eq!(level_after(Region::Ntsc, 3700), 0);
+    assert_eq!(level_after(Region::Dendy, 3700), 0);
+}
+
+#[test]
+fn dmc_irq() {
+    let mut nes = dmc_nes(0x00);
+    play_dmc(&mut nes, 0x8F);
+    assert!(nes.bus.irq());
+    // Reading $4015 leaves it, writing acknowledges it.
+    assert_eq!(nes.bus.read_u8(0x4015), 0x80);
+    assert!(nes.bus.irq());
+    nes.bus.write_u8(0x4015, 0x00);
+    assert!(!nes.bus.irq());
+
+    // Looping samples never finish.
+    let mut nes = dmc_nes(0x00);
+    play_dmc(&mut nes, 0xCF);
+    assert_eq!(nes.bus.read_u8(0x4015), 0x10);
+    assert!(!nes.bus.irq());
+}
+
+#[test]
+fn controller_reads() {
+    let mut nes = nes();
+    // A, Start and Left
+    nes.press(1, 0b0100_1001);
+    nes.press(2, 0b0000_0010);
+    nes.bus.write_u8(0x4016, 1);
+    assert_eq!(nes.bus.read_u8(0x4016), 1);
+    assert_eq!(nes.bus.read_u8(0x4016), 1);
+    nes.bus.write_u8(0x4016, 0);
+
+    let reads: Vec<u8> = (0..10).map(|_| nes.bus.read_u8(0x4016)).collect();
+    assert_eq!(reads, [1, 0, 0, 1, 0, 0, 1, 0, 1, 1]);
+    let reads: Vec<u8> = (0..3).map(|_| nes.bus.read_u8(0x4017)).collect();
+    assert_eq!(reads, [0, 1, 0]);
+
+    nes.release(1, 0b0000_0001);
+    nes.bus.write_u8(0x4016, 1);
+    nes.bus.write_u8(0x4016, 0);
+    assert_eq!(nes.bus.read_u8(0x4016), 0);
+}
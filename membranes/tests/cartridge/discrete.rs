@@ -0,0 +1,136 @@
+use crate::{ines, load};
+use membranes::{cartridge::Mirroring, cpu::Bus as _, Nes};
+
+/// Offset in every PRG bank that holds 0xFF, so latch writes there don't conflict.
+const OPEN: u16 = 0x0100;
+
+fn rom(mapper: u8, prg_banks: u8, chr_banks: u8) -> Vec<u8> {
+    let mut rom = ines(mapper, 0, prg_banks, chr_banks);
+    for bank in 0..usize::from(prg_banks) {
+        rom[16 + bank * 0x4000 + usize::from(OPEN)] = 0xFF;
+    }
+    rom
+}
+
+fn with_submapper(mut rom: Vec<u8>, submapper: u8) -> Vec<u8> {
+    rom[7] |= 0x08;
+    rom[8] = submapper << 4;
+    rom
+}
+
+/// PRG bank ids at $8000 and $C000.
+fn prg(nes: &mut Nes) -> [u8; 2] {
+    [nes.bus.read_u8(0x8000), nes.bus.read_u8(0xC000)]
+}
+
+/// CHR bank ids at $0000 and $1000.
+fn chr(nes: &mut Nes) -> [u8; 2] {
+    [
+        nes.bus.cartridge.ppu_read(0x0000),
+        nes.bus.cartridge.ppu_read(0x1000),
+    ]
+}
+
+#[test]
+fn latch() {
+    // (mapper, PRG banks, CHR banks, latch value, PRG after, CHR after), with zeroed CHR-RAM
+    let cases = [
+        (2, 8, 0, 0x03, [3, 7], [0x00, 0x00]),
+        (3, 2, 4, 0x02, [0, 1], [0x82, 0x82]),
+        (7, 8, 0, 0x02, [4, 5], [0x00, 0x00]),
+        (11, 8, 4, 0x31, [2, 3], [0x83, 0x83]),
+        (34, 8, 0, 0x02, [4, 5], [0x00, 0x00]),
+        (66, 8, 4, 0x21, [4, 5], [0x81, 0x81]),
+    ];
+
+    for (mapper, prg_banks, chr_banks, value, expected_prg, expected_chr) in cases {
+        let mut nes = load(&rom(mapper, prg_banks, chr_banks));
+        nes.bus.write_u8(0x8000 + OPEN, value);
+
+        assert_eq!(prg(&mut nes), expected_prg, "mapper {mapper}");
+        assert_eq!(chr(&mut nes), expected_chr, "mapper {mapper}");
+    }
+}
+
+#[test]
+fn power_on_banks() {
+    assert_eq!(prg(&mut load(&rom(2, 8, 0))), [0, 7]);
+    for mapper in [3, 7, 11, 34, 66] {
+        assert_eq!(
+            prg(&mut load(&rom(mapper, 8, 4))),
+            [0, 1],
+            "mapper {mapper}"
+        );
+    }
+}
+
+#[test]
+fn chr_ram() {
+    for mapper in [2, 7, 34] {
+        let mut nes = load(&rom(mapper, 8, 0));
+        nes.bus.cartridge.ppu_write(0x1234, 0x5A);
+        assert_eq!(nes.bus.cartridge.ppu_read(0x1234), 0x5A, "mapper {mapper}");
+    }
+}
+
+#[test]
+fn bus_conflicts() {
+    // Bank 0 reads as 0x00 at $8000, so conflicting writes there select bank 0.
+    for mapper in [2, 3, 11, 34, 66] {
+        let chr_banks = if matches!(mapper, 2 | 34) { 0 } else { 4 };
+        let mut nes = load(&rom(mapper, 8, chr_banks));
+        let before = (prg(&mut nes), chr(&mut nes));
+        nes.bus.write_u8(0x8000, 0xFF);
+        assert_eq!((prg(&mut nes), chr(&mut nes)), before, "mapper {mapper}");
+    }
+}
+
+#[test]
+fn no_bus_conflicts() {
+    let mut nes = load(&with_submapper(rom(2, 8, 0), 1));
+    nes.bus.write_u8(0x8000, 0x03);
+    assert_eq!(prg(&mut nes), [3, 7]);
+
+    let mut nes = load(&rom(7, 8, 0));
+    nes.bus.write_u8(0x8000, 0x01);
+    assert_eq!(prg(&mut nes), [2, 3]);
+
+    let mut nes = load(&with_submapper(rom(7, 8, 0), 2));
+    nes.bus.write_u8(0x8000, 0x01);
+    assert_eq!(prg(&mut nes), [0, 1]);
+}
+
+#[test]
+fn axrom_single_screen() {
+    let mut nes = load(&rom(7, 8, 0));
+    assert_eq!(nes.mirroring(), Mirroring::SingleScreenLower);
+
+    nes.bus.write_u8(0x8000 + OPEN, 0x10);
+    assert_eq!(nes.mirroring(), Mirroring::SingleScreenUpper);
+    nes.bus.write_u8(0x8000 + OPEN, 0x00);
+    assert_eq!(nes.mirroring(), Mirroring::SingleScreenLower);
+}
+
+#[test]
+fn nina_001() {
+    let mut nes = load(&rom(34, 4, 2));
+
+    nes.bus.write_u8(0x7FFD, 1);
+    nes.bus.write_u8(0x7FFE, 3);
+    nes.bus.write_u8(0x7FFF, 0);
+
+    assert_eq!(prg(&mut nes), [2, 3]);
+    assert_eq!(chr(&mut nes), [0x81, 0x80]);
+    assert_eq!(nes.bus.read_u8(0x7FFD), 1);
+
+    nes.bus.write_u8(0x6000, 0x42);
+    assert_eq!(nes.bus.read_u8(0x6000), 0x42);
+}
+
+#[test]
+fn empty_prg_rom() {
+    for mapper in [2, 3, 7, 11, 34, 66] {
+        let mut nes = Nes::new();
+        assert!(nes.load(&ines(mapper, 0, 0, 1)).is_err(), "mapper {mapper}");
+    }
+}
@@ -0,0 +1,133 @@
+use crate::{banked, chr, load};
+use membranes::{audio::PULSE_LEVEL, cartridge::Mirroring, cpu::Bus as _, Nes};
+
+fn rom() -> Vec<u8> {
+    banked(69, 0, 2, 0x400)
+}
+
+fn command(nes: &mut Nes, command: u8, parameter: u8) {
+    nes.bus.write_u8(0x8000, command);
+    nes.bus.write_u8(0xA000, parameter);
+}
+
+fn write_audio(nes: &mut Nes, register: u8, data: u8) {
+    nes.bus.write_u8(0xC000, register);
+    nes.bus.write_u8(0xE000, data);
+}
+
+#[test]
+fn prg_banks() {
+    let mut nes = load(&rom());
+    command(&mut nes, 0x8, 2);
+    command(&mut nes, 0x9, 3);
+    command(&mut nes, 0xA, 5);
+    command(&mut nes, 0xB, 7);
+    let prg = [0x6000, 0x8000, 0xA000, 0xC000, 0xE000].map(|address| nes.bus.read_u8(address));
+    assert_eq!(prg, [2, 3, 5, 7, 15]);
+}
+
+#[test]
+fn prg_ram() {
+    let mut nes = load(&rom());
+    // Selected but disabled.
+    command(&mut nes, 0x8, 0x40);
+    nes.bus.write_u8(0x6000, 0xAB);
+    assert_eq!(nes.bus.read_u8(0x6000), 0x00);
+
+    command(&mut nes, 0x8, 0xC0);
+    nes.bus.write_u8(0x6000, 0xAB);
+    assert_eq!(nes.bus.read_u8(0x6000), 0xAB);
+
+    command(&mut nes, 0x8, 0x01);
+    assert_eq!(nes.bus.read_u8(0x6000), 1);
+}
+
+#[test]
+fn chr_banks_and_mirroring() {
+    let mut nes = load(&rom());
+    for i in 0..8 {
+        command(&mut nes, i, 15 - i);
+    }
+    assert_eq!(chr(&mut nes), [15, 14, 13, 12, 11, 10, 9, 8]);
+
+    for (data, mirroring) in [
+        (0, Mirroring::Vertical),
+        (1, Mirroring::Horizontal),
+        (2, Mirroring::SingleScreenLower),
+        (3, Mirroring::SingleScreenUpper),
+    ] {
+        command(&mut nes, 0xC, data);
+        assert_eq!(nes.mirroring(), mirroring);
+    }
+}
+
+#[test]
+fn irq() {
+    let mut nes = load(&rom());
+    command(&mut nes, 0xE, 0x02);
+    command(&mut nes, 0xF, 0x00);
+    command(&mut nes, 0xD, 0x81);
+    nes.bus.clock(2);
+    assert!(!nes.bus.irq());
+    nes.bus.clock(1);
+    assert!(nes.bus.irq());
+    command(&mut nes, 0xD, 0x81);
+    assert!(!nes.bus.irq());
+
+    // Counting without raising IRQ.
+    command(&mut nes, 0xE, 0x00);
+    command(&mut nes, 0xD, 0x80);
+    nes.bus.clock(2);
+    assert!(!nes.bus.irq());
+}
+
+#[test]
+fn tone() {
+    let mut nes = load(&rom());
+    write_audio(&mut nes, 0x0, 1);
+    write_audio(&mut nes, 0x7, 0b111110);
+    write_audio(&mut nes, 0x8, 12);
+
+    let mut levels = Vec::new();
+    for _ in 0..4 {
+        nes.bus.clock(16);
+        levels.push(nes.bus.cartridge.audio());
+    }
+    assert_eq!(levels, [PULSE_LEVEL, 0.0, PULSE_LEVEL, 0.0]);
+
+    // Addresses with the top bits set select nothing.
+    nes.bus.write_u8(0xC000, 0x18);
+    nes.bus.write_u8(0xE000, 0);
+    nes.bus.clock(16);
+    assert_eq!(nes.bus.cartridge.audio(), PULSE_LEVEL);
+}
+
+#[test]
+fn envelope() {
+    let mut nes = load(&rom());
+    write_audio(&mut nes, 0x7, 0b111111);
+    write_audio(&mut nes, 0x8, 0x10);
+    write_audio(&mut nes, 0xB, 1);
+    // Rise once, then hold.
+    write_audio(&mut nes, 0xD, 0b1101);
+
+    let mut levels = Vec::new();
+    for _ in 0..40 {
+        levels.push(nes.bus.cartridge.audio());
+        nes.bus.clock(16);
+    }
+    assert_eq!(levels[0], 0.0);
+    assert!(levels[..32].windows(2).all(|pair| pair[0] < pair[1]));
+    assert!(levels[31..].iter().all(|&level| level == levels[31]));
+    // 6 steps of 1.5 dB above volume 12.
+    assert!((levels[31] / PULSE_LEVEL - 2.818).abs() < 0.01);
+
+    // A triangle turns back after the top.
+    write_audio(&mut nes, 0xD, 0b1110);
+    for _ in 0..32 {
+        nes.bus.clock(16);
+    }
+    let top = nes.bus.cartridge.audio();
+    nes.bus.clock(16 * 2);
+    assert!(nes.bus.cartridge.audio() < top);
+}
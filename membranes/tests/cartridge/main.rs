@@ -0,0 +1,85 @@
+use membranes::{cpu::Bus as _, Nes};
+
+mod discrete;
+mod fme7;
+mod mmc1;
+mod mmc2;
+mod mmc3;
+mod mmc5;
+mod namco163;
+mod nrom;
+mod vrc2;
+mod vrc6;
+mod vrc7;
+
+/// iNES image whose PRG banks are filled with their bank number and CHR banks with 0x80 + theirs.
+fn ines(mapper: u8, flags_6: u8, prg_banks: u8, chr_banks: u8) -> Vec<u8> {
+    let mut rom = vec![
+        b'N',
+        b'E',
+        b'S',
+        0x1A,
+        prg_banks,
+        chr_banks,
+        mapper << 4 | flags_6,
+        mapper & 0xF0,
+    ];
+    rom.resize(16, 0x00);
+    for bank in 0..prg_banks {
+        rom.extend([bank; 0x4000]);
+    }
+    for bank in 0..chr_banks {
+        rom.extend([0x80 + bank; 0x2000]);
+    }
+    rom
+}
+
+/// iNES image with 128 KiB of PRG-ROM, whose 8 KiB banks read as their number, and
+/// `chr_banks` times 8 KiB of CHR-ROM, whose `chr_bank_len` banks read as theirs.
+fn banked(mapper: u8, flags_6: u8, chr_banks: u8, chr_bank_len: usize) -> Vec<u8> {
+    let mut rom = ines(mapper, flags_6, 8, chr_banks);
+    for (i, bank) in rom[16..].chunks_mut(0x2000).take(16).enumerate() {
+        bank.fill(i as u8);
+    }
+    for (i, bank) in rom[(16 + 0x20000)..].chunks_mut(chr_bank_len).enumerate() {
+        bank.fill(i as u8);
+    }
+    rom
+}
+
+/// What each 8 KiB PRG window at $8000-$FFFF starts with.
+fn prg(nes: &mut Nes) -> [u8; 4] {
+    [0x8000, 0xA000, 0xC000, 0xE000].map(|address| nes.bus.read_u8(address))
+}
+
+/// What each 1 KiB CHR window starts with.
+fn chr(nes: &mut Nes) -> [u8; 8] {
+    [0, 1, 2, 3, 4, 5, 6, 7].map(|i| nes.bus.cartridge.ppu_read(i * 0x400))
+}
+
+/// NES 2.0 image with 4 KiB of PRG-ROM, smaller than any bank.
+fn tiny_prg(mapper: u8) -> Vec<u8> {
+    let mut rom = ines(mapper, 0, 0, 1);
+    rom[7] |= 0x08;
+    // 2^12 in exponent-multiplier notation
+    rom[4] = 12 << 2;
+    rom[9] = 0x0F;
+    rom.splice(16..16, [0xEA; 0x1000]);
+    rom
+}
+
+fn load(rom: &[u8]) -> Nes {
+    let mut nes = Nes::new();
+    nes.load(rom).unwrap();
+    nes
+}
+
+#[test]
+fn prg_smaller_than_a_bank() {
+    for mapper in [1, 4, 21, 23, 24, 26, 85, 19, 69] {
+        let mut nes = load(&tiny_prg(mapper));
+        for address in [0x8000, 0xA000, 0xC000, 0xE000] {
+            assert_eq!(nes.bus.read_u8(address), 0xEA, "mapper {mapper}");
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use crate::{ines, load};
+use membranes::{cartridge::Mirroring, cpu::Bus as _, Nes};
+
+/// Shifts a register value in one bit per write, on separate cycles.
+fn write_serial(nes: &mut Nes, address: u16, value: u8) {
+    for bit in 0..5 {
+        nes.bus.write_u8(address, value >> bit & 1);
+        nes.bus.clock(1);
+    }
+}
+
+/// Switches to NES 2.0 to declare PRG-RAM of `64 << shift` bytes.
+fn with_prg_ram(mut rom: Vec<u8>, shift: u8) -> Vec<u8> {
+    rom[7] |= 0x08;
+    rom[10] = shift;
+    rom[11] = if rom[5] == 0 { 7 } else { 0 };
+    rom
+}
+
+#[test]
+fn power_on_fixes_last_bank() {
+    let mut nes = load(&ines(1, 0, 8, 1));
+
+    assert_eq!(nes.bus.read_u8(0x8000), 0);
+    assert_eq!(nes.bus.read_u8(0xFFFF), 7);
+}
+
+#[test]
+fn prg_modes() {
+    let mut nes = load(&ines(1, 0, 8, 1));
+    write_serial(&mut nes, 0xE000, 3);
+    assert_eq!(nes.bus.read_u8(0x8000), 3);
+    assert_eq!(nes.bus.read_u8(0xC000), 7);
+
+    write_serial(&mut nes, 0x8000, 0b01000);
+    assert_eq!(nes.bus.read_u8(0x8000), 0);
+    assert_eq!(nes.bus.read_u8(0xC000), 3);
+
+    write_serial(&mut nes, 0x8000, 0b00000);
+    assert_eq!(nes.bus.read_u8(0x8000), 2);
+    assert_eq!(nes.bus.read_u8(0xC000), 3);
+}
+
+#[test]
+fn chr_modes() {
+    let mut nes = load(&ines(1, 0, 2, 4));
+    write_serial(&mut nes, 0xA000, 3);
+    assert_eq!(nes.bus.cartridge.ppu_read(0x0000), 0x81);
+    assert_eq!(nes.bus.cartridge.ppu_read(0x1000), 0x81);
+
+    write_serial(&mut nes, 0x8000, 0b11100);
+    write_serial(&mut nes, 0xA000, 2);
+    write_serial(&mut nes, 0xC000, 7);
+    assert_eq!(nes.bus.cartridge.ppu_read(0x0000), 0x81);
+    assert_eq!(nes.bus.cartridge.ppu_read(0x1000), 0x83);
+}
+
+#[test]
+fn mirroring() {
+    let mut nes = load(&ines(1, 0, 2, 1));
+    let expected = [
+        Mirroring::SingleScreenLower,
+        Mirroring::SingleScreenUpper,
+        Mirroring::Vertical,
+        Mirroring::Horizontal,
+    ];
+    for (value, expected) in expected.into_iter().enumerate() {
+        write_serial(&mut nes, 0x8000, 0b01100 | value as u8);
+        assert_eq!(nes.mirroring(), expected);
+    }
+}
+
+#[test]
+fn reset_bit() {
+    let mut nes = load(&ines(1, 0, 8, 1));
+    write_serial(&mut nes, 0x8000, 0b00000);
+    nes.bus.write_u8(0xE000, 0b1);
+    nes.bus.clock(1);
+    nes.bus.write_u8(0x8000, 0x80);
+    nes.bus.clock(1);
+
+    // The partial value was discarded and the last bank is fixed again.
+    write_serial(&mut nes, 0xE000, 2);
+    assert_eq!(nes.bus.read_u8(0x8000), 2);
+    assert_eq!(nes.bus.read_u8(0xC000), 7);
+}
+
+#[test]
+fn consecutive_writes_are_ignored() {
+    let mut nes = load(&ines(1, 0, 8, 1));
+    for _ in 0..5 {
+        nes.bus.write_u8(0xE000, 0);
+        nes.bus.write_u8(0xE000, 1);
+        nes.bus.clock(1);
+    }
+    assert_eq!(nes.bus.read_u8(0x8000), 0);
+}
+
+#[test]
+fn prg_ram_enable() {
+    let mut nes = load(&ines(1, 0, 2, 1));
+    nes.bus.write_u8(0x6000, 0x42);
+    assert_eq!(nes.bus.read_u8(0x6000), 0x42);
+
+    write_serial(&mut nes, 0xE000, 0x10);
+    assert_eq!(nes.bus.read_u8(0x6000), 0x00);
+    write_serial(&mut nes, 0xE000, 0x00);
+    assert_eq!(nes.bus.read_u8(0x6000), 0x42);
+}
+
+#[test]
+fn snrom_disables_prg_ram() {
+    let mut nes = load(&ines(1, 0, 16, 0));
+    nes.bus.write_u8(0x6000, 0x42);
+
+    write_serial(&mut nes, 0xA000, 0x10);
+    assert_eq!(nes.bus.read_u8(0x6000), 0x00);
+    write_serial(&mut nes, 0xA000, 0x00);
+    assert_eq!(nes.bus.read_u8(0x6000), 0x42);
+}
+
+#[test]
+fn surom_outer_bank() {
+    let mut nes = load(&ines(1, 0, 32, 0));
+    assert_eq!(nes.bus.read_u8(0x8000), 0);
+    assert_eq!(nes.bus.read_u8(0xC000), 15);
+
+    write_serial(&mut nes, 0xA000, 0x10);
+    write_serial(&mut nes, 0xE000, 3);
+    assert_eq!(nes.bus.read_u8(0x8000), 19);
+    assert_eq!(nes.bus.read_u8(0xC000), 31);
+}
+
+#[test]
+fn sorom_prg_ram_banks() {
+    let mut nes = load(&with_prg_ram(ines(1, 0, 16, 0), 8));
+    nes.bus.write_u8(0x6000, 0x11);
+    write_serial(&mut nes, 0xA000, 0x08);
+    nes.bus.write_u8(0x6000, 0x22);
+
+    assert_eq!(nes.bus.read_u8(0x6000), 0x22);
+    write_serial(&mut nes, 0xA000, 0x00);
+    assert_eq!(nes.bus.read_u8(0x6000), 0x11);
+}
+
+#[test]
+fn sxrom_prg_ram_banks() {
+    let mut nes = load(&with_prg_ram(ines(1, 0, 32, 0), 9));
+    for bank in 0..4 {
+        write_serial(&mut nes, 0xA000, bank << 2);
+        nes.bus.write_u8(0x6000, bank);
+    }
+    for bank in 0..4 {
+        write_serial(&mut nes, 0xA000, bank << 2);
+        assert_eq!(nes.bus.read_u8(0x6000), bank);
+    }
+}
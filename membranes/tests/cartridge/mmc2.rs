@@ -0,0 +1,89 @@
+use crate::{banked, load, prg};
+use membranes::{cartridge::Mirroring, cpu::Bus as _, Nes};
+
+fn rom(mapper: u8) -> Vec<u8> {
+    banked(mapper, 0, 4, 0x1000)
+}
+
+/// Selects CHR banks 1/2 for $0000 and 3/4 for $1000, at latch $FD/$FE.
+fn set_chr_banks(nes: &mut Nes) {
+    for (address, bank) in [(0xB000, 1), (0xC000, 2), (0xD000, 3), (0xE000, 4)] {
+        nes.bus.write_u8(address, bank);
+    }
+}
+
+#[test]
+fn mmc2_prg() {
+    let mut nes = load(&rom(9));
+    nes.bus.write_u8(0xA000, 5);
+    assert_eq!(prg(&mut nes), [5, 13, 14, 15]);
+}
+
+#[test]
+fn mmc4_prg_and_ram() {
+    let mut nes = load(&rom(10));
+    nes.bus.write_u8(0xA000, 2);
+    assert_eq!(prg(&mut nes), [4, 5, 14, 15]);
+
+    nes.bus.write_u8(0x6000, 0x42);
+    assert_eq!(nes.bus.read_u8(0x6000), 0x42);
+}
+
+#[test]
+fn latches() {
+    for mapper in [9, 10] {
+        let mut nes = load(&rom(mapper));
+        set_chr_banks(&mut nes);
+        let cartridge = &mut nes.bus.cartridge;
+        assert_eq!(cartridge.ppu_read(0x0000), 2);
+        assert_eq!(cartridge.ppu_read(0x1000), 4);
+
+        // The triggering fetch still reads the old bank.
+        assert_eq!(cartridge.ppu_read(0x0FD8), 2);
+        assert_eq!(cartridge.ppu_read(0x0000), 1);
+        assert_eq!(cartridge.ppu_read(0x1FDC), 4);
+        assert_eq!(cartridge.ppu_read(0x1000), 3);
+
+        cartridge.ppu_read(0x0FE8);
+        cartridge.ppu_read(0x1FE8);
+        assert_eq!(cartridge.ppu_read(0x0000), 2, "mapper {mapper}");
+        assert_eq!(cartridge.ppu_read(0x1000), 4, "mapper {mapper}");
+    }
+}
+
+#[test]
+fn peek_keeps_latches() {
+    let mut nes = load(&rom(9));
+    set_chr_banks(&mut nes);
+    let cartridge = &mut nes.bus.cartridge;
+    assert_eq!(cartridge.ppu_peek(0x0FD8), 2);
+    assert_eq!(cartridge.ppu_peek(0x1FD8), 4);
+    assert_eq!(cartridge.ppu_read(0x0000), 2);
+    assert_eq!(cartridge.ppu_read(0x1000), 4);
+
+    // Drawing the pattern tables peeks through every tile, latch triggers included.
+    nes.debug_pattern_tables(0);
+    assert_eq!(nes.bus.cartridge.ppu_read(0x0000), 2);
+}
+
+#[test]
+fn left_latch_range() {
+    let mut nes = load(&rom(9));
+    set_chr_banks(&mut nes);
+    nes.bus.cartridge.ppu_read(0x0FDA);
+    assert_eq!(nes.bus.cartridge.ppu_read(0x0000), 2);
+
+    let mut nes = load(&rom(10));
+    set_chr_banks(&mut nes);
+    nes.bus.cartridge.ppu_read(0x0FDA);
+    assert_eq!(nes.bus.cartridge.ppu_read(0x0000), 1);
+}
+
+#[test]
+fn mirroring() {
+    let mut nes = load(&rom(9));
+    nes.bus.write_u8(0xF000, 1);
+    assert_eq!(nes.mirroring(), Mirroring::Horizontal);
+    nes.bus.write_u8(0xF000, 0);
+    assert_eq!(nes.mirroring(), Mirroring::Vertical);
+}
@@ -0,0 +1,166 @@
+use crate::{banked, chr, load, prg};
+use membranes::{cartridge::Mirroring, cpu::Bus as _, Nes};
+
+/// NES 2.0 image with 8 KiB of PRG-RAM.
+fn rom(submapper: u8) -> Vec<u8> {
+    let mut rom = banked(4, 0, 2, 0x400);
+    rom[7] |= 0x08;
+    rom[8] = submapper << 4;
+    rom[10] = 7;
+    rom
+}
+
+fn set_banks(nes: &mut Nes, mode: u8, banks: [u8; 8]) {
+    for (i, bank) in banks.into_iter().enumerate() {
+        nes.bus.write_u8(0x8000, mode | i as u8);
+        nes.bus.write_u8(0x8001, bank);
+    }
+}
+
+/// Drives A12 like one rendered scanline: background fetches from $0000 and
+/// sprite fetches from $1000.
+fn scanline(nes: &mut Nes) {
+    nes.bus.cartridge.ppu_address(0x0000);
+    nes.bus.clock(85);
+    nes.bus.cartridge.ppu_address(0x1000);
+    nes.bus.clock(28);
+}
+
+fn start_irq(nes: &mut Nes, latch: u8) {
+    nes.bus.write_u8(0xC000, latch);
+    nes.bus.write_u8(0xC001, 0);
+    nes.bus.write_u8(0xE001, 0);
+}
+
+#[test]
+fn prg_modes() {
+    let mut nes = load(&rom(0));
+    set_banks(&mut nes, 0x00, [0, 0, 0, 0, 0, 0, 3, 5]);
+    assert_eq!(prg(&mut nes), [3, 5, 14, 15]);
+
+    nes.bus.write_u8(0x8000, 0x40);
+    assert_eq!(prg(&mut nes), [14, 5, 3, 15]);
+}
+
+#[test]
+fn chr_inversion() {
+    let mut nes = load(&rom(0));
+    set_banks(&mut nes, 0x00, [3, 6, 8, 9, 10, 11, 0, 1]);
+    assert_eq!(chr(&mut nes), [2, 3, 6, 7, 8, 9, 10, 11]);
+
+    nes.bus.write_u8(0x8000, 0x80);
+    assert_eq!(chr(&mut nes), [8, 9, 10, 11, 2, 3, 6, 7]);
+}
+
+#[test]
+fn mirroring() {
+    let mut nes = load(&rom(0));
+    nes.bus.write_u8(0xA000, 1);
+    assert_eq!(nes.mirroring(), Mirroring::Horizontal);
+    nes.bus.write_u8(0xA000, 0);
+    assert_eq!(nes.mirroring(), Mirroring::Vertical);
+}
+
+#[test]
+fn prg_ram_protect() {
+    let mut nes = load(&rom(0));
+    nes.bus.write_u8(0x6000, 0x42);
+    assert_eq!(nes.bus.read_u8(0x6000), 0x42);
+
+    nes.bus.write_u8(0xA001, 0xC0);
+    nes.bus.write_u8(0x6000, 0x00);
+    assert_eq!(nes.bus.read_u8(0x6000), 0x42);
+
+    nes.bus.write_u8(0xA001, 0x00);
+    assert_eq!(nes.bus.read_u8(0x6000), 0x00);
+}
+
+#[test]
+fn scanline_irq() {
+    let mut nes = load(&rom(0));
+    start_irq(&mut nes, 3);
+
+    for _ in 0..3 {
+        scanline(&mut nes);
+        assert!(!nes.bus.irq());
+    }
+    scanline(&mut nes);
+    assert!(nes.bus.irq());
+
+    nes.bus.write_u8(0xE000, 0);
+    assert!(!nes.bus.irq());
+}
+
+#[test]
+fn a12_filter() {
+    let mut nes = load(&rom(0));
+    start_irq(&mut nes, 1);
+    scanline(&mut nes);
+
+    // Sprite fetches drop A12 only briefly for nametable fetches.
+    for _ in 0..8 {
+        nes.bus.cartridge.ppu_address(0x2000);
+        nes.bus.clock(1);
+        nes.bus.cartridge.ppu_address(0x1000);
+        nes.bus.clock(2);
+    }
+    assert!(!nes.bus.irq());
+
+    scanline(&mut nes);
+    assert!(nes.bus.irq());
+}
+
+#[test]
+fn ppuaddr_drives_a12() {
+    let mut nes = load(&rom(0));
+    start_irq(&mut nes, 0);
+    for address in [0x0000, 0x1000] {
+        let [high, low] = u16::to_be_bytes(address);
+        nes.bus.write_u8(0x2006, high);
+        nes.bus.write_u8(0x2006, low);
+        nes.bus.clock(20);
+    }
+    assert!(nes.bus.irq());
+}
+
+#[test]
+fn latch_zero_revisions() {
+    let mut nes = load(&rom(0));
+    start_irq(&mut nes, 0);
+    for _ in 0..3 {
+        scanline(&mut nes);
+        assert!(nes.bus.irq());
+        nes.bus.write_u8(0xE000, 0);
+        nes.bus.write_u8(0xE001, 0);
+    }
+
+    // The MMC3A only raises IRQ for the reload.
+    let mut nes = load(&rom(4));
+    start_irq(&mut nes, 0);
+    scanline(&mut nes);
+    assert!(nes.bus.irq());
+    nes.bus.write_u8(0xE000, 0);
+    nes.bus.write_u8(0xE001, 0);
+    scanline(&mut nes);
+    assert!(!nes.bus.irq());
+}
+
+#[test]
+fn mmc6_prg_ram() {
+    let mut nes = load(&rom(1));
+    nes.bus.write_u8(0x7000, 0x42);
+    assert_eq!(nes.bus.read_u8(0x7000), 0x00);
+
+    nes.bus.write_u8(0x8000, 0x20);
+    nes.bus.write_u8(0xA001, 0b1011_0000);
+    nes.bus.write_u8(0x7000, 0x42);
+    nes.bus.write_u8(0x7200, 0x24);
+    assert_eq!(nes.bus.read_u8(0x7000), 0x42);
+    assert_eq!(nes.bus.read_u8(0x7400), 0x42);
+    assert_eq!(nes.bus.read_u8(0x7200), 0x00);
+
+    nes.bus.write_u8(0xA001, 0b1110_0000);
+    assert_eq!(nes.bus.read_u8(0x7200), 0x00);
+    nes.bus.write_u8(0x7000, 0x11);
+    assert_eq!(nes.bus.read_u8(0x7000), 0x42);
+}
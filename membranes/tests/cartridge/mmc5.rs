@@ -0,0 +1,248 @@
+use crate::{banked, chr, load, prg};
+use membranes::{cartridge::Cartridge, cpu::Bus as _, Nes};
+
+fn rom() -> Vec<u8> {
+    banked(5, 0, 8, 0x400)
+}
+
+/// Background tile fetches: nametable, attribute and both pattern planes.
+fn tile(cartridge: &mut dyn Cartridge, column: u16, reads: &mut Vec<(u16, u8)>) {
+    let nametable = 0x2000 + column;
+    let attribute = 0x23C0 + column / 4;
+    reads.push((nametable, cartridge.nametable_read(nametable, 0x00)));
+    reads.push((attribute, cartridge.nametable_read(attribute, 0x00)));
+    reads.push((0x0000, cartridge.ppu_read(0x0000)));
+    reads.push((0x0008, cartridge.ppu_read(0x0008)));
+}
+
+/// Performs the PPU reads of one rendered scanline, returning each (address, data).
+/// The last two reads make the next call's first read start a scanline.
+fn scanline(cartridge: &mut dyn Cartridge) -> Vec<(u16, u8)> {
+    let mut reads = Vec::new();
+
+    for column in 2..34 {
+        tile(cartridge, column, &mut reads);
+    }
+    for _ in 0..8 {
+        for address in [0x2000, 0x2000] {
+            reads.push((address, cartridge.nametable_read(address, 0x00)));
+        }
+        for address in [0x1000, 0x1008] {
+            reads.push((address, cartridge.ppu_read(address)));
+        }
+    }
+    for column in 0..2 {
+        tile(cartridge, column, &mut reads);
+    }
+    for _ in 0..2 {
+        reads.push((0x2002, cartridge.nametable_read(0x2002, 0x00)));
+    }
+    reads
+}
+
+/// Enables rendering and runs the pre-render line, so the next line is scanline 0.
+fn start_frame(nes: &mut Nes) {
+    nes.bus.write_u8(0x2001, 0x18);
+    scanline(nes.bus.cartridge.as_mut());
+}
+
+#[test]
+fn prg_modes() {
+    let mut nes = load(&rom());
+    assert_eq!(nes.bus.read_u8(0xE000), 15);
+
+    for (address, bank) in [
+        (0x5114, 0x81),
+        (0x5115, 0x82),
+        (0x5116, 0x83),
+        (0x5117, 0x84),
+    ] {
+        nes.bus.write_u8(address, bank);
+    }
+    assert_eq!(prg(&mut nes), [1, 2, 3, 4]);
+
+    nes.bus.write_u8(0x5100, 2);
+    assert_eq!(prg(&mut nes), [2, 3, 3, 4]);
+    nes.bus.write_u8(0x5100, 1);
+    assert_eq!(prg(&mut nes), [2, 3, 4, 5]);
+    nes.bus.write_u8(0x5100, 0);
+    assert_eq!(prg(&mut nes), [4, 5, 6, 7]);
+}
+
+#[test]
+fn prg_ram() {
+    let mut nes = load(&rom());
+    nes.bus.write_u8(0x6000, 0x42);
+    assert_eq!(nes.bus.read_u8(0x6000), 0x00);
+
+    nes.bus.write_u8(0x5102, 0b10);
+    nes.bus.write_u8(0x5103, 0b01);
+    nes.bus.write_u8(0x6000, 0x42);
+    assert_eq!(nes.bus.read_u8(0x6000), 0x42);
+
+    // RAM can also be banked into $8000-$DFFF.
+    nes.bus.write_u8(0x5114, 0x00);
+    assert_eq!(nes.bus.read_u8(0x8000), 0x42);
+}
+
+#[test]
+fn chr_modes() {
+    let mut nes = load(&rom());
+    for (i, address) in (0x5120..=0x5127).enumerate() {
+        nes.bus.write_u8(address, 8 + i as u8);
+    }
+    assert_eq!(chr(&mut nes), [8, 9, 10, 11, 12, 13, 14, 15]);
+
+    nes.bus.write_u8(0x5101, 2);
+    assert_eq!(chr(&mut nes), [18, 19, 22, 23, 26, 27, 30, 31]);
+
+    nes.bus.write_u8(0x5101, 0);
+    nes.bus.write_u8(0x5127, 1);
+    assert_eq!(chr(&mut nes), [8, 9, 10, 11, 12, 13, 14, 15]);
+}
+
+#[test]
+fn sprite_and_background_sets() {
+    let mut nes = load(&rom());
+    for (i, address) in (0x5120..=0x512B).enumerate() {
+        nes.bus.write_u8(address, 16 + i as u8);
+    }
+    // Outside rendering, the last written set is used.
+    assert_eq!(nes.bus.cartridge.ppu_read(0x1000), 24);
+
+    nes.bus.write_u8(0x2000, 0x20);
+    start_frame(&mut nes);
+    let reads = scanline(nes.bus.cartridge.as_mut());
+
+    assert_eq!(reads[2], (0x0000, 24));
+    assert_eq!(reads[128 + 2], (0x1000, 20));
+}
+
+#[test]
+fn scanline_irq() {
+    let mut nes = load(&rom());
+    nes.bus.write_u8(0x5203, 3);
+    nes.bus.write_u8(0x5204, 0x80);
+    start_frame(&mut nes);
+
+    for _ in 0..3 {
+        scanline(nes.bus.cartridge.as_mut());
+        assert!(!nes.bus.irq());
+    }
+    scanline(nes.bus.cartridge.as_mut());
+    assert!(nes.bus.irq());
+    assert_eq!(nes.bus.read_u8(0x5204), 0xC0);
+    assert!(!nes.bus.irq());
+
+    // The PPU stops reading at vertical blank.
+    nes.bus.ppu.scanline = 241;
+    nes.bus.clock(3);
+    assert_eq!(nes.bus.read_u8(0x5204), 0x00);
+}
+
+#[test]
+fn fill_mode() {
+    let mut nes = load(&rom());
+    nes.bus.write_u8(0x5105, 0xFF);
+    nes.bus.write_u8(0x5106, 0x42);
+    nes.bus.write_u8(0x5107, 0x02);
+
+    let cartridge = &mut nes.bus.cartridge;
+    assert_eq!(cartridge.nametable_read(0x2C10, 0x00), 0x42);
+    assert_eq!(cartridge.nametable_read(0x2FC0, 0x00), 0xAA);
+    assert!(cartridge.nametable_write(0x2C10, 0x00));
+}
+
+#[test]
+fn exram() {
+    let mut nes = load(&rom());
+    nes.bus.write_u8(0x5105, 0b10_10_01_00);
+
+    // As a nametable, ExRAM can only be cleared by the CPU outside rendering.
+    nes.bus.write_u8(0x5C10, 0x42);
+    assert!(nes.bus.cartridge.nametable_write(0x2810, 0x24));
+    assert_eq!(nes.bus.cartridge.nametable_read(0x2810, 0x00), 0x24);
+    assert_eq!(nes.bus.cartridge.nametable_read(0x2C10, 0x00), 0x24);
+    assert!(!nes.bus.cartridge.nametable_write(0x2410, 0x24));
+    assert_eq!(nes.bus.cartridge.ciram_page(1), 1);
+
+    nes.bus.write_u8(0x5104, 2);
+    nes.bus.write_u8(0x5C10, 0x42);
+    assert_eq!(nes.bus.read_u8(0x5C10), 0x42);
+
+    nes.bus.write_u8(0x5104, 3);
+    nes.bus.write_u8(0x5C10, 0x00);
+    assert_eq!(nes.bus.read_u8(0x5C10), 0x42);
+}
+
+#[test]
+fn extended_attributes() {
+    let mut nes = load(&rom());
+    nes.bus.write_u8(0x5104, 2);
+    // Column 2 of the first row: palette 3, 4 KiB CHR bank 1.
+    nes.bus.write_u8(0x5C02, 0xC1);
+    nes.bus.write_u8(0x5104, 1);
+    start_frame(&mut nes);
+
+    let reads = scanline(nes.bus.cartridge.as_mut());
+    assert_eq!(reads[1], (0x23C0, 0xFF));
+    assert_eq!(reads[2], (0x0000, 4));
+}
+
+#[test]
+fn vertical_split() {
+    let mut nes = load(&rom());
+    nes.bus.write_u8(0x5104, 2);
+    nes.bus.write_u8(0x5C00 + 8 * 32 + 2, 0x07);
+    // Row 8 of the split, column 2: attribute row 2, top right quadrant.
+    nes.bus.write_u8(0x5C00 + 0x3C0 + 2 * 8, 0b10 << 2);
+    nes.bus.write_u8(0x5104, 0);
+    // Split the left four columns, scrolled down 64 lines, from 4 KiB CHR bank 1.
+    nes.bus.write_u8(0x5200, 0x80 | 4);
+    nes.bus.write_u8(0x5201, 64);
+    nes.bus.write_u8(0x5202, 1);
+    start_frame(&mut nes);
+
+    let reads = scanline(nes.bus.cartridge.as_mut());
+    assert_eq!(reads[0], (0x2002, 0x07));
+    assert_eq!(reads[1], (0x23C0, 0xAA));
+    assert_eq!(reads[2], (0x0000, 4));
+    // Column 4 is outside the split.
+    assert_eq!(reads[8], (0x2004, 0x00));
+}
+
+#[test]
+fn multiplier() {
+    let mut nes = load(&rom());
+    nes.bus.write_u8(0x5205, 200);
+    nes.bus.write_u8(0x5206, 100);
+    assert_eq!(nes.bus.read_u8(0x5205), (20000 & 0xFF) as u8);
+    assert_eq!(nes.bus.read_u8(0x5206), (20000 >> 8) as u8);
+}
+
+#[test]
+fn audio() {
+    let mut nes = load(&rom());
+    assert_eq!(nes.bus.cartridge.audio(), 0.0);
+
+    nes.bus.write_u8(0x5011, 0xFF);
+    assert!(nes.bus.cartridge.audio() > 0.0);
+    nes.bus.write_u8(0x5011, 0x01);
+
+    // Constant volume 15, 50% duty
+    nes.bus.write_u8(0x5015, 0b01);
+    nes.bus.write_u8(0x5000, 0b1011_1111);
+    nes.bus.write_u8(0x5002, 0x40);
+    nes.bus.write_u8(0x5003, 0x08);
+    assert_eq!(nes.bus.read_u8(0x5015), 0b01);
+
+    let levels: Vec<f32> = (0..1000)
+        .map(|_| {
+            nes.bus.clock(1);
+            nes.bus.cartridge.audio()
+        })
+        .collect();
+    let max = levels.iter().cloned().fold(0.0, f32::max);
+    let min = levels.iter().cloned().fold(1.0, f32::min);
+    assert!(max > min + 0.1, "{min} {max}");
+}
@@ -0,0 +1,177 @@
+use crate::{banked, chr, load, prg};
+use membranes::{audio::PULSE_LEVEL, cpu::Bus as _, Nes};
+
+fn rom(flags_6: u8) -> Vec<u8> {
+    banked(19, flags_6, 2, 0x400)
+}
+
+fn write_sound_ram(nes: &mut Nes, address: u8, data: &[u8]) {
+    nes.bus.write_u8(0xF800, 0x80 | address);
+    for &byte in data {
+        nes.bus.write_u8(0x4800, byte);
+    }
+}
+
+/// Channel 7 playing a wave of full-scale samples at full volume.
+fn start_channel_7(nes: &mut Nes, channels: u8) {
+    write_sound_ram(nes, 0x00, &[0xFF; 8]);
+    write_sound_ram(
+        nes,
+        0x78,
+        &[
+            0x00,
+            0x00,
+            0x00,
+            0x00,
+            0xF0,
+            0x00,
+            0x00,
+            (channels - 1) << 4 | 0x0F,
+        ],
+    );
+}
+
+#[test]
+fn prg_banks() {
+    let mut nes = load(&rom(0));
+    nes.bus.write_u8(0xE000, 3);
+    nes.bus.write_u8(0xE800, 5);
+    nes.bus.write_u8(0xF000, 7);
+    assert_eq!(prg(&mut nes), [3, 5, 7, 15]);
+}
+
+#[test]
+fn chr_banks() {
+    let mut nes = load(&rom(0));
+    for i in 0..8 {
+        nes.bus.write_u8(0x8000 + i * 0x800, 15 - i as u8);
+    }
+    assert_eq!(chr(&mut nes), [15, 14, 13, 12, 11, 10, 9, 8]);
+}
+
+#[test]
+fn nametables() {
+    let mut nes = load(&rom(0));
+    nes.bus.write_u8(0xC000, 0xE0);
+    nes.bus.write_u8(0xC800, 0xE1);
+    nes.bus.write_u8(0xD000, 0xE0);
+    nes.bus.write_u8(0xD800, 3);
+    assert_eq!(nes.bus.cartridge.ciram_page(1), 1);
+
+    assert!(nes.bus.cartridge.nametable_write(0x2000, 0x55));
+    assert_eq!(nes.bus.cartridge.nametable_read(0x2800, 0x00), 0x55);
+    assert_eq!(nes.bus.cartridge.nametable_read(0x2400, 0x00), 0x00);
+
+    // CHR-ROM as a nametable ignores writes.
+    assert!(nes.bus.cartridge.nametable_write(0x2C00, 0x55));
+    assert_eq!(nes.bus.cartridge.nametable_read(0x2C00, 0x00), 3);
+}
+
+#[test]
+fn ciram_as_pattern_table() {
+    let mut nes = load(&rom(0));
+    nes.bus.write_u8(0xC000, 0xE0);
+    nes.bus.write_u8(0x8000, 0xE0);
+    nes.bus.write_u8(0xA000, 0xE1);
+    nes.bus.cartridge.ppu_write(0x0010, 0x66);
+    assert_eq!(nes.bus.cartridge.nametable_read(0x2010, 0x00), 0x66);
+
+    // $E800 bits 6 and 7 make $E0-$FF select CHR-ROM for each pattern table.
+    nes.bus.write_u8(0xE800, 0x40);
+    assert_eq!(nes.bus.cartridge.ppu_read(0x0010), 0xE0 % 16);
+    nes.bus.cartridge.ppu_write(0x1000, 0x77);
+    assert_eq!(nes.bus.cartridge.nametable_read(0x2C00, 0x00), 0x00);
+    nes.bus.write_u8(0xDC00, 0xE1);
+    assert_eq!(nes.bus.cartridge.nametable_read(0x2C00, 0x00), 0x77);
+}
+
+#[test]
+fn prg_ram_write_protect() {
+    let mut nes = load(&rom(0));
+    nes.bus.write_u8(0x6000, 0xAB);
+    assert_eq!(nes.bus.read_u8(0x6000), 0x00);
+
+    nes.bus.write_u8(0xF800, 0x42);
+    nes.bus.write_u8(0x6000, 0xAB);
+    nes.bus.write_u8(0x6800, 0xCD);
+    assert_eq!(nes.bus.read_u8(0x6000), 0xAB);
+    assert_eq!(nes.bus.read_u8(0x6800), 0x00);
+}
+
+#[test]
+fn irq() {
+    let mut nes = load(&rom(0));
+    nes.bus.write_u8(0x5000, 0xFD);
+    nes.bus.write_u8(0x5800, 0xFF);
+    assert_eq!(nes.bus.read_u8(0x5800), 0xFF);
+    nes.bus.clock(1);
+    assert!(!nes.bus.irq());
+    nes.bus.clock(1);
+    assert!(nes.bus.irq());
+
+    // The counter stops at $7FFF.
+    nes.bus.clock(10);
+    assert_eq!(nes.bus.read_u8(0x5000), 0xFF);
+    nes.bus.write_u8(0x5800, 0x00);
+    assert!(!nes.bus.irq());
+}
+
+#[test]
+fn sound_ram_port() {
+    let mut nes = load(&rom(0));
+    write_sound_ram(&mut nes, 0x7E, &[0x12, 0x34, 0x56]);
+    nes.bus.write_u8(0xF800, 0x7E);
+    assert_eq!(nes.bus.read_u8(0x4800), 0x12);
+    assert_eq!(nes.bus.read_u8(0x4800), 0x12);
+    nes.bus.write_u8(0xF800, 0xFF);
+    assert_eq!(nes.bus.read_u8(0x4800), 0x34);
+    assert_eq!(nes.bus.read_u8(0x4800), 0x56);
+}
+
+#[test]
+fn battery_ram() {
+    let nes = load(&rom(0));
+    assert!(nes.battery_ram().is_empty());
+
+    let mut nes = load(&rom(0b10));
+    nes.bus.write_u8(0xF800, 0x40);
+    nes.bus.write_u8(0x7FFF, 0xAB);
+    write_sound_ram(&mut nes, 0x10, &[0xCD]);
+    let saved = nes.battery_ram();
+    assert_eq!(saved.len(), 0x2000 + 0x80);
+
+    let mut nes = load(&rom(0b10));
+    nes.load_battery_ram(&saved);
+    assert_eq!(nes.bus.read_u8(0x7FFF), 0xAB);
+    nes.bus.write_u8(0xF800, 0x10);
+    assert_eq!(nes.bus.read_u8(0x4800), 0xCD);
+}
+
+#[test]
+fn multiplexing() {
+    let mut nes = load(&rom(0));
+    start_channel_7(&mut nes, 1);
+    for _ in 0..8 {
+        nes.bus.clock(15);
+        assert_eq!(nes.bus.cartridge.audio(), 2.0 * PULSE_LEVEL);
+    }
+
+    // With 8 channels, channel 7 only has the DAC for 15 of every 120 cycles.
+    let mut nes = load(&rom(0));
+    start_channel_7(&mut nes, 8);
+    let mut playing = 0;
+    for _ in 0..80 {
+        nes.bus.clock(15);
+        if nes.bus.cartridge.audio() > 0.0 {
+            playing += 1;
+        }
+    }
+    assert_eq!(playing, 10);
+
+    // $E000 bit 6 silences the chip.
+    nes.bus.write_u8(0xE000, 0x40);
+    assert!((0..8).all(|_| {
+        nes.bus.clock(15);
+        nes.bus.cartridge.audio() == 0.0
+    }));
+}
@@ -0,0 +1,80 @@
+use crate::{ines, load};
+use membranes::{cartridge::Mirroring, cpu::Bus as _};
+
+#[test]
+fn prg_rom_16k_is_mirrored() {
+    let mut rom = ines(0, 0, 1, 1);
+    rom[16 + 0x1234] = 0x42;
+    let mut nes = load(&rom);
+
+    assert_eq!(nes.bus.read_u8(0x9234), 0x42);
+    assert_eq!(nes.bus.read_u8(0xD234), 0x42);
+}
+
+#[test]
+fn prg_rom_32k() {
+    let mut nes = load(&ines(0, 0, 2, 1));
+
+    assert_eq!(nes.bus.read_u8(0x8000), 0);
+    assert_eq!(nes.bus.read_u8(0xC000), 1);
+    nes.bus.write_u8(0xC000, 0xFF);
+    assert_eq!(nes.bus.read_u8(0xC000), 1);
+}
+
+#[test]
+fn chr_rom_is_read_only() {
+    let mut nes = load(&ines(0, 0, 1, 1));
+
+    assert_eq!(nes.bus.cartridge.ppu_read(0x1FFF), 0x80);
+    nes.bus.cartridge.ppu_write(0x1FFF, 0x00);
+    assert_eq!(nes.bus.cartridge.ppu_read(0x1FFF), 0x80);
+}
+
+#[test]
+fn chr_ram_without_chr_rom() {
+    let mut nes = load(&ines(0, 0, 1, 0));
+
+    nes.bus.cartridge.ppu_write(0x0ABC, 0x5A);
+    assert_eq!(nes.bus.cartridge.ppu_read(0x0ABC), 0x5A);
+}
+
+#[test]
+fn family_basic_prg_ram() {
+    let mut nes = load(&ines(0, 0, 2, 1));
+
+    nes.bus.write_u8(0x6000, 0x12);
+    nes.bus.write_u8(0x7FFF, 0x34);
+    assert_eq!(nes.bus.read_u8(0x6000), 0x12);
+    assert_eq!(nes.bus.read_u8(0x7FFF), 0x34);
+}
+
+#[test]
+fn mirroring_from_header() {
+    assert_eq!(
+        load(&ines(0, 0b0000, 1, 1)).mirroring(),
+        Mirroring::Horizontal
+    );
+    assert_eq!(
+        load(&ines(0, 0b0001, 1, 1)).mirroring(),
+        Mirroring::Vertical
+    );
+    assert_eq!(
+        load(&ines(0, 0b1000, 1, 1)).mirroring(),
+        Mirroring::FourScreen
+    );
+}
+
+#[test]
+fn unsupported_mapper() {
+    let mut nes = membranes::Nes::new();
+    let err = nes.load(&ines(255, 0, 1, 1)).unwrap_err();
+    assert!(err.contains("255"), "{err}");
+}
+
+#[test]
+fn empty_prg_rom() {
+    for mapper in [0, 1] {
+        let mut nes = membranes::Nes::new();
+        assert!(nes.load(&ines(mapper, 0, 0, 1)).is_err(), "mapper {mapper}");
+    }
+}
@@ -0,0 +1,163 @@
+use crate::{banked, chr, ines, load, prg};
+use membranes::{cartridge::Mirroring, cpu::Bus as _};
+
+/// NES 2.0 image with 8 KiB of PRG-RAM, or none.
+fn rom(mapper: u8, submapper: u8, prg_ram: bool) -> Vec<u8> {
+    let mut rom = banked(mapper, 0, 2, 0x400);
+    rom[7] |= 0x08;
+    rom[8] = submapper << 4;
+    rom[10] = if prg_ram { 7 } else { 0 };
+    rom
+}
+
+#[test]
+fn wiring() {
+    // (mapper, submapper, A0, A1)
+    let boards = [
+        (21, 1, 0x02, 0x04),
+        (21, 2, 0x40, 0x80),
+        (21, 0, 0x40, 0x80),
+        (22, 0, 0x02, 0x01),
+        (23, 1, 0x01, 0x02),
+        (23, 2, 0x04, 0x08),
+        (23, 3, 0x01, 0x02),
+        (23, 0, 0x04, 0x08),
+        (25, 1, 0x02, 0x01),
+        (25, 2, 0x08, 0x04),
+        (25, 3, 0x02, 0x01),
+        (25, 0, 0x08, 0x04),
+    ];
+    for (mapper, submapper, a0, a1) in boards {
+        let mut nes = load(&rom(mapper, submapper, true));
+        nes.bus.write_u8(0x8000, 3);
+        nes.bus.write_u8(0xA000 | a0 | a1, 5);
+        assert_eq!(prg(&mut nes), [3, 5, 14, 15], "mapper {mapper}.{submapper}");
+
+        // Bank 3 is the high half of $C000.
+        nes.bus.write_u8(0xC000 | a1, 4);
+        nes.bus.write_u8(0xC000 | a1 | a0, 0);
+        let bank = if mapper == 22 { 2 } else { 4 };
+        assert_eq!(chr(&mut nes)[3], bank, "mapper {mapper}.{submapper}");
+    }
+}
+
+#[test]
+fn prg_swap_mode() {
+    let mut nes = load(&rom(23, 1, true));
+    nes.bus.write_u8(0x8000, 3);
+    nes.bus.write_u8(0x9002, 0b10);
+    assert_eq!(prg(&mut nes), [14, 1, 3, 15]);
+
+    // VRC2 has no swap mode, $9002 is mirroring there.
+    let mut nes = load(&rom(23, 3, true));
+    nes.bus.write_u8(0x8000, 3);
+    nes.bus.write_u8(0x9002, 0b11);
+    assert_eq!(prg(&mut nes), [3, 1, 14, 15]);
+    assert_eq!(nes.mirroring(), Mirroring::Horizontal);
+}
+
+#[test]
+fn mirroring() {
+    let mut nes = load(&rom(25, 1, true));
+    for (data, mirroring) in [
+        (0, Mirroring::Vertical),
+        (1, Mirroring::Horizontal),
+        (2, Mirroring::SingleScreenLower),
+        (3, Mirroring::SingleScreenUpper),
+    ] {
+        nes.bus.write_u8(0x9000, data);
+        assert_eq!(nes.mirroring(), mirroring);
+    }
+}
+
+#[test]
+fn irq_cycle_mode() {
+    let mut nes = load(&rom(23, 1, true));
+    nes.bus.write_u8(0xF000, 0x0D);
+    nes.bus.write_u8(0xF001, 0x0F);
+    nes.bus.write_u8(0xF002, 0b111);
+    nes.bus.clock(2);
+    assert!(!nes.bus.irq());
+    nes.bus.clock(1);
+    assert!(nes.bus.irq());
+
+    // Acknowledging keeps counting, as the control write set "enable after acknowledge".
+    nes.bus.write_u8(0xF003, 0);
+    assert!(!nes.bus.irq());
+    nes.bus.clock(3);
+    assert!(nes.bus.irq());
+}
+
+#[test]
+fn irq_scanline_mode() {
+    let mut nes = load(&rom(23, 1, true));
+    nes.bus.write_u8(0xF000, 0x0E);
+    nes.bus.write_u8(0xF001, 0x0F);
+    nes.bus.write_u8(0xF002, 0b010);
+    // Two scanlines of 341 dots at 3 dots per CPU cycle.
+    nes.bus.clock(227);
+    assert!(!nes.bus.irq());
+    nes.bus.clock(1);
+    assert!(nes.bus.irq());
+
+    nes.bus.write_u8(0xF003, 0);
+    nes.bus.clock(255);
+    assert!(!nes.bus.irq());
+}
+
+#[test]
+fn microwire_latch() {
+    let mut nes = load(&rom(23, 3, false));
+    nes.bus.write_u8(0x6000, 0xFF);
+    assert_eq!(nes.bus.read_u8(0x6000), 1);
+    nes.bus.write_u8(0x6000, 0xFE);
+    assert_eq!(nes.bus.read_u8(0x6000), 0);
+
+    let mut nes = load(&rom(23, 3, true));
+    nes.bus.write_u8(0x7FFF, 0xAB);
+    assert_eq!(nes.bus.read_u8(0x7FFF), 0xAB);
+}
+
+#[test]
+fn microwire_latch_ines_v1() {
+    // iNES 1.0 always reports 8 KiB of PRG-RAM, which VRC2 boards rarely have.
+    for mapper in [22, 23] {
+        let mut nes = load(&ines(mapper, 0, 8, 2));
+        nes.bus.write_u8(0x6000, 0xFF);
+        assert_eq!(nes.bus.read_u8(0x6000), 1, "mapper {mapper}");
+        nes.bus.write_u8(0x6000, 0xFE);
+        assert_eq!(nes.bus.read_u8(0x6000), 0, "mapper {mapper}");
+    }
+
+    // A battery means the RAM is real, and VRC4 gets it back.
+    let mut nes = load(&ines(23, 0x02, 8, 2));
+    nes.bus.write_u8(0x7FFF, 0xAB);
+    assert_eq!(nes.bus.read_u8(0x7FFF), 0xAB);
+
+    let mut nes = load(&ines(23, 0, 8, 2));
+    nes.bus.write_u8(0xF000, 0x00);
+    nes.bus.write_u8(0x7FFF, 0xAB);
+    assert_eq!(nes.bus.read_u8(0x7FFF), 0xAB);
+}
+
+#[test]
+fn mapper_23_chip_detection() {
+    // VRC2b: 1-bit mirroring and the microwire latch.
+    let mut nes = load(&rom(23, 0, false));
+    nes.bus.write_u8(0x9000, 0x03);
+    assert_eq!(nes.mirroring(), Mirroring::Horizontal);
+    nes.bus.write_u8(0x6000, 0x01);
+    assert_eq!(nes.bus.read_u8(0x6000), 1);
+
+    // VRC4e gives itself away through A2/A3 or the IRQ registers.
+    for address in [0x9008, 0xF000] {
+        let mut nes = load(&rom(23, 0, false));
+        nes.bus.write_u8(address, 0x00);
+        nes.bus.write_u8(0x9000, 0x03);
+        assert_eq!(
+            nes.mirroring(),
+            Mirroring::SingleScreenUpper,
+            "{address:04X}"
+        );
+    }
+}
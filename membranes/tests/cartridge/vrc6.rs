@@ -0,0 +1,137 @@
+use crate::{banked, chr, load, prg};
+use membranes::{audio::PULSE_LEVEL, cartridge::Mirroring, cpu::Bus as _};
+
+fn rom(mapper: u8) -> Vec<u8> {
+    banked(mapper, 0, 2, 0x400)
+}
+
+#[test]
+fn prg_banks() {
+    let mut nes = load(&rom(24));
+    nes.bus.write_u8(0x8000, 2);
+    nes.bus.write_u8(0xC000, 9);
+    assert_eq!(prg(&mut nes), [4, 5, 9, 15]);
+}
+
+#[test]
+fn chr_modes() {
+    let mut nes = load(&rom(24));
+    for (i, address) in [
+        0xD000, 0xD001, 0xD002, 0xD003, 0xE000, 0xE001, 0xE002, 0xE003,
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        nes.bus.write_u8(address, 8 - i as u8);
+    }
+    nes.bus.write_u8(0xB003, 0x20);
+    assert_eq!(chr(&mut nes), [8, 7, 6, 5, 4, 3, 2, 1]);
+
+    nes.bus.write_u8(0xB003, 0x21);
+    assert_eq!(chr(&mut nes), [0, 1, 14, 15, 12, 13, 10, 11]);
+
+    nes.bus.write_u8(0xB003, 0x22);
+    assert_eq!(chr(&mut nes), [8, 7, 6, 5, 8, 9, 6, 7]);
+}
+
+#[test]
+fn swapped_lines() {
+    let mut nes = load(&rom(26));
+    nes.bus.write_u8(0xD001, 3);
+    nes.bus.write_u8(0xD002, 4);
+    nes.bus.write_u8(0xB003, 0x20);
+    assert_eq!(chr(&mut nes)[..3], [0, 4, 3]);
+
+    // $B003 is unaffected.
+    nes.bus.write_u8(0xB003, 0x24);
+    assert_eq!(nes.mirroring(), Mirroring::Horizontal);
+}
+
+#[test]
+fn mirroring() {
+    let mut nes = load(&rom(24));
+    for (data, mirroring) in [
+        (0x20, Mirroring::Vertical),
+        (0x24, Mirroring::Horizontal),
+        (0x28, Mirroring::SingleScreenLower),
+        (0x2C, Mirroring::SingleScreenUpper),
+    ] {
+        nes.bus.write_u8(0xB003, data);
+        assert_eq!(nes.mirroring(), mirroring);
+    }
+}
+
+#[test]
+fn prg_ram_enable() {
+    let mut nes = load(&rom(24));
+    nes.bus.write_u8(0x6000, 0xAB);
+    assert_eq!(nes.bus.read_u8(0x6000), 0x00);
+
+    nes.bus.write_u8(0xB003, 0xA0);
+    nes.bus.write_u8(0x6000, 0xAB);
+    assert_eq!(nes.bus.read_u8(0x6000), 0xAB);
+}
+
+#[test]
+fn irq() {
+    let mut nes = load(&rom(24));
+    nes.bus.write_u8(0xF000, 0xFE);
+    nes.bus.write_u8(0xF001, 0b110);
+    nes.bus.clock(1);
+    assert!(!nes.bus.irq());
+    nes.bus.clock(1);
+    assert!(nes.bus.irq());
+
+    // Disabled after acknowledging, as "enable after acknowledge" was clear.
+    nes.bus.write_u8(0xF002, 0);
+    assert!(!nes.bus.irq());
+    nes.bus.clock(10);
+    assert!(!nes.bus.irq());
+}
+
+#[test]
+fn pulse() {
+    let mut nes = load(&rom(24));
+    // Duty 8/16, period 1: two cycles per step.
+    nes.bus.write_u8(0x9000, 0x7F);
+    nes.bus.write_u8(0x9001, 1);
+    nes.bus.write_u8(0x9002, 0x80);
+
+    let mut levels = Vec::new();
+    for _ in 0..16 {
+        nes.bus.clock(2);
+        levels.push(nes.bus.cartridge.audio());
+    }
+    assert_eq!(
+        levels.iter().filter(|&&level| level == PULSE_LEVEL).count(),
+        8
+    );
+    assert_eq!(levels.iter().filter(|&&level| level == 0.0).count(), 8);
+
+    // Digitized mode ignores the duty.
+    nes.bus.write_u8(0x9000, 0x8F);
+    for _ in 0..16 {
+        nes.bus.clock(2);
+        assert_eq!(nes.bus.cartridge.audio(), PULSE_LEVEL);
+    }
+}
+
+#[test]
+fn sawtooth() {
+    let mut nes = load(&rom(24));
+    nes.bus.write_u8(0xB000, 0x2A);
+    nes.bus.write_u8(0xB001, 0);
+    nes.bus.write_u8(0xB002, 0x80);
+
+    let mut levels = Vec::new();
+    for _ in 0..14 {
+        levels.push(nes.bus.cartridge.audio() / (PULSE_LEVEL / 15.0));
+        nes.bus.clock(1);
+    }
+    let levels: Vec<u8> = levels
+        .into_iter()
+        .map(|level| level.round() as u8)
+        .collect();
+    assert_eq!(levels, [0, 0, 5, 5, 10, 10, 15, 15, 21, 21, 26, 26, 31, 31]);
+    assert_eq!(nes.bus.cartridge.audio(), 0.0);
+}
@@ -0,0 +1,143 @@
+use crate::{banked, chr, load, prg};
+use membranes::{cartridge::Mirroring, cpu::Bus as _, Nes};
+
+/// NES 2.0 image with 8 KiB of PRG-RAM.
+fn rom(submapper: u8) -> Vec<u8> {
+    let mut rom = banked(85, 0, 2, 0x400);
+    rom[7] |= 0x08;
+    rom[8] = submapper << 4;
+    rom[10] = 7;
+    rom
+}
+
+fn write_audio(nes: &mut Nes, register: u8, data: u8) {
+    nes.bus.write_u8(0x9010, register);
+    nes.bus.write_u8(0x9030, data);
+}
+
+/// Sign changes of the audio output over one second.
+fn zero_crossings(nes: &mut Nes) -> usize {
+    let mut crossings = 0;
+    let mut positive = nes.bus.cartridge.audio() > 0.0;
+    for _ in 0..1_789_773 / 36 {
+        nes.bus.clock(36);
+        let level = nes.bus.cartridge.audio();
+        if level != 0.0 && (level > 0.0) != positive {
+            positive = level > 0.0;
+            crossings += 1;
+        }
+    }
+    crossings
+}
+
+#[test]
+fn banks() {
+    for (submapper, line) in [(1, 0x08), (2, 0x10), (0, 0x08), (0, 0x10)] {
+        let mut nes = load(&rom(submapper));
+        nes.bus.write_u8(0x8000, 3);
+        nes.bus.write_u8(0x8000 | line, 5);
+        nes.bus.write_u8(0x9000, 7);
+        assert_eq!(prg(&mut nes), [3, 5, 7, 15]);
+
+        for (i, address) in [0xA000, 0xB000, 0xC000, 0xD000].into_iter().enumerate() {
+            nes.bus.write_u8(address, 15 - 2 * i as u8);
+            nes.bus.write_u8(address | line, 14 - 2 * i as u8);
+        }
+        assert_eq!(chr(&mut nes), [15, 14, 13, 12, 11, 10, 9, 8]);
+    }
+}
+
+#[test]
+fn control() {
+    let mut nes = load(&rom(1));
+    for (data, mirroring) in [
+        (0, Mirroring::Vertical),
+        (1, Mirroring::Horizontal),
+        (2, Mirroring::SingleScreenLower),
+        (3, Mirroring::SingleScreenUpper),
+    ] {
+        nes.bus.write_u8(0xE000, data);
+        assert_eq!(nes.mirroring(), mirroring);
+    }
+
+    nes.bus.write_u8(0x6000, 0xAB);
+    assert_eq!(nes.bus.read_u8(0x6000), 0x00);
+    nes.bus.write_u8(0xE000, 0x80);
+    nes.bus.write_u8(0x6000, 0xAB);
+    assert_eq!(nes.bus.read_u8(0x6000), 0xAB);
+}
+
+#[test]
+fn irq() {
+    let mut nes = load(&rom(2));
+    nes.bus.write_u8(0xE010, 0xFE);
+    nes.bus.write_u8(0xF000, 0b111);
+    nes.bus.clock(1);
+    assert!(!nes.bus.irq());
+    nes.bus.clock(1);
+    assert!(nes.bus.irq());
+    nes.bus.write_u8(0xF010, 0);
+    assert!(!nes.bus.irq());
+}
+
+#[test]
+fn custom_instrument_pitch() {
+    let mut nes = load(&rom(1));
+    // Silent modulator into a sustained, instant attack carrier.
+    for (register, data) in [0x20, 0x21, 0x3F, 0x00, 0x00, 0xF0, 0x00, 0x00]
+        .into_iter()
+        .enumerate()
+    {
+        write_audio(&mut nes, register as u8, data);
+    }
+    // 440 Hz: F-number 290 at block 4.
+    write_audio(&mut nes, 0x30, 0x00);
+    write_audio(&mut nes, 0x10, 0x22);
+    write_audio(&mut nes, 0x20, 0x10 | 4 << 1 | 1);
+
+    let crossings = zero_crossings(&mut nes);
+    assert!((878..=882).contains(&crossings), "{crossings}");
+}
+
+#[test]
+fn key_off_release() {
+    let mut nes = load(&rom(1));
+    write_audio(&mut nes, 0x30, 0x30);
+    write_audio(&mut nes, 0x10, 0xAC);
+    write_audio(&mut nes, 0x20, 0x10 | 4 << 1);
+    nes.bus.clock(255);
+    nes.bus.clock(255);
+    let playing = (0..100).any(|_| {
+        nes.bus.clock(36);
+        nes.bus.cartridge.audio() != 0.0
+    });
+    assert!(playing);
+
+    write_audio(&mut nes, 0x20, 4 << 1);
+    for _ in 0..8000 {
+        nes.bus.clock(255);
+    }
+    let silent = (0..100).all(|_| {
+        nes.bus.clock(36);
+        nes.bus.cartridge.audio() == 0.0
+    });
+    assert!(silent);
+}
+
+#[test]
+fn audio_reset() {
+    let mut nes = load(&rom(1));
+    write_audio(&mut nes, 0x30, 0x30);
+    write_audio(&mut nes, 0x10, 0xAC);
+    write_audio(&mut nes, 0x20, 0x10 | 4 << 1);
+    nes.bus.write_u8(0xE000, 0x40);
+    nes.bus.clock(255);
+    assert_eq!(nes.bus.cartridge.audio(), 0.0);
+
+    // Clearing the bit doesn't restore the notes.
+    nes.bus.write_u8(0xE000, 0x00);
+    for _ in 0..10 {
+        nes.bus.clock(255);
+        assert_eq!(nes.bus.cartridge.audio(), 0.0);
+    }
+}
@@ -0,0 +1,117 @@
+use membranes::{cartridge::fds::FdsError, cpu::Bus as _, Nes};
+
+/// Minimal stand-in for the FDS BIOS: `program` at $E000 plus the interrupt vectors.
+fn bios(program: &[u8], irq_handler: &[u8]) -> Vec<u8> {
+    let mut bios = vec![0x00; 0x2000];
+    bios[..program.len()].copy_from_slice(program);
+    bios[0x1000..(0x1000 + irq_handler.len())].copy_from_slice(irq_handler);
+    // NMI $E000, reset $E000, IRQ $F000
+    bios[0x1FFA..].copy_from_slice(&[0x00, 0xE0, 0x00, 0xE0, 0x00, 0xF0]);
+    bios
+}
+
+fn disk() -> Vec<u8> {
+    let mut side = vec![0x01];
+    side.extend(b"*NINTENDO-HVC*");
+    side.resize(56, 0x00);
+    side.extend([0x02, 0x00]);
+    side.resize(65500, 0x00);
+    [side.clone(), side].concat()
+}
+
+fn run(nes: &mut Nes, instructions: usize) {
+    for _ in 0..instructions {
+        nes.tick();
+    }
+}
+
+#[test]
+fn missing_bios() {
+    let mut nes = Nes::new();
+    let err = nes.load_fds(&disk(), &[]).unwrap_err();
+    assert!(err.contains("BIOS"), "{err}");
+    assert!(nes.load_fds(&disk(), &[0x00; 0x1000]).is_err());
+}
+
+#[test]
+fn timer_irq() {
+    let program = [
+        // Enable disk registers, reload $0100, enable timer IRQ with repeat
+        0xA9, 0x01, 0x8D, 0x23, 0x40, // LDA #$01; STA $4023
+        0xA9, 0x00, 0x8D, 0x20, 0x40, // LDA #$00; STA $4020
+        0xA9, 0x01, 0x8D, 0x21, 0x40, // LDA #$01; STA $4021
+        0xA9, 0x03, 0x8D, 0x22, 0x40, // LDA #$03; STA $4022
+        0x58, // CLI
+        0x4C, 0x15, 0xE0, // JMP *
+    ];
+    let irq_handler = [
+        0xE6, 0x00, // INC $00
+        0xAD, 0x30, 0x40, // LDA $4030
+        0x40, // RTI
+    ];
+    let mut nes = Nes::new();
+    nes.load_fds(&disk(), &bios(&program, &irq_handler))
+        .unwrap();
+    nes.reset();
+
+    run(&mut nes, 1000);
+
+    // ~3000 cycles at 257 cycles per interrupt
+    let count = nes.bus.read_u8(0x00);
+    assert!((8..=12).contains(&count), "{count}");
+}
+
+#[test]
+fn disk_read() {
+    let program = [
+        0xA9, 0x01, 0x8D, 0x23, 0x40, // LDA #$01; STA $4023
+        0xA9, 0x65, 0x8D, 0x25, 0x40, // LDA #$65; STA $4025 (motor on, read, transfer)
+        0xAD, 0x30, 0x40, // wait: LDA $4030
+        0x29, 0x02, // AND #$02
+        0xF0, 0xF9, // BEQ wait
+        0xAD, 0x31, 0x40, // LDA $4031
+        0x85, 0x00, // STA $00
+        0xAD, 0x30, 0x40, // wait: LDA $4030
+        0x29, 0x02, // AND #$02
+        0xF0, 0xF9, // BEQ wait
+        0xAD, 0x31, 0x40, // LDA $4031
+        0x85, 0x01, // STA $01
+        0x4C, 0x22, 0xE0, // JMP *
+    ];
+    let mut nes = Nes::new();
+    nes.load_fds(&disk(), &bios(&program, &[])).unwrap();
+    nes.reset();
+
+    run(&mut nes, 300_000);
+
+    assert_eq!(nes.bus.read_u8(0x00), 0x01);
+    assert_eq!(nes.bus.read_u8(0x01), b'*');
+}
+
+#[test]
+fn side_switching() {
+    let mut nes = Nes::new();
+    nes.load_fds(&disk(), &bios(&[0x4C, 0x00, 0xE0], &[]))
+        .unwrap();
+    nes.reset();
+    nes.bus.write_u8(0x4023, 0x01);
+
+    assert_eq!(nes.disk_side_count(), 2);
+    assert_eq!(nes.bus.read_u8(0x4032) & 0b1, 0);
+
+    nes.eject_disk();
+    assert_eq!(nes.bus.read_u8(0x4032) & 0b1, 1);
+
+    assert!(nes.insert_disk(2).is_err());
+    assert!(matches!(
+        nes.fds_mut().unwrap().insert(2),
+        Err(FdsError::NoSuchSide(2))
+    ));
+    assert_eq!(nes.fds_mut().unwrap().side(), None);
+    nes.insert_disk(1).unwrap();
+    assert_eq!(nes.fds_mut().unwrap().side(), Some(1));
+    // Still reads as ejected until the drive notices the new disk.
+    assert_eq!(nes.bus.read_u8(0x4032) & 0b1, 1);
+    run(&mut nes, 300_000);
+    assert_eq!(nes.bus.read_u8(0x4032) & 0b1, 0);
+}
@@ -0,0 +1,86 @@
+use membranes::{audio, cpu::Bus as _, nsf::NsfPlayer, ppu::Region};
+
+fn nsf(bankswitch: [u8; 8], data: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![0x00; 0x80];
+    bytes[..5].copy_from_slice(b"NESM\x1A");
+    bytes[0x05] = 0x01;
+    bytes[0x06] = 4;
+    bytes[0x07] = 1;
+    // load $8000, init $8000, play $8003
+    bytes[0x08..0x0E].copy_from_slice(&[0x00, 0x80, 0x00, 0x80, 0x03, 0x80]);
+    bytes[0x6E..0x70].copy_from_slice(&16639u16.to_le_bytes());
+    bytes[0x70..0x78].copy_from_slice(&bankswitch);
+    bytes.extend(data);
+    bytes
+}
+
+#[test]
+fn init_and_play() {
+    let program = [
+        // INIT: STA $00; RTS
+        0x85, 0x00, 0x60, // PLAY: INC $01; LDA #$0F; STA $4015; RTS
+        0xE6, 0x01, 0xA9, 0x0F, 0x8D, 0x15, 0x40, 0x60,
+    ];
+    let mut player = NsfPlayer::new(&nsf([0; 8], &program)).unwrap();
+
+    assert_eq!(player.track(), 0);
+    assert_eq!(player.track_count(), 4);
+
+    player.select_track(2);
+    assert_eq!(player.bus.read_u8(0x00), 2);
+
+    let start = player.cycles();
+    for _ in 0..3 {
+        player.play();
+    }
+    assert_eq!(player.bus.read_u8(0x01), 3);
+    assert_eq!(player.cycles() - start, 3 * player.play_period());
+
+    let writes = player.take_apu_writes();
+    assert_eq!(writes.len(), 3);
+    assert!(writes.iter().all(|w| w.address == 0x4015 && w.data == 0x0F));
+    assert!(writes[1].cycle >= writes[0].cycle + player.play_period());
+}
+
+#[test]
+fn bankswitching() {
+    let mut data = vec![0x00; 0x3000];
+    // INIT: LDA #$02; STA $5FF9; LDA $9000; STA $02; RTS
+    data[..12].copy_from_slice(&[
+        0xA9, 0x02, 0x8D, 0xF9, 0x5F, 0xAD, 0x00, 0x90, 0x85, 0x02, 0x60, 0x60,
+    ]);
+    data[0x2000] = 0x42;
+    let mut player = NsfPlayer::new(&nsf([0, 1, 2, 3, 4, 5, 6, 7], &data)).unwrap();
+
+    assert_eq!(player.bus.read_u8(0x02), 0x42);
+    assert_eq!(player.bus.banks[1], 2);
+}
+
+#[test]
+fn region() {
+    // INIT: STX $00; RTS
+    let mut bytes = nsf([0; 8], &[0x86, 0x00, 0x60, 0x60]);
+    bytes[0x78..0x7A].copy_from_slice(&19997u16.to_le_bytes());
+    let mut player = NsfPlayer::new(&bytes).unwrap();
+    assert_eq!(player.region(), Region::Ntsc);
+    assert_eq!(player.bus.read_u8(0x00), 0);
+
+    for (region, speed, pal) in [
+        (Region::Pal, 19997, 1),
+        (Region::Dendy, 19997, 0),
+        (Region::Ntsc, 16639, 0),
+    ] {
+        player.set_region(region);
+        let clock = u64::from(audio::cpu_clock(region));
+        assert_eq!(
+            player.play_period(),
+            speed * clock / 1_000_000,
+            "{region:?}"
+        );
+        assert_eq!(player.bus.read_u8(0x00), pal, "{region:?}");
+    }
+
+    bytes[0x7A] = 0x01;
+    let player = NsfPlayer::new(&bytes).unwrap();
+    assert_eq!(player.region(), Region::Pal);
+}
@@ -0,0 +1,357 @@
+use membranes::{
+    apu,
+    cartridge::Mirroring,
+    cpu::Bus as _,
+    ppu::{self, Ctrl, Region, Status},
+    scheduler::{self, Scheduler},
+    Nes,
+};
+
+/// NROM image with CHR-RAM.
+fn nes() -> Nes {
+    let mut rom = vec![b'N', b'E', b'S', 0x1A, 1, 0, 0x00, 0x00];
+    rom.resize(16 + 0x4000, 0x00);
+    let mut nes = Nes::new();
+    nes.load(&rom).unwrap();
+    nes
+}
+
+#[test]
+fn registers_mirrored() {
+    let mut nes = nes();
+    nes.bus.write_u8(0x3FFE, 0x00);
+    nes.bus.write_u8(0x200E, 0x10);
+    nes.bus.write_u8(0x3457, 0xAB);
+    assert_eq!(nes.bus.ppu.v, 0x0011);
+}
+
+#[test]
+fn pattern_tables_on_cartridge() {
+    let mut nes = nes();
+    nes.bus.write_u8(0x2006, 0x00);
+    nes.bus.write_u8(0x2006, 0x10);
+    nes.bus.write_u8(0x2007, 0xAB);
+    assert_eq!(nes.bus.cartridge.ppu_read(0x0010), 0xAB);
+
+    nes.bus.write_u8(0x2006, 0x00);
+    nes.bus.write_u8(0x2006, 0x10);
+    nes.bus.read_u8(0x2007);
+    assert_eq!(nes.bus.read_u8(0x2007), 0xAB);
+}
+
+fn set_address(nes: &mut Nes, address: u16) {
+    let [high, low] = address.to_be_bytes();
+    nes.bus.write_u8(0x2006, high);
+    nes.bus.write_u8(0x2006, low);
+}
+
+#[test]
+fn nametables_follow_mirroring() {
+    let mut nes = nes();
+    assert_eq!(nes.mirroring(), Mirroring::Horizontal);
+    set_address(&mut nes, 0x2803);
+    nes.bus.write_u8(0x2007, 0x5A);
+    assert_eq!(nes.bus.ciram[0x403], 0x5A);
+
+    set_address(&mut nes, 0x2C03);
+    nes.bus.read_u8(0x2007);
+    assert_eq!(nes.bus.read_u8(0x2007), 0x5A);
+}
+
+#[test]
+fn palette_ram() {
+    let mut nes = nes();
+    set_address(&mut nes, 0x3F10);
+    nes.bus.write_u8(0x2007, 0x2C);
+    assert_eq!(nes.bus.ppu.palette[0x00], 0x2C);
+
+    set_address(&mut nes, 0x3F00);
+    assert_eq!(nes.bus.read_u8(0x2007), 0x2C);
+}
+
+#[test]
+fn three_dots_per_cpu_cycle() {
+    let mut nes = nes();
+    nes.bus.clock(7);
+    assert_eq!((nes.bus.ppu.scanline, nes.bus.ppu.dot), (0, 21));
+    nes.bus.clock(114);
+    assert_eq!((nes.bus.ppu.scanline, nes.bus.ppu.dot), (1, 22));
+}
+
+#[test]
+fn framebuffer_shows_backdrop() {
+    let mut nes = nes();
+    set_address(&mut nes, 0x3F00);
+    nes.bus.write_u8(0x2007, 0x11);
+    set_address(&mut nes, 0x2000);
+    // A little over a frame.
+    for _ in 0..300 {
+        nes.bus.clock(100);
+    }
+    let framebuffer =
+        unsafe { std::slice::from_raw_parts(nes.framebuffer(), ppu::WIDTH * ppu::HEIGHT) };
+    assert!(framebuffer.iter().all(|&color| color == 0x11));
+}
+
+#[test]
+fn oam_dma() {
+    let mut nes = nes();
+    for (i, byte) in nes.bus.ram[0x200..0x300].iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+    nes.bus.clock(1);
+    nes.bus.write_u8(0x2003, 0x10);
+    nes.bus.write_u8(0x4014, 0x02);
+    assert_eq!(nes.bus.run_oam_dma(), 513);
+    // The copy starts at the OAM address and wraps around.
+    assert_eq!(nes.bus.ppu.oam[0x10..0x13], [0x00, 0x01, 0x02]);
+    assert_eq!(nes.bus.ppu.oam[0x0F], 0xFF);
+    assert_eq!(nes.bus.ppu.oam_address, 0x10);
+    assert_eq!(nes.bus.run_oam_dma(), 0);
+}
+
+#[test]
+fn oam_dma_alignment() {
+    let mut nes = nes();
+    // Halted on an even cycle, the first read would land on an odd one.
+    nes.bus.write_u8(0x4014, 0x00);
+    assert_eq!(nes.bus.run_oam_dma(), 514);
+    assert_eq!(nes.bus.cycles, 514);
+}
+
+#[test]
+fn oam_dma_after_instruction() {
+    let mut nes = nes();
+    // STA $4014 with A = 0.
+    nes.bus.ram[0x0300] = 0x8D;
+    nes.bus.ram[0x0301] = 0x14;
+    nes.bus.ram[0x0302] = 0x40;
+    nes.bus.ram[0x0010] = 0xAB;
+    nes.cpu.regs.pc = 0x0300;
+    nes.tick();
+    assert_eq!(nes.bus.ppu.oam[0x10], 0xAB);
+    assert_eq!(nes.bus.cycles, 4 + 514);
+}
+
+/// LDA $2002 at $0300, started with the PPU at `dot` of scanline 240.
+fn read_status_from(dot: u16) -> Nes {
+    let mut nes = nes();
+    nes.bus.ram[0x0300..0x0303].copy_from_slice(&[0xAD, 0x02, 0x20]);
+    nes.cpu.regs.pc = 0x0300;
+    nes.bus.ppu.ctrl = Ctrl::NMI;
+    while (nes.bus.ppu.scanline, nes.bus.ppu.dot) != (240, dot) {
+        nes.bus.clock(1);
+    }
+    nes.tick();
+    nes
+}
+
+#[test]
+fn status_read_mid_instruction() {
+    // The read lands 3 cycles in, on 241/1, as vblank starts.
+    let mut nes = read_status_from(333);
+    assert_eq!(nes.cpu.regs.a & 0x80, 0);
+    assert!(!nes.bus.ppu.status.contains(Status::VBLANK));
+    assert!(!nes.bus.ppu.take_nmi());
+
+    // A cycle later it sees vblank.
+    let mut nes = read_status_from(336);
+    assert_eq!(nes.cpu.regs.a & 0x80, 0x80);
+    assert!(nes.bus.ppu.take_nmi());
+}
+
+/// Starts a one byte DMC sample from $C000.
+fn start_dmc(nes: &mut Nes) {
+    nes.bus.write_u8(0x4012, 0x00);
+    nes.bus.write_u8(0x4013, 0x00);
+    nes.bus.write_u8(0x4015, 0x10);
+}
+
+#[test]
+fn dmc_dma_during_oam_dma() {
+    let mut nes = nes();
+    nes.bus.ram[0x0200] = 0x11;
+    nes.bus.clock(1);
+    nes.bus.write_u8(0x4014, 0x02);
+    start_dmc(&mut nes);
+    assert_eq!(nes.bus.run_oam_dma(), 513 + 2);
+    assert_eq!(nes.bus.read_u8(0x4015) & 0x10, 0);
+    assert_eq!(nes.bus.ppu.oam[0], 0x11);
+    assert_eq!(nes.bus.run_dmc_dma(), 0);
+}
+
+#[test]
+fn dmc_dma() {
+    let mut nes = nes();
+    assert_eq!(nes.bus.run_dmc_dma(), 0);
+    start_dmc(&mut nes);
+    assert_eq!(nes.bus.read_u8(0x4015) & 0x10, 0x10);
+    assert_eq!(nes.bus.run_dmc_dma(), 3);
+    assert_eq!(nes.bus.read_u8(0x4015) & 0x10, 0);
+}
+
+#[test]
+fn dmc_dma_alignment() {
+    // Halted on an odd cycle, the read needs an alignment cycle.
+    let mut nes = nes();
+    nes.bus.clock(1);
+    start_dmc(&mut nes);
+    assert_eq!(nes.bus.run_dmc_dma(), 4);
+    assert_eq!(nes.bus.cycles, 5);
+}
+
+/// NROM image whose reset code enables NMI and spins, and whose NMI handler
+/// counts frames at $0010.
+fn nmi_counter() -> Nes {
+    let mut rom = vec![b'N', b'E', b'S', 0x1A, 1, 0, 0x00, 0x00];
+    rom.resize(16 + 0x4000, 0x00);
+    let prg = &mut rom[16..];
+    // LDA #$80, STA $2000, JMP $8005
+    prg[..8].copy_from_slice(&[0xA9, 0x80, 0x8D, 0x00, 0x20, 0x4C, 0x05, 0x80]);
+    // INC $10, RTI
+    prg[0x10..0x13].copy_from_slice(&[0xE6, 0x10, 0x40]);
+    prg[0x3FFA..].copy_from_slice(&[0x10, 0x80, 0x00, 0x80, 0x00, 0x80]);
+    let mut nes = Nes::new();
+    nes.load(&rom).unwrap();
+    nes.reset();
+    nes
+}
+
+#[test]
+fn nmi_every_frame() {
+    let mut nes = nmi_counter();
+    nes.run_frame();
+    assert_eq!(nes.bus.ram[0x10], 1);
+    for _ in 0..10 {
+        nes.run_frame();
+    }
+    assert_eq!(nes.bus.ram[0x10], 11);
+}
+
+#[test]
+fn frame_length() {
+    let mut nes = nmi_counter();
+    // 341 x 262 dots at 3 per cycle, give or take an instruction.
+    let cycles = cycles_per_frame(&mut nes);
+    assert!((cycles - 29780.7).abs() < 2.0, "{cycles}");
+}
+
+/// CPU cycles per frame, averaged over 10.
+fn cycles_per_frame(nes: &mut Nes) -> f64 {
+    nes.run_frame();
+    let start = nes.bus.cycles;
+    for _ in 0..10 {
+        nes.run_frame();
+    }
+    (nes.bus.cycles - start) as f64 / 10.0
+}
+
+#[test]
+fn region_frame_lengths() {
+    // 341 x 312 dots at 3.2 then 3 per cycle.
+    for (region, expected) in [(Region::Pal, 33247.5), (Region::Dendy, 35464.0)] {
+        let mut nes = nmi_counter();
+        nes.set_region(region);
+        let cycles = cycles_per_frame(&mut nes);
+        assert!((cycles - expected).abs() < 2.0, "{region:?} {cycles}");
+        assert_eq!(nes.bus.ram[0x10], 11);
+    }
+}
+
+#[test]
+fn region_from_header() {
+    let mut rom = vec![b'N', b'E', b'S', 0x1A, 1, 0, 0x00, 0x00, 0x00, 0x01];
+    rom.resize(16 + 0x4000, 0x00);
+    let mut nes = Nes::new();
+    nes.load(&rom).unwrap();
+    assert_eq!(nes.region(), Region::Pal);
+    assert_eq!(
+        nes.bus.scheduler.dots_per_cycle,
+        scheduler::PAL_DOTS_PER_CYCLE
+    );
+
+    // NES 2.0 timing: 3 is Dendy, 2 (multi-region) runs as NTSC.
+    rom[7] = 0x08;
+    rom[9] = 0x00;
+    for (timing, region) in [(3, Region::Dendy), (2, Region::Ntsc), (1, Region::Pal)] {
+        rom[12] = timing;
+        nes.load(&rom).unwrap();
+        assert_eq!(nes.region(), region);
+    }
+
+    nes.set_region(Region::Dendy);
+    assert_eq!(nes.region(), Region::Dendy);
+    assert_eq!(nes.bus.ppu.region, Region::Dendy);
+}
+
+#[test]
+fn frame_rates() {
+    let mut nes = nes();
+    for (region, expected) in [
+        (Region::Ntsc, 60.0988),
+        (Region::Pal, 50.0070),
+        (Region::Dendy, 50.0070),
+    ] {
+        nes.set_region(region);
+        assert!((nes.frame_rate() - expected).abs() < 0.001, "{region:?}");
+    }
+}
+
+#[test]
+fn region_change_keeps_samples() {
+    let mut nes = nes();
+    nes.bus.clock(200);
+    nes.set_region(Region::Pal);
+    assert_eq!(nes.audio_samples().len(), 4);
+    // PAL's slower clock takes fewer cycles per sample.
+    nes.bus.clock(190);
+    nes.bus.clock(190);
+    assert_eq!(nes.audio_samples().len(), 11);
+}
+
+#[test]
+fn apu_tables() {
+    assert_eq!(apu::dmc_rates(Region::Pal)[0], 398);
+    for region in [Region::Ntsc, Region::Dendy] {
+        assert_eq!(apu::dmc_rates(region)[0], 428);
+    }
+}
+
+#[test]
+fn pal_dot_ratio() {
+    let mut scheduler = Scheduler::new(scheduler::PAL_DOTS_PER_CYCLE);
+    let dots: Vec<u8> = (0..10).map(|_| scheduler.dots()).collect();
+    assert_eq!(dots, [3, 3, 3, 3, 4, 3, 3, 3, 3, 4]);
+    let mut scheduler = Scheduler::default();
+    assert!((0..10).all(|_| scheduler.dots() == 3));
+}
+
+#[test]
+fn rgb_frame() {
+    let mut nes = nes();
+    nes.bus.ppu.framebuffer.fill(0x30);
+    let rgba = nes.frame_rgba();
+    assert_eq!(rgba.len(), ppu::WIDTH * ppu::HEIGHT * 4);
+    assert!(rgba.iter().all(|&byte| byte == 0xFF));
+    assert_eq!(nes.frame_rgb24().len(), ppu::WIDTH * ppu::HEIGHT * 3);
+
+    let pal: Vec<u8> = (0..64).flat_map(|i| [i, 0, 0]).collect();
+    nes.set_palette(&pal).unwrap();
+    assert_eq!(nes.frame_rgb24()[..3], [0x30, 0, 0]);
+    assert!(nes.set_palette(&pal[..3]).is_err());
+    nes.reset_palette();
+    assert_eq!(nes.frame_rgb24()[..3], [0xFF, 0xFF, 0xFF]);
+}
+
+#[test]
+fn ntsc_frame() {
+    let mut nes = nes();
+    nes.bus.ppu.framebuffer.fill(0x30);
+    let frame = nes.frame_ntsc_rgba();
+    assert_eq!(frame.len(), ppu::NTSC_WIDTH * ppu::HEIGHT * 4);
+    assert_eq!(frame[ppu::NTSC_WIDTH * 2..][..4], [0xFF; 4]);
+
+    nes.set_ntsc(0.5, -1.0, 0.0, 0.0);
+    assert_eq!(nes.ntsc.settings.sharpness, 0.5);
+    assert_eq!(nes.ntsc.settings.saturation, -1.0);
+}